@@ -0,0 +1,295 @@
+//! Content addressed block storage.
+//!
+//! `put` derives the CID from the bytes and `get` checks the bytes against
+//! the CID, so a corrupted or substituted block is never handed out.
+//! Blocks have a pin count; `gc` removes the ones nobody pins.
+
+use alloc::{collections::BTreeMap, string::ToString, vec::Vec};
+use cid::Cid;
+
+use crate::{cid::CidExt, error::CommonError};
+
+pub trait BlockStore {
+    /// Stored bytes of a block, not verified.
+    fn read(&self, cid: &Cid) -> Result<Option<Vec<u8>>, CommonError>;
+    fn write(&mut self, cid: &Cid, bytes: &[u8]) -> Result<(), CommonError>;
+    fn delete(&mut self, cid: &Cid) -> Result<(), CommonError>;
+    fn cids(&self) -> Result<Vec<Cid>, CommonError>;
+    fn refs(&self, cid: &Cid) -> Result<u64, CommonError>;
+    fn set_refs(&mut self, cid: &Cid, refs: u64) -> Result<(), CommonError>;
+
+    fn has(&self, cid: &Cid) -> Result<bool, CommonError> {
+        Ok(self.read(cid)?.is_some())
+    }
+
+    /// Stores a block under the CID of its bytes with the default hash.
+    fn put(&mut self, codec: u64, bytes: &[u8]) -> Result<Cid, CommonError> {
+        let cid = Cid::create(codec, bytes)?;
+        if !self.has(&cid)? {
+            self.write(&cid, bytes)?;
+        }
+        Ok(cid)
+    }
+
+    /// Stores a block received under a given CID, after checking it.
+    fn put_cid(&mut self, cid: &Cid, bytes: &[u8]) -> Result<(), CommonError> {
+        cid.ensure(bytes, vec![cid.codec()])?;
+        if !self.has(cid)? {
+            self.write(cid, bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Verified bytes of a block.
+    fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, CommonError> {
+        match self.read(cid)? {
+            Some(bytes) => {
+                cid.ensure(&bytes, vec![cid.codec()])?;
+                Ok(Some(bytes))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Adds a reference to a stored block, returns the new count.
+    fn pin(&mut self, cid: &Cid) -> Result<u64, CommonError> {
+        if !self.has(cid)? {
+            return Err(CommonError::BlockNotFound(cid.to_string()));
+        }
+        let refs = self.refs(cid)? + 1;
+        self.set_refs(cid, refs)?;
+        Ok(refs)
+    }
+
+    /// Drops a reference to a block, returns the new count.
+    fn unpin(&mut self, cid: &Cid) -> Result<u64, CommonError> {
+        let refs = self.refs(cid)?.saturating_sub(1);
+        self.set_refs(cid, refs)?;
+        Ok(refs)
+    }
+
+    /// Removes every block without references.
+    fn gc(&mut self) -> Result<Vec<Cid>, CommonError> {
+        let mut removed = vec![];
+        for cid in self.cids()? {
+            if self.refs(&cid)? == 0 {
+                self.delete(&cid)?;
+                removed.push(cid);
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MemoryBlockStore {
+    blocks: BTreeMap<Cid, Vec<u8>>,
+    refs: BTreeMap<Cid, u64>,
+}
+
+impl MemoryBlockStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BlockStore for MemoryBlockStore {
+    fn read(&self, cid: &Cid) -> Result<Option<Vec<u8>>, CommonError> {
+        Ok(self.blocks.get(cid).cloned())
+    }
+
+    fn write(&mut self, cid: &Cid, bytes: &[u8]) -> Result<(), CommonError> {
+        self.blocks.insert(*cid, bytes.to_vec());
+        Ok(())
+    }
+
+    fn delete(&mut self, cid: &Cid) -> Result<(), CommonError> {
+        self.blocks.remove(cid);
+        self.refs.remove(cid);
+        Ok(())
+    }
+
+    fn cids(&self) -> Result<Vec<Cid>, CommonError> {
+        Ok(self.blocks.keys().copied().collect())
+    }
+
+    fn refs(&self, cid: &Cid) -> Result<u64, CommonError> {
+        Ok(self.refs.get(cid).copied().unwrap_or(0))
+    }
+
+    fn set_refs(&mut self, cid: &Cid, refs: u64) -> Result<(), CommonError> {
+        if refs == 0 {
+            self.refs.remove(cid);
+        } else {
+            self.refs.insert(*cid, refs);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+pub use fs::FsBlockStore;
+
+#[cfg(any(test, feature = "std"))]
+mod fs {
+    use alloc::{format, string::ToString, vec::Vec};
+    use cid::Cid;
+    use core::str::FromStr;
+    use std::{
+        fs, io,
+        path::{Path, PathBuf},
+    };
+
+    use super::BlockStore;
+    use crate::error::CommonError;
+
+    /// Blocks as files named by their CID, pin counts next to them.
+    ///
+    /// Files are written to a temporary name and renamed, so a crash never
+    /// leaves a partial block under a CID.
+    #[derive(Debug, Clone)]
+    pub struct FsBlockStore {
+        blocks: PathBuf,
+        pins: PathBuf,
+    }
+
+    fn storage_error(e: io::Error) -> CommonError {
+        CommonError::StorageError(e.to_string())
+    }
+
+    fn read_file(path: &Path) -> Result<Option<Vec<u8>>, CommonError> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    fn write_file(path: &Path, bytes: &[u8]) -> Result<(), CommonError> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, bytes).map_err(storage_error)?;
+        fs::rename(&tmp, path).map_err(storage_error)
+    }
+
+    fn remove_file(path: &Path) -> Result<(), CommonError> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(storage_error(e)),
+            _ => Ok(()),
+        }
+    }
+
+    impl FsBlockStore {
+        pub fn open(root: impl AsRef<Path>) -> Result<Self, CommonError> {
+            let blocks = root.as_ref().join("blocks");
+            let pins = root.as_ref().join("pins");
+            fs::create_dir_all(&blocks).map_err(storage_error)?;
+            fs::create_dir_all(&pins).map_err(storage_error)?;
+            Ok(Self { blocks, pins })
+        }
+    }
+
+    impl BlockStore for FsBlockStore {
+        fn read(&self, cid: &Cid) -> Result<Option<Vec<u8>>, CommonError> {
+            read_file(&self.blocks.join(cid.to_string()))
+        }
+
+        fn write(&mut self, cid: &Cid, bytes: &[u8]) -> Result<(), CommonError> {
+            write_file(&self.blocks.join(cid.to_string()), bytes)
+        }
+
+        fn delete(&mut self, cid: &Cid) -> Result<(), CommonError> {
+            remove_file(&self.pins.join(cid.to_string()))?;
+            remove_file(&self.blocks.join(cid.to_string()))
+        }
+
+        fn cids(&self) -> Result<Vec<Cid>, CommonError> {
+            let mut cids = vec![];
+            for entry in fs::read_dir(&self.blocks).map_err(storage_error)? {
+                let name = entry.map_err(storage_error)?.file_name();
+                // Leftover temporary files don't parse as CIDs
+                if let Some(cid) = name.to_str().and_then(|n| Cid::from_str(n).ok()) {
+                    cids.push(cid);
+                }
+            }
+            Ok(cids)
+        }
+
+        fn refs(&self, cid: &Cid) -> Result<u64, CommonError> {
+            match read_file(&self.pins.join(cid.to_string()))? {
+                Some(bytes) => core::str::from_utf8(&bytes)
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or_else(|| CommonError::StorageError(format!("Invalid pin count: {cid}"))),
+                None => Ok(0),
+            }
+        }
+
+        fn set_refs(&mut self, cid: &Cid, refs: u64) -> Result<(), CommonError> {
+            let path = self.pins.join(cid.to_string());
+            if refs == 0 {
+                return remove_file(&path);
+            }
+            write_file(&path, refs.to_string().as_bytes())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CBOR_CODE;
+
+    fn block_store_test(store: &mut impl BlockStore) {
+        let cid = store.put(CBOR_CODE, b"receipt").unwrap();
+        assert_eq!(cid, Cid::create(CBOR_CODE, b"receipt").unwrap());
+        assert_eq!(store.get(&cid).unwrap().unwrap(), b"receipt");
+        assert_eq!(store.put(CBOR_CODE, b"receipt").unwrap(), cid);
+
+        // Blocks received under a CID must match it
+        let other = Cid::create(CBOR_CODE, b"other").unwrap();
+        assert!(store.put_cid(&other, b"forged").is_err());
+        store.put_cid(&other, b"other").unwrap();
+
+        // Corrupted storage is detected on read
+        store.write(&other, b"corrupted").unwrap();
+        assert!(matches!(
+            store.get(&other),
+            Err(CommonError::PayloadHashMismatch)
+        ));
+
+        assert_eq!(store.pin(&cid).unwrap(), 1);
+        assert_eq!(store.pin(&cid).unwrap(), 2);
+        assert_eq!(store.unpin(&cid).unwrap(), 1);
+        assert_eq!(store.gc().unwrap(), vec![other]);
+        assert!(!store.has(&other).unwrap());
+        assert!(store.has(&cid).unwrap());
+
+        assert_eq!(store.unpin(&cid).unwrap(), 0);
+        assert_eq!(store.gc().unwrap(), vec![cid]);
+        assert!(store.get(&cid).unwrap().is_none());
+        assert!(matches!(
+            store.pin(&cid),
+            Err(CommonError::BlockNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn memory_block_store_test() {
+        block_store_test(&mut MemoryBlockStore::new());
+    }
+
+    #[test]
+    fn fs_block_store_test() {
+        let root = std::env::temp_dir().join(format!("idp2p-blocks-{}", std::process::id()));
+        let mut store = FsBlockStore::open(&root).unwrap();
+        block_store_test(&mut store);
+
+        // Pins survive reopening
+        let cid = store.put(CBOR_CODE, b"persisted").unwrap();
+        store.pin(&cid).unwrap();
+        let reopened = FsBlockStore::open(&root).unwrap();
+        assert_eq!(reopened.refs(&cid).unwrap(), 1);
+        assert_eq!(reopened.get(&cid).unwrap().unwrap(), b"persisted");
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,190 @@
+//! CARv1 archives.
+//!
+//! An archive is a varint length prefixed DAG-CBOR header
+//! `{"roots": [links], "version": 1}` followed by sections of
+//! `varint(len) || CID bytes || block bytes`. Every block is checked against
+//! its CID when an archive is read.
+
+use alloc::{string::ToString, vec::Vec};
+use ciborium::Value;
+use cid::Cid;
+
+use crate::{
+    cbor::{canonical_value, write},
+    cid::CidExt,
+    dagcbor::{link, link_cid},
+    error::CommonError,
+    multikey::{read_varint, write_varint},
+};
+
+pub const CAR_VERSION: u64 = 1;
+
+fn invalid(reason: &str) -> CommonError {
+    CommonError::InvalidCar(reason.to_string())
+}
+
+fn read_varint_len(bytes: &[u8]) -> Result<(usize, &[u8]), CommonError> {
+    let (len, rest) = read_varint(bytes).map_err(|_| invalid("Invalid length"))?;
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= rest.len())
+        .ok_or_else(|| invalid("Unexpected end of archive"))?;
+    Ok((len, rest))
+}
+
+/// Length of the CID at the start of a section.
+fn cid_len(section: &[u8]) -> Result<usize, CommonError> {
+    // CIDv0 is a bare SHA-256 multihash
+    if section.starts_with(&[0x12, 0x20]) {
+        return Ok(34);
+    }
+    let mut rest = section;
+    // Version, codec, hash code
+    for _ in 0..3 {
+        rest = read_varint(rest).map_err(|_| invalid("Invalid CID"))?.1;
+    }
+    let (size, rest) = read_varint_len(rest)?;
+    Ok(section.len() - rest.len() + size)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Car {
+    pub roots: Vec<Cid>,
+    pub blocks: Vec<(Cid, Vec<u8>)>,
+}
+
+impl Car {
+    pub fn new(roots: Vec<Cid>) -> Self {
+        Self {
+            roots,
+            blocks: vec![],
+        }
+    }
+
+    /// Adds a block under the CID of its bytes.
+    pub fn put(&mut self, codec: u64, bytes: &[u8]) -> Result<Cid, CommonError> {
+        let cid = Cid::create(codec, bytes)?;
+        if !self.blocks.iter().any(|(c, _)| *c == cid) {
+            self.blocks.push((cid, bytes.to_vec()));
+        }
+        Ok(cid)
+    }
+
+    pub fn get(&self, cid: &Cid) -> Option<&[u8]> {
+        self.blocks
+            .iter()
+            .find(|(c, _)| c == cid)
+            .map(|(_, bytes)| bytes.as_slice())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CommonError> {
+        let header = Value::Map(vec![
+            (
+                Value::Text("roots".into()),
+                Value::Array(self.roots.iter().map(link).collect()),
+            ),
+            (
+                Value::Text("version".into()),
+                Value::Integer(CAR_VERSION.into()),
+            ),
+        ]);
+        let header = write(&header)?;
+        let mut bytes = vec![];
+        write_varint(header.len() as u64, &mut bytes);
+        bytes.extend_from_slice(&header);
+        for (cid, block) in &self.blocks {
+            let cid = cid.to_bytes();
+            write_varint((cid.len() + block.len()) as u64, &mut bytes);
+            bytes.extend_from_slice(&cid);
+            bytes.extend_from_slice(block);
+        }
+        Ok(bytes)
+    }
+
+    /// Reads an archive, every block must match its CID.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CommonError> {
+        let (len, rest) = read_varint_len(bytes)?;
+        let (header, mut rest) = rest.split_at(len);
+        let Value::Map(entries) = canonical_value(header)? else {
+            return Err(invalid("Invalid header"));
+        };
+        let mut roots = None;
+        let mut version = None;
+        for (key, value) in entries {
+            match (key.as_text(), value) {
+                (Some("roots"), Value::Array(links)) => {
+                    roots = Some(
+                        links
+                            .into_iter()
+                            .map(link_cid)
+                            .collect::<Result<Vec<_>, _>>()?,
+                    );
+                }
+                (Some("version"), Value::Integer(v)) => version = u64::try_from(v).ok(),
+                _ => return Err(invalid("Invalid header")),
+            }
+        }
+        if version != Some(CAR_VERSION) {
+            return Err(invalid("Unsupported version"));
+        }
+        let mut car = Self::new(roots.ok_or_else(|| invalid("Missing roots"))?);
+        while !rest.is_empty() {
+            let (len, section) = read_varint_len(rest)?;
+            let (section, next) = section.split_at(len);
+            let cid_len = cid_len(section)?;
+            if cid_len > section.len() {
+                return Err(invalid("Unexpected end of archive"));
+            }
+            let (cid, block) = section.split_at(cid_len);
+            let cid = Cid::try_from(cid).map_err(|_| invalid("Invalid CID"))?;
+            cid.ensure(block, vec![cid.codec()])?;
+            car.blocks.push((cid, block.to_vec()));
+            rest = next;
+        }
+        Ok(car)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CBOR_CODE, DAG_CBOR_CODE};
+
+    fn car() -> Car {
+        let mut car = Car::new(vec![]);
+        car.put(CBOR_CODE, b"first").unwrap();
+        let root = car.put(DAG_CBOR_CODE, b"second").unwrap();
+        car.roots.push(root);
+        car
+    }
+
+    #[test]
+    fn roundtrip_test() {
+        let car = car();
+        let bytes = car.to_bytes().unwrap();
+        // Header length, then {"roots": [...], "version": 1}
+        assert_eq!(&bytes[1..8], &[0xa2, 0x65, b'r', b'o', b'o', b't', b's']);
+        let decoded = Car::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, car);
+        assert_eq!(decoded.get(&car.roots[0]).unwrap(), b"second");
+    }
+
+    #[test]
+    fn corrupted_block_test() {
+        let mut bytes = car().to_bytes().unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(matches!(
+            Car::from_bytes(&bytes),
+            Err(CommonError::PayloadHashMismatch)
+        ));
+    }
+
+    #[test]
+    fn truncated_archive_test() {
+        let bytes = car().to_bytes().unwrap();
+        for len in [0, 1, 10, bytes.len() - 1] {
+            assert!(Car::from_bytes(&bytes[..len]).is_err());
+        }
+    }
+}
@@ -1,15 +1,332 @@
-use alloc::{string::ToString, vec::Vec};
+use alloc::{boxed::Box, string::ToString, vec::Vec};
+use ciborium::Value;
 
 use crate::error::CommonError;
 
-pub fn encode<T: serde::Serialize>(value: &T) -> Vec<u8> {
-    let mut bytes = Vec::new(); 
-    ciborium::ser::into_writer(&value, &mut bytes).expect("Failed to serialize");
-    bytes
+/// Limits applied when decoding untrusted CBOR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Maximum input size in bytes
+    pub max_size: usize,
+    /// Maximum nesting of arrays, maps and tags
+    pub max_depth: usize,
+    /// Maximum number of entries in a single array or map
+    pub max_items: u64,
 }
 
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_size: 1024 * 1024,
+            max_depth: 32,
+            max_items: 65_536,
+        }
+    }
+}
+
+/// Encodes a value in RFC 8949 core deterministic form.
+///
+/// Integers, lengths and floats use their shortest form and map entries are
+/// sorted by the bytes of their encoded keys.
+pub fn encode<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, CommonError> {
+    let value = Value::serialized(value).map_err(|_| CommonError::EncodeError)?;
+    write(&canonicalize(value)?)
+}
+
+/// Decodes a value within the default limits.
 pub fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, CommonError> {
+    decode_with_limits(bytes, &DecodeLimits::default())
+}
+
+/// Decodes a value after checking `bytes` against `limits`.
+///
+/// The input is scanned without allocating before it is deserialized, so
+/// oversized or deeply nested input is rejected up front.
+pub fn decode_with_limits<T: serde::de::DeserializeOwned>(
+    bytes: &[u8],
+    limits: &DecodeLimits,
+) -> Result<T, CommonError> {
+    check_limits(bytes, limits)?;
     let value: T =
         ciborium::de::from_reader(bytes).map_err(|e| CommonError::DecodeError(e.to_string()))?;
     Ok(value)
 }
+
+/// Decodes a value only if `bytes` is its deterministic encoding.
+///
+/// Use it for content-addressed payloads so a CID identifies exactly one
+/// encoding of a value.
+pub fn decode_canonical<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, CommonError> {
+    canonical_value(bytes)?;
+    decode(bytes)
+}
+
+/// Parses `bytes` within the default limits if it is a deterministic encoding.
+pub(crate) fn canonical_value(bytes: &[u8]) -> Result<Value, CommonError> {
+    check_limits(bytes, &DecodeLimits::default())?;
+    let value: Value =
+        ciborium::de::from_reader(bytes).map_err(|e| CommonError::DecodeError(e.to_string()))?;
+    let value = canonicalize(value)?;
+    if write(&value)? != bytes {
+        return Err(CommonError::NonCanonicalEncoding);
+    }
+    Ok(value)
+}
+
+/// An open array, map or tag while scanning.
+struct Frame {
+    /// Items left, `None` for indefinite length
+    remaining: Option<u64>,
+    seen: u64,
+    limit: u64,
+}
+
+fn check_limits(bytes: &[u8], limits: &DecodeLimits) -> Result<(), CommonError> {
+    if bytes.len() > limits.max_size {
+        return Err(CommonError::PayloadTooLarge(bytes.len()));
+    }
+    if scan(bytes, limits)? != bytes.len() {
+        return Err(CommonError::DecodeError("Trailing bytes".to_string()));
+    }
+    Ok(())
+}
+
+/// Length of the CBOR item at the start of a stream, e.g. an event body
+/// followed by its attachments.
+pub fn item_len(bytes: &[u8]) -> Result<usize, CommonError> {
+    let limits = DecodeLimits::default();
+    scan(&bytes[..bytes.len().min(limits.max_size)], &limits)
+}
+
+/// Scans one item within `limits`, returns the position after it.
+fn scan(bytes: &[u8], limits: &DecodeLimits) -> Result<usize, CommonError> {
+    let invalid = |reason: &str| CommonError::DecodeError(reason.to_string());
+    let mut pos = 0usize;
+    let mut stack: Vec<Frame> = Vec::new();
+    loop {
+        let initial = *bytes.get(pos).ok_or_else(|| invalid("Unexpected end of input"))?;
+        pos += 1;
+        if initial == 0xff {
+            match stack.pop() {
+                Some(Frame { remaining: None, .. }) => {}
+                _ => return Err(invalid("Unexpected break")),
+            }
+        } else {
+            let major = initial >> 5;
+            let info = initial & 0x1f;
+            let arg = match info {
+                0..=23 => Some(info as u64),
+                24..=27 => {
+                    let size = 1usize << (info - 24);
+                    let arg = bytes
+                        .get(pos..pos + size)
+                        .ok_or_else(|| invalid("Unexpected end of input"))?;
+                    pos += size;
+                    Some(arg.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+                }
+                31 if (2..=5).contains(&major) => None,
+                _ => return Err(invalid("Invalid additional information")),
+            };
+            if let Some(frame) = stack.last_mut() {
+                frame.seen += 1;
+                if frame.seen > frame.limit {
+                    return Err(CommonError::CollectionTooLarge(frame.seen));
+                }
+                if let Some(remaining) = frame.remaining.as_mut() {
+                    *remaining -= 1;
+                }
+            }
+            let child = match (major, arg) {
+                (2 | 3, Some(len)) => {
+                    let len = usize::try_from(len)
+                        .ok()
+                        .filter(|len| *len <= bytes.len() - pos)
+                        .ok_or_else(|| invalid("Unexpected end of input"))?;
+                    pos += len;
+                    None
+                }
+                (4 | 5, Some(len)) => {
+                    if len > limits.max_items {
+                        return Err(CommonError::CollectionTooLarge(len));
+                    }
+                    let items = if major == 5 { len * 2 } else { len };
+                    if items > (bytes.len() - pos) as u64 {
+                        return Err(invalid("Unexpected end of input"));
+                    }
+                    Some(Some(items))
+                }
+                (2..=5, None) => Some(None),
+                (6, _) => Some(Some(1)),
+                _ => None,
+            };
+            if let Some(remaining) = child {
+                if stack.len() >= limits.max_depth {
+                    return Err(CommonError::NestingTooDeep(limits.max_depth));
+                }
+                let limit = if major == 5 { limits.max_items * 2 } else { limits.max_items };
+                stack.push(Frame { remaining, seen: 0, limit });
+            }
+        }
+        while matches!(stack.last(), Some(Frame { remaining: Some(0), .. })) {
+            stack.pop();
+        }
+        if stack.is_empty() {
+            break;
+        }
+    }
+    Ok(pos)
+}
+
+pub(crate) fn write(value: &Value) -> Result<Vec<u8>, CommonError> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(value, &mut bytes).map_err(|_| CommonError::EncodeError)?;
+    Ok(bytes)
+}
+
+pub(crate) fn canonicalize(value: Value) -> Result<Value, CommonError> {
+    Ok(match value {
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(canonicalize)
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Map(entries) => {
+            let mut entries = entries
+                .into_iter()
+                .map(|(k, v)| {
+                    let k = canonicalize(k)?;
+                    Ok((write(&k)?, k, canonicalize(v)?))
+                })
+                .collect::<Result<Vec<_>, CommonError>>()?;
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            if entries.windows(2).any(|w| w[0].0 == w[1].0) {
+                return Err(CommonError::NonCanonicalEncoding);
+            }
+            Value::Map(entries.into_iter().map(|(_, k, v)| (k, v)).collect())
+        }
+        Value::Tag(tag, inner) => Value::Tag(tag, Box::new(canonicalize(*inner)?)),
+        other => other,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        zeta: u64,
+        a: alloc::string::String,
+        ratio: f64,
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            zeta: 500,
+            a: "x".into(),
+            ratio: 1.5,
+        }
+    }
+
+    #[test]
+    fn encode_sorts_keys_test() {
+        let bytes = encode(&sample()).unwrap();
+        // map(3), "a" comes first, then "zeta", then "ratio" (longer keys sort later)
+        assert_eq!(&bytes[..3], &[0xa3, 0x61, b'a']);
+        let mut map = BTreeMap::new();
+        map.insert("bb", 1);
+        map.insert("c", 2);
+        assert_eq!(encode(&map).unwrap(), vec![0xa2, 0x61, b'c', 0x02, 0x62, b'b', b'b', 0x01]);
+    }
+
+    #[test]
+    fn decode_canonical_test() {
+        let bytes = encode(&sample()).unwrap();
+        let decoded: Sample = decode_canonical(&bytes).unwrap();
+        assert_eq!(decoded, sample());
+    }
+
+    #[test]
+    fn decode_canonical_rejects_field_order_test() {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&sample(), &mut bytes).unwrap();
+        assert!(decode::<Sample>(&bytes).is_ok());
+        assert!(matches!(
+            decode_canonical::<Sample>(&bytes),
+            Err(CommonError::NonCanonicalEncoding)
+        ));
+    }
+
+    #[test]
+    fn decode_canonical_rejects_long_forms_test() {
+        // 1 encoded with a one byte argument
+        assert!(decode_canonical::<u64>(&[0x18, 0x01]).is_err());
+        // indefinite length array [1]
+        assert!(decode_canonical::<Vec<u64>>(&[0x9f, 0x01, 0xff]).is_err());
+        // trailing bytes
+        assert!(decode_canonical::<u64>(&[0x01, 0x01]).is_err());
+        // duplicate keys {"a": 1, "a": 1}
+        let dup = [0xa2, 0x61, b'a', 0x01, 0x61, b'a', 0x01];
+        assert!(decode_canonical::<BTreeMap<alloc::string::String, u64>>(&dup).is_err());
+        assert_eq!(decode_canonical::<u64>(&[0x01]).unwrap(), 1);
+    }
+
+    #[test]
+    fn decode_limits_test() {
+        let limits = DecodeLimits {
+            max_size: 64,
+            max_depth: 3,
+            max_items: 4,
+        };
+        assert!(decode_with_limits::<Vec<u64>>(&[0x84, 1, 2, 3, 4], &limits).is_ok());
+        assert!(matches!(
+            decode_with_limits::<Vec<u64>>(&[0x85, 1, 2, 3, 4, 5], &limits),
+            Err(CommonError::CollectionTooLarge(5))
+        ));
+        // [[[[]]]]
+        assert!(matches!(
+            decode_with_limits::<Vec<Vec<Vec<Vec<u64>>>>>(&[0x81, 0x81, 0x81, 0x80], &limits),
+            Err(CommonError::NestingTooDeep(3))
+        ));
+        assert!(matches!(
+            decode_with_limits::<Vec<u8>>(&[0u8; 65], &limits),
+            Err(CommonError::PayloadTooLarge(65))
+        ));
+        // indefinite array [1, 2, 3, 4, 5]
+        assert!(matches!(
+            decode_with_limits::<Vec<u64>>(&[0x9f, 1, 2, 3, 4, 5, 0xff], &limits),
+            Err(CommonError::CollectionTooLarge(5))
+        ));
+    }
+
+    #[test]
+    fn decode_rejects_lying_lengths_test() {
+        // array claiming 2^32 - 1 items
+        assert!(matches!(
+            decode::<Vec<u64>>(&[0x9a, 0xff, 0xff, 0xff, 0xff]),
+            Err(CommonError::CollectionTooLarge(_))
+        ));
+        // array claiming 1000 items with none present
+        assert!(matches!(
+            decode::<Vec<u64>>(&[0x99, 0x03, 0xe8]),
+            Err(CommonError::DecodeError(_))
+        ));
+        // byte string claiming 2^64 - 1 bytes
+        let huge = [0x5b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        assert!(matches!(decode::<Vec<u8>>(&huge), Err(CommonError::DecodeError(_))));
+        // unbalanced break
+        assert!(decode::<u64>(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn decode_deep_nesting_test() {
+        let deep = vec![0x81u8; 10_000];
+        assert!(matches!(
+            decode::<Value>(&deep),
+            Err(CommonError::NestingTooDeep(_))
+        ));
+    }
+}
@@ -0,0 +1,662 @@
+//! CESR primitives in the text (qb64) and binary (qb2) domains.
+//!
+//! A primitive is a derivation code followed by its raw bytes in base64url,
+//! lead padded so the text form is a whole number of quadlets. The binary
+//! form is the base64 decoding of the text form. Counters frame groups of
+//! primitives, e.g. the indexed signatures attached to an event body.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+
+use crate::{
+    BLAKE3_CODE, ED_CODE, P256_CODE, SECP256K1_CODE, SHA2_256_CODE, SHA3_256_CODE, X25519_CODE,
+    error::CommonError,
+};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Largest count of a two character counter.
+pub const MAX_COUNT: u16 = 4095;
+
+fn invalid(reason: &str) -> CommonError {
+    CommonError::InvalidCesr(reason.to_string())
+}
+
+fn end_of_stream() -> CommonError {
+    invalid("Unexpected end of stream")
+}
+
+fn b64_index(c: u8) -> Result<u8, CommonError> {
+    ALPHABET
+        .iter()
+        .position(|a| *a == c)
+        .map(|i| i as u8)
+        .ok_or_else(|| invalid("Invalid base64 character"))
+}
+
+/// Lead bytes that make raw bytes a whole number of triplets.
+fn pad_size(raw_size: usize) -> usize {
+    (3 - raw_size % 3) % 3
+}
+
+fn full_size(code_size: usize, raw_size: usize) -> usize {
+    let ps = pad_size(raw_size);
+    code_size + (ps + raw_size) / 3 * 4 - ps
+}
+
+/// Encodes lead padded raw bytes, the code takes the place of the pad.
+fn encode_text(code: &str, raw: &[u8]) -> String {
+    let ps = pad_size(raw.len());
+    let mut padded = vec![0u8; ps];
+    padded.extend_from_slice(raw);
+    let mut text = code.to_string();
+    text.push_str(&URL_SAFE_NO_PAD.encode(padded)[ps..]);
+    text
+}
+
+fn decode_text(text: &str, code_size: usize, raw_size: usize) -> Result<Vec<u8>, CommonError> {
+    let ps = pad_size(raw_size);
+    let mut b64 = "A".repeat(ps);
+    b64.push_str(&text[code_size..]);
+    let bytes = URL_SAFE_NO_PAD
+        .decode(b64)
+        .map_err(|_| invalid("Invalid base64"))?;
+    let (pad, raw) = bytes.split_at(ps);
+    if pad.iter().any(|b| *b != 0) {
+        return Err(invalid("Non-zero pad bits"));
+    }
+    Ok(raw.to_vec())
+}
+
+/// Checks the size of a text primitive before it is sliced.
+fn check_text(text: &str, size: usize) -> Result<(), CommonError> {
+    if !text.is_ascii() || text.len() != size {
+        return Err(invalid("Invalid primitive size"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Domain {
+    /// Base64url characters
+    Text,
+    /// Bytes
+    Binary,
+}
+
+impl Domain {
+    /// Domain of a stream starting with a counter, from its first three
+    /// bits. `None` for anything else, e.g. a CBOR body.
+    pub fn sniff(stream: &[u8]) -> Option<Self> {
+        match stream.first()? >> 5 {
+            0b001 => Some(Self::Text),
+            0b111 => Some(Self::Binary),
+            _ => None,
+        }
+    }
+}
+
+/// A primitive or counter readable from and writable to both domains.
+pub trait Primitive: Sized {
+    /// Text size of the item starting with `head`, the first four
+    /// characters of the stream.
+    fn text_size(head: &str) -> Result<usize, CommonError>;
+    fn from_qb64(text: &str) -> Result<Self, CommonError>;
+    fn qb64(&self) -> String;
+
+    fn qb2(&self) -> Vec<u8> {
+        URL_SAFE_NO_PAD
+            .decode(self.qb64())
+            .expect("qb64 is whole quadlets of base64url")
+    }
+
+    fn from_qb2(bytes: &[u8]) -> Result<Self, CommonError> {
+        Self::from_qb64(&URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    fn encode(&self, domain: Domain) -> Vec<u8> {
+        match domain {
+            Domain::Text => self.qb64().into_bytes(),
+            Domain::Binary => self.qb2(),
+        }
+    }
+
+    /// Reads the item at the start of a stream, returns it with the number
+    /// of bytes consumed.
+    fn parse(stream: &[u8], domain: Domain) -> Result<(Self, usize), CommonError> {
+        let head = &stream[..stream.len().min(4)];
+        match domain {
+            Domain::Text => {
+                let head = core::str::from_utf8(head).map_err(|_| invalid("Invalid text"))?;
+                let size = Self::text_size(head)?;
+                let text = stream.get(..size).ok_or_else(end_of_stream)?;
+                let text = core::str::from_utf8(text).map_err(|_| invalid("Invalid text"))?;
+                Ok((Self::from_qb64(text)?, size))
+            }
+            Domain::Binary => {
+                let head = URL_SAFE_NO_PAD.encode(&head[..head.len().min(3)]);
+                let size = Self::text_size(&head)? / 4 * 3;
+                let bytes = stream.get(..size).ok_or_else(end_of_stream)?;
+                Ok((Self::from_qb2(bytes)?, size))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatterCode {
+    /// Ed25519 non-transferable prefix key
+    Ed25519N,
+    X25519,
+    /// Ed25519 key
+    Ed25519,
+    Blake3Digest,
+    Sha3Digest,
+    Sha2Digest,
+    Ed25519Sig,
+    Secp256k1Sig,
+    P256Sig,
+    /// Compressed secp256k1 non-transferable prefix key
+    Secp256k1N,
+    /// Compressed secp256k1 key
+    Secp256k1,
+    /// Compressed P-256 non-transferable prefix key
+    P256N,
+    /// Compressed P-256 key
+    P256,
+}
+
+impl MatterCode {
+    const ALL: [Self; 13] = [
+        Self::Ed25519N,
+        Self::X25519,
+        Self::Ed25519,
+        Self::Blake3Digest,
+        Self::Sha3Digest,
+        Self::Sha2Digest,
+        Self::Ed25519Sig,
+        Self::Secp256k1Sig,
+        Self::P256Sig,
+        Self::Secp256k1N,
+        Self::Secp256k1,
+        Self::P256N,
+        Self::P256,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Ed25519N => "B",
+            Self::X25519 => "C",
+            Self::Ed25519 => "D",
+            Self::Blake3Digest => "E",
+            Self::Sha3Digest => "H",
+            Self::Sha2Digest => "I",
+            Self::Ed25519Sig => "0B",
+            Self::Secp256k1Sig => "0C",
+            Self::P256Sig => "0I",
+            Self::Secp256k1N => "1AAA",
+            Self::Secp256k1 => "1AAB",
+            Self::P256N => "1AAI",
+            Self::P256 => "1AAJ",
+        }
+    }
+
+    pub fn raw_size(&self) -> usize {
+        match self {
+            Self::Ed25519Sig | Self::Secp256k1Sig | Self::P256Sig => 64,
+            Self::Secp256k1N | Self::Secp256k1 | Self::P256N | Self::P256 => 33,
+            _ => 32,
+        }
+    }
+
+    pub fn text_size(&self) -> usize {
+        full_size(self.code().len(), self.raw_size())
+    }
+
+    /// Transferable key code of a public key, secp256k1 keys must be
+    /// compressed ECDSA keys.
+    pub fn from_key(codec: u64, public_key: &[u8]) -> Option<Self> {
+        match codec {
+            ED_CODE => Some(Self::Ed25519),
+            X25519_CODE => Some(Self::X25519),
+            SECP256K1_CODE if public_key.len() == 33 => Some(Self::Secp256k1),
+            P256_CODE => Some(Self::P256),
+            _ => None,
+        }
+    }
+
+    pub fn from_hash(code: u64) -> Option<Self> {
+        match code {
+            BLAKE3_CODE => Some(Self::Blake3Digest),
+            SHA3_256_CODE => Some(Self::Sha3Digest),
+            SHA2_256_CODE => Some(Self::Sha2Digest),
+            _ => None,
+        }
+    }
+
+    /// Code of the leading characters of a text primitive.
+    fn read(head: &str) -> Result<Self, CommonError> {
+        let size = match head.as_bytes().first().ok_or_else(end_of_stream)? {
+            b'A'..=b'Z' | b'a'..=b'z' => 1,
+            b'0' => 2,
+            b'1'..=b'3' => 4,
+            _ => return Err(invalid("Expected a primitive")),
+        };
+        let code = head.get(..size).ok_or_else(end_of_stream)?;
+        Self::ALL
+            .into_iter()
+            .find(|c| c.code() == code)
+            .ok_or_else(|| CommonError::InvalidCesr(format!("Unsupported code: {code}")))
+    }
+}
+
+/// A key, digest or signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matter {
+    pub code: MatterCode,
+    pub raw: Vec<u8>,
+}
+
+impl Matter {
+    pub fn new(code: MatterCode, raw: &[u8]) -> Result<Self, CommonError> {
+        if raw.len() != code.raw_size() {
+            return Err(invalid("Invalid raw size"));
+        }
+        Ok(Self {
+            code,
+            raw: raw.to_vec(),
+        })
+    }
+}
+
+impl Primitive for Matter {
+    fn text_size(head: &str) -> Result<usize, CommonError> {
+        Ok(MatterCode::read(head)?.text_size())
+    }
+
+    fn from_qb64(text: &str) -> Result<Self, CommonError> {
+        let code = MatterCode::read(text)?;
+        check_text(text, code.text_size())?;
+        let raw = decode_text(text, code.code().len(), code.raw_size())?;
+        Ok(Self { code, raw })
+    }
+
+    fn qb64(&self) -> String {
+        encode_text(self.code.code(), &self.raw)
+    }
+}
+
+/// Signature codes of the indexed signature table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexerCode {
+    Ed25519Sig,
+    Secp256k1Sig,
+    P256Sig,
+}
+
+impl IndexerCode {
+    const ALL: [Self; 3] = [Self::Ed25519Sig, Self::Secp256k1Sig, Self::P256Sig];
+    const RAW_SIZE: usize = 64;
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Ed25519Sig => "A",
+            Self::Secp256k1Sig => "C",
+            Self::P256Sig => "E",
+        }
+    }
+
+    /// Signature code of a signer key, secp256k1 keys must be compressed
+    /// ECDSA keys.
+    pub fn from_key(codec: u64, public_key: &[u8]) -> Option<Self> {
+        match codec {
+            ED_CODE => Some(Self::Ed25519Sig),
+            SECP256K1_CODE if public_key.len() == 33 => Some(Self::Secp256k1Sig),
+            P256_CODE => Some(Self::P256Sig),
+            _ => None,
+        }
+    }
+
+    fn read(head: &str) -> Result<Self, CommonError> {
+        let code = head.get(..1).ok_or_else(end_of_stream)?;
+        Self::ALL
+            .into_iter()
+            .find(|c| c.code() == code)
+            .ok_or_else(|| CommonError::InvalidCesr(format!("Unsupported index code: {code}")))
+    }
+}
+
+/// A signature with the index of its key in the signer list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Indexer {
+    pub code: IndexerCode,
+    pub index: u8,
+    pub raw: Vec<u8>,
+}
+
+impl Indexer {
+    pub fn new(code: IndexerCode, index: usize, raw: &[u8]) -> Result<Self, CommonError> {
+        if index >= ALPHABET.len() {
+            return Err(invalid("Index out of range"));
+        }
+        if raw.len() != IndexerCode::RAW_SIZE {
+            return Err(invalid("Invalid raw size"));
+        }
+        Ok(Self {
+            code,
+            index: index as u8,
+            raw: raw.to_vec(),
+        })
+    }
+}
+
+impl Primitive for Indexer {
+    fn text_size(head: &str) -> Result<usize, CommonError> {
+        IndexerCode::read(head)?;
+        Ok(full_size(2, IndexerCode::RAW_SIZE))
+    }
+
+    fn from_qb64(text: &str) -> Result<Self, CommonError> {
+        let code = IndexerCode::read(text)?;
+        check_text(text, full_size(2, IndexerCode::RAW_SIZE))?;
+        let index = b64_index(text.as_bytes()[1])?;
+        let raw = decode_text(text, 2, IndexerCode::RAW_SIZE)?;
+        Ok(Self { code, index, raw })
+    }
+
+    fn qb64(&self) -> String {
+        let code = format!(
+            "{}{}",
+            self.code.code(),
+            ALPHABET[self.index as usize] as char
+        );
+        encode_text(&code, &self.raw)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterCode {
+    /// Indexed signatures of the controller keys
+    ControllerIdxSigs,
+    /// Indexed signatures of the witness keys
+    WitnessIdxSigs,
+    /// Couples of a non-transferable prefix key and its signature
+    NonTransReceiptCouples,
+    /// Attachments, counted in quadlets or triplets
+    AttachmentGroup,
+}
+
+impl CounterCode {
+    const ALL: [Self; 4] = [
+        Self::ControllerIdxSigs,
+        Self::WitnessIdxSigs,
+        Self::NonTransReceiptCouples,
+        Self::AttachmentGroup,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ControllerIdxSigs => "-A",
+            Self::WitnessIdxSigs => "-B",
+            Self::NonTransReceiptCouples => "-C",
+            Self::AttachmentGroup => "-V",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Counter {
+    pub code: CounterCode,
+    pub count: u16,
+}
+
+impl Counter {
+    pub fn new(code: CounterCode, count: usize) -> Result<Self, CommonError> {
+        let count = u16::try_from(count)
+            .ok()
+            .filter(|c| *c <= MAX_COUNT)
+            .ok_or_else(|| invalid("Count out of range"))?;
+        Ok(Self { code, count })
+    }
+}
+
+impl Primitive for Counter {
+    fn text_size(head: &str) -> Result<usize, CommonError> {
+        if !head.starts_with('-') {
+            return Err(invalid("Expected a counter"));
+        }
+        Ok(4)
+    }
+
+    fn from_qb64(text: &str) -> Result<Self, CommonError> {
+        check_text(text, 4)?;
+        let code = CounterCode::ALL
+            .into_iter()
+            .find(|c| c.code() == &text[..2])
+            .ok_or_else(|| {
+                CommonError::InvalidCesr(format!("Unsupported counter: {}", &text[..2]))
+            })?;
+        let bytes = text.as_bytes();
+        let count = (b64_index(bytes[2])? as u16) << 6 | b64_index(bytes[3])? as u16;
+        Ok(Self { code, count })
+    }
+
+    fn qb64(&self) -> String {
+        let hi = ALPHABET[(self.count >> 6) as usize] as char;
+        let lo = ALPHABET[(self.count & 63) as usize] as char;
+        format!("{}{hi}{lo}", self.code.code())
+    }
+}
+
+/// A counted group of attached primitives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Group {
+    ControllerIdxSigs(Vec<Indexer>),
+    WitnessIdxSigs(Vec<Indexer>),
+    /// Prefix key and signature couples
+    NonTransReceiptCouples(Vec<(Matter, Matter)>),
+}
+
+impl Group {
+    pub fn encode(&self, domain: Domain) -> Result<Vec<u8>, CommonError> {
+        let (code, count, items) = match self {
+            Self::ControllerIdxSigs(sigs) | Self::WitnessIdxSigs(sigs) => {
+                let code = match self {
+                    Self::ControllerIdxSigs(_) => CounterCode::ControllerIdxSigs,
+                    _ => CounterCode::WitnessIdxSigs,
+                };
+                let items: Vec<_> = sigs.iter().map(|s| s.encode(domain)).collect();
+                (code, sigs.len(), items)
+            }
+            Self::NonTransReceiptCouples(couples) => {
+                let items = couples
+                    .iter()
+                    .flat_map(|(key, sig)| [key.encode(domain), sig.encode(domain)])
+                    .collect();
+                (CounterCode::NonTransReceiptCouples, couples.len(), items)
+            }
+        };
+        let mut bytes = Counter::new(code, count)?.encode(domain);
+        bytes.extend(items.concat());
+        Ok(bytes)
+    }
+
+    /// Reads a group at the start of a stream, returns it with the number
+    /// of bytes consumed.
+    pub fn parse(stream: &[u8], domain: Domain) -> Result<(Self, usize), CommonError> {
+        let (counter, mut pos) = Counter::parse(stream, domain)?;
+        let next = |pos: &mut usize| -> Result<Indexer, CommonError> {
+            let (sig, len) = Indexer::parse(&stream[*pos..], domain)?;
+            *pos += len;
+            Ok(sig)
+        };
+        let group = match counter.code {
+            CounterCode::ControllerIdxSigs => Self::ControllerIdxSigs(
+                (0..counter.count)
+                    .map(|_| next(&mut pos))
+                    .collect::<Result<_, _>>()?,
+            ),
+            CounterCode::WitnessIdxSigs => Self::WitnessIdxSigs(
+                (0..counter.count)
+                    .map(|_| next(&mut pos))
+                    .collect::<Result<_, _>>()?,
+            ),
+            CounterCode::NonTransReceiptCouples => {
+                let mut couples = vec![];
+                for _ in 0..counter.count {
+                    let (key, len) = Matter::parse(&stream[pos..], domain)?;
+                    pos += len;
+                    let (sig, len) = Matter::parse(&stream[pos..], domain)?;
+                    pos += len;
+                    couples.push((key, sig));
+                }
+                Self::NonTransReceiptCouples(couples)
+            }
+            CounterCode::AttachmentGroup => return Err(invalid("Nested attachment group")),
+        };
+        Ok((group, pos))
+    }
+}
+
+/// Size of a counted unit, a quadlet of text or a triplet of bytes.
+fn unit_size(domain: Domain) -> usize {
+    match domain {
+        Domain::Text => 4,
+        Domain::Binary => 3,
+    }
+}
+
+/// Writes groups as an attachment group, whose count lets a reader skip
+/// attachments it doesn't understand.
+pub fn encode_attachments(groups: &[Group], domain: Domain) -> Result<Vec<u8>, CommonError> {
+    let body = groups
+        .iter()
+        .map(|g| g.encode(domain))
+        .collect::<Result<Vec<_>, _>>()?
+        .concat();
+    let counter = Counter::new(CounterCode::AttachmentGroup, body.len() / unit_size(domain))?;
+    let mut bytes = counter.encode(domain);
+    bytes.extend(body);
+    Ok(bytes)
+}
+
+/// Reads an attachment group in either domain, returns its groups and the
+/// number of bytes consumed.
+pub fn parse_attachments(stream: &[u8]) -> Result<(Vec<Group>, usize), CommonError> {
+    let domain = Domain::sniff(stream).ok_or_else(|| invalid("Expected a counter"))?;
+    let (counter, start) = Counter::parse(stream, domain)?;
+    if counter.code != CounterCode::AttachmentGroup {
+        return Err(invalid("Expected an attachment group"));
+    }
+    let end = start + counter.count as usize * unit_size(domain);
+    let body = stream.get(start..end).ok_or_else(end_of_stream)?;
+    let mut groups = vec![];
+    let mut pos = 0;
+    while pos < body.len() {
+        let (group, len) = Group::parse(&body[pos..], domain)?;
+        groups.push(group);
+        pos += len;
+    }
+    Ok((groups, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matter(code: MatterCode) -> Matter {
+        let raw: Vec<u8> = (0..code.raw_size() as u8).collect();
+        Matter::new(code, &raw).unwrap()
+    }
+
+    fn sig(index: usize) -> Indexer {
+        Indexer::new(IndexerCode::Ed25519Sig, index, &[index as u8; 64]).unwrap()
+    }
+
+    #[test]
+    fn known_encoding_test() {
+        let key = Matter::new(MatterCode::Ed25519N, &[0; 32]).unwrap();
+        assert_eq!(key.qb64(), format!("B{}", "A".repeat(43)));
+        assert_eq!(key.qb2()[0], 0x04);
+        let key = Matter::new(MatterCode::Ed25519, &[0xff; 32]).unwrap();
+        assert_eq!(key.qb64(), format!("DP{}", "_".repeat(42)));
+        let sig = Indexer::new(IndexerCode::Ed25519Sig, 5, &[0; 64]).unwrap();
+        assert_eq!(sig.qb64(), format!("AF{}", "A".repeat(86)));
+        let counter = Counter::new(CounterCode::ControllerIdxSigs, 1).unwrap();
+        assert_eq!(counter.qb64(), "-AAB");
+        assert_eq!(counter.qb2(), [0xf8, 0x00, 0x01]);
+        let counter = Counter::new(CounterCode::AttachmentGroup, MAX_COUNT as usize).unwrap();
+        assert_eq!(counter.qb64(), "-V__");
+        assert!(Counter::new(CounterCode::AttachmentGroup, 4096).is_err());
+    }
+
+    #[test]
+    fn primitive_roundtrip_test() {
+        for code in MatterCode::ALL {
+            let matter = matter(code);
+            let text = matter.qb64();
+            assert_eq!(text.len(), code.text_size());
+            assert_eq!(text.len() % 4, 0);
+            assert_eq!(Matter::from_qb64(&text).unwrap(), matter);
+            assert_eq!(Matter::from_qb2(&matter.qb2()).unwrap(), matter);
+            for domain in [Domain::Text, Domain::Binary] {
+                let mut stream = matter.encode(domain);
+                let len = stream.len();
+                stream.extend(b"-AAB");
+                assert_eq!(
+                    Matter::parse(&stream, domain).unwrap(),
+                    (matter.clone(), len)
+                );
+            }
+        }
+        for index in [0, 1, 63] {
+            let sig = sig(index);
+            assert_eq!(Indexer::from_qb64(&sig.qb64()).unwrap(), sig);
+            assert_eq!(Indexer::from_qb2(&sig.qb2()).unwrap(), sig);
+        }
+        assert!(Indexer::new(IndexerCode::Ed25519Sig, 64, &[0; 64]).is_err());
+    }
+
+    #[test]
+    fn invalid_primitive_test() {
+        let text = matter(MatterCode::Ed25519).qb64();
+        // Pad bits must be zero
+        let mut forged = text.clone().into_bytes();
+        forged[1] = b'_';
+        assert!(Matter::from_qb64(core::str::from_utf8(&forged).unwrap()).is_err());
+        assert!(Matter::from_qb64(&text[..43]).is_err());
+        assert!(Matter::from_qb64(&format!("Z{}", &text[1..])).is_err());
+        assert!(Matter::parse(&text.as_bytes()[..20], Domain::Text).is_err());
+        assert!(Counter::from_qb64("-Z00").is_err());
+    }
+
+    #[test]
+    fn attachments_test() {
+        let groups = vec![
+            Group::ControllerIdxSigs(vec![sig(0), sig(2)]),
+            Group::WitnessIdxSigs(vec![sig(1)]),
+            Group::NonTransReceiptCouples(vec![(
+                matter(MatterCode::Ed25519N),
+                matter(MatterCode::Ed25519Sig),
+            )]),
+        ];
+        for domain in [Domain::Text, Domain::Binary] {
+            let mut stream = encode_attachments(&groups, domain).unwrap();
+            assert_eq!(Domain::sniff(&stream), Some(domain));
+            let len = stream.len();
+            // Next event body
+            stream.push(0xa1);
+            assert_eq!(Domain::sniff(&stream[len..]), None);
+            assert_eq!(parse_attachments(&stream).unwrap(), (groups.clone(), len));
+            assert!(parse_attachments(&stream[..len - 1]).is_err());
+        }
+        let text = encode_attachments(&groups, Domain::Text).unwrap();
+        assert!(text.starts_with(b"-VBm-AAC"));
+        let binary = encode_attachments(&groups, Domain::Binary).unwrap();
+        assert_eq!(URL_SAFE_NO_PAD.encode(binary).as_bytes(), text);
+    }
+}
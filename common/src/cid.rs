@@ -2,23 +2,26 @@ use alloc::vec::Vec;
 use cid::Cid;
 use cid::multihash::Multihash;
 
-use crate::{error::CommonError, utils::sha256_hash, SHA2_256_CODE};
+use crate::{
+    BLAKE3_CODE, SHA2_256_CODE, SHA2_512_CODE, SHA3_256_CODE, error::CommonError, utils::digest,
+};
+
+/// Multihash codes accepted in CIDs.
+pub const HASH_CODES: [u64; 4] = [SHA2_256_CODE, SHA2_512_CODE, SHA3_256_CODE, BLAKE3_CODE];
 
 pub trait CidExt {
     fn ensure(&self, input: &[u8], codecs: Vec<u64>) -> Result<(), CommonError>;
+    /// CID of the input hashed with SHA2-256.
     fn create(code: u64, input: &[u8]) -> Result<Cid, CommonError>;
+    /// CID of the input hashed with `hash_code`, one of `HASH_CODES`.
+    fn create_with(code: u64, hash_code: u64, input: &[u8]) -> Result<Cid, CommonError>;
 }
 
 impl CidExt for Cid {
     fn ensure(&self, input: &[u8], codecs: Vec<u64>) -> Result<(), CommonError> {
-        match self.hash().code() {
-            SHA2_256_CODE => {
-                let input_digest = sha256_hash(input);
-                if self.hash().digest() != input_digest.as_slice() {
-                    return Err(CommonError::PayloadHashMismatch);
-                }
-            }
-            _ => return Err(CommonError::UnsupportedHashAlgorithm(self.hash().code())),
+        let input_digest = digest(self.hash().code(), input)?;
+        if self.hash().digest() != input_digest.as_slice() {
+            return Err(CommonError::PayloadHashMismatch);
         }
         if !codecs.contains(&self.codec()) {
             return Err(CommonError::UnsupportedCodec(self.codec()));
@@ -27,9 +30,48 @@ impl CidExt for Cid {
     }
 
     fn create(code: u64, input: &[u8]) -> Result<Self, CommonError> {
-        let input_digest = sha256_hash(input);
-        let mh = Multihash::<64>::wrap(SHA2_256_CODE, &input_digest)?;
+        Self::create_with(code, SHA2_256_CODE, input)
+    }
+
+    fn create_with(code: u64, hash_code: u64, input: &[u8]) -> Result<Self, CommonError> {
+        let input_digest = digest(hash_code, input)?;
+        let mh = Multihash::<64>::wrap(hash_code, &input_digest)?;
         Ok(Cid::new_v1(code, mh))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CBOR_CODE;
+
+    #[test]
+    fn create_with_all_hashes_test() {
+        for hash_code in HASH_CODES {
+            let cid = Cid::create_with(CBOR_CODE, hash_code, b"payload").unwrap();
+            assert_eq!(cid.hash().code(), hash_code);
+            assert!(cid.ensure(b"payload", vec![CBOR_CODE]).is_ok());
+            assert!(matches!(
+                cid.ensure(b"other", vec![CBOR_CODE]),
+                Err(CommonError::PayloadHashMismatch)
+            ));
+        }
+    }
+
+    #[test]
+    fn unsupported_hash_test() {
+        let mh = Multihash::<64>::wrap(0x14, &[0u8; 32]).unwrap();
+        let cid = Cid::new_v1(CBOR_CODE, mh);
+        assert!(matches!(
+            cid.ensure(b"payload", vec![CBOR_CODE]),
+            Err(CommonError::UnsupportedHashAlgorithm(0x14))
+        ));
+        assert!(Cid::create_with(CBOR_CODE, 0x14, b"payload").is_err());
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn default_hash_test() {
+        let cid = Cid::create(CBOR_CODE, b"payload").unwrap();
+        assert_eq!(cid.hash().code(), SHA2_256_CODE);
+    }
+}
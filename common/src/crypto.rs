@@ -0,0 +1,5 @@
+pub mod agreement;
+pub mod hd;
+pub mod keystore;
+pub mod shamir;
+pub mod signer;
@@ -0,0 +1,305 @@
+//! X25519 key agreement and ChaCha20-Poly1305 boxes.
+//!
+//! Sealed boxes are anonymous, only the recipient key is known. Authenticated
+//! boxes also prove the static key of the sender. Both use a fresh ephemeral
+//! key per message, so the derived key is never reused and the nonce is zero.
+//!
+//! Wire formats, all keys are 32 bytes:
+//! - sealed box v1: `0x01 || ephemeral || ciphertext`
+//! - authenticated box v1: `0x02 || ephemeral || sender || ciphertext`
+
+use alloc::vec::Vec;
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use hkdf::Hkdf;
+use rand_core::CryptoRngCore;
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::error::CommonError;
+
+pub const KEY_SIZE: usize = 32;
+pub const SEALED_BOX_V1: u8 = 0x01;
+pub const AUTH_BOX_V1: u8 = 0x02;
+
+const SEALED_BOX_INFO: &[u8] = b"idp2p/agreement/sealed-box/v1";
+const AUTH_BOX_INFO: &[u8] = b"idp2p/agreement/auth-box/v1";
+
+/// X25519 public key of a secret key.
+pub fn public_key(secret: &[u8; KEY_SIZE]) -> [u8; KEY_SIZE] {
+    PublicKey::from(&StaticSecret::from(*secret)).to_bytes()
+}
+
+/// Generates an X25519 secret key.
+pub fn generate(rng: &mut impl CryptoRngCore) -> Zeroizing<[u8; KEY_SIZE]> {
+    Zeroizing::new(StaticSecret::random_from_rng(rng).to_bytes())
+}
+
+/// X25519 Diffie-Hellman, rejecting low order public keys.
+pub fn shared_secret(
+    secret: &[u8; KEY_SIZE],
+    public: &[u8; KEY_SIZE],
+) -> Result<Zeroizing<[u8; KEY_SIZE]>, CommonError> {
+    let shared = StaticSecret::from(*secret).diffie_hellman(&PublicKey::from(*public));
+    if !shared.was_contributory() {
+        return Err(CommonError::WeakSharedSecret);
+    }
+    Ok(Zeroizing::new(shared.to_bytes()))
+}
+
+/// X25519 public key of an Ed25519 public key.
+pub fn ed25519_public_to_x25519(public: &[u8]) -> Result<[u8; KEY_SIZE], CommonError> {
+    let public: [u8; KEY_SIZE] = public
+        .try_into()
+        .map_err(|_| CommonError::InvalidPublicKey)?;
+    let public = VerifyingKey::from_bytes(&public).map_err(|_| CommonError::InvalidPublicKey)?;
+    Ok(public.to_montgomery().to_bytes())
+}
+
+/// X25519 secret key of an Ed25519 seed.
+pub fn ed25519_secret_to_x25519(seed: &[u8; KEY_SIZE]) -> Zeroizing<[u8; KEY_SIZE]> {
+    Zeroizing::new(SigningKey::from_bytes(seed).to_scalar_bytes())
+}
+
+/// HKDF-SHA256 into `out`.
+pub fn hkdf_sha256(
+    ikm: &[u8],
+    salt: &[u8],
+    info: &[u8],
+    out: &mut [u8],
+) -> Result<(), CommonError> {
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(info, out)
+        .map_err(|_| CommonError::EncryptError)
+}
+
+fn cipher(ikm: &[u8], salt: &[u8], info: &[u8]) -> Result<ChaCha20Poly1305, CommonError> {
+    let mut key = Zeroizing::new([0u8; KEY_SIZE]);
+    hkdf_sha256(ikm, salt, info, key.as_mut())?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key[..])))
+}
+
+fn split_key(bytes: &[u8]) -> Result<([u8; KEY_SIZE], &[u8]), CommonError> {
+    if bytes.len() < KEY_SIZE {
+        return Err(CommonError::InvalidVersionedMessage);
+    }
+    let (key, rest) = bytes.split_at(KEY_SIZE);
+    Ok((key.try_into().expect("key size"), rest))
+}
+
+/// Encrypts to a recipient key without revealing the sender.
+pub fn seal(
+    recipient: &[u8; KEY_SIZE],
+    aad: &[u8],
+    plaintext: &[u8],
+    rng: &mut impl CryptoRngCore,
+) -> Result<Vec<u8>, CommonError> {
+    let ephemeral = generate(rng);
+    let ephemeral_public = public_key(&ephemeral);
+    let shared = shared_secret(&ephemeral, recipient)?;
+    let salt = [ephemeral_public, *recipient].concat();
+    let ciphertext = cipher(shared.as_ref(), &salt, SEALED_BOX_INFO)?
+        .encrypt(
+            &Nonce::default(),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| CommonError::EncryptError)?;
+    let mut sealed = vec![SEALED_BOX_V1];
+    sealed.extend_from_slice(&ephemeral_public);
+    sealed.extend(ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypts a sealed box with the recipient secret key.
+pub fn open(secret: &[u8; KEY_SIZE], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, CommonError> {
+    let Some((&SEALED_BOX_V1, rest)) = sealed.split_first() else {
+        return Err(CommonError::InvalidVersionedMessage);
+    };
+    let (ephemeral_public, ciphertext) = split_key(rest)?;
+    let shared = shared_secret(secret, &ephemeral_public)?;
+    let salt = [ephemeral_public, public_key(secret)].concat();
+    cipher(shared.as_ref(), &salt, SEALED_BOX_INFO)?
+        .decrypt(
+            &Nonce::default(),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| CommonError::DecryptError)
+}
+
+fn auth_box_cipher(
+    ephemeral_dh: &[u8; KEY_SIZE],
+    static_dh: &[u8; KEY_SIZE],
+    ephemeral: &[u8; KEY_SIZE],
+    sender: &[u8; KEY_SIZE],
+    recipient: &[u8; KEY_SIZE],
+) -> Result<ChaCha20Poly1305, CommonError> {
+    let ikm = Zeroizing::new([*ephemeral_dh, *static_dh].concat());
+    let salt = [*ephemeral, *sender, *recipient].concat();
+    cipher(&ikm, &salt, AUTH_BOX_INFO)
+}
+
+/// Encrypts to a recipient key, authenticated by the sender static key.
+pub fn auth_seal(
+    sender_secret: &[u8; KEY_SIZE],
+    recipient: &[u8; KEY_SIZE],
+    aad: &[u8],
+    plaintext: &[u8],
+    rng: &mut impl CryptoRngCore,
+) -> Result<Vec<u8>, CommonError> {
+    let ephemeral = generate(rng);
+    let ephemeral_public = public_key(&ephemeral);
+    let sender = public_key(sender_secret);
+    let ephemeral_dh = shared_secret(&ephemeral, recipient)?;
+    let static_dh = shared_secret(sender_secret, recipient)?;
+    let ciphertext = auth_box_cipher(
+        &ephemeral_dh,
+        &static_dh,
+        &ephemeral_public,
+        &sender,
+        recipient,
+    )?
+    .encrypt(
+        &Nonce::default(),
+        Payload {
+            msg: plaintext,
+            aad,
+        },
+    )
+    .map_err(|_| CommonError::EncryptError)?;
+    let mut boxed = vec![AUTH_BOX_V1];
+    boxed.extend_from_slice(&ephemeral_public);
+    boxed.extend_from_slice(&sender);
+    boxed.extend(ciphertext);
+    Ok(boxed)
+}
+
+/// Decrypts an authenticated box, returning the sender key and plaintext.
+///
+/// Callers must check the sender key belongs to whom they expect.
+pub fn auth_open(
+    secret: &[u8; KEY_SIZE],
+    aad: &[u8],
+    boxed: &[u8],
+) -> Result<([u8; KEY_SIZE], Vec<u8>), CommonError> {
+    let Some((&AUTH_BOX_V1, rest)) = boxed.split_first() else {
+        return Err(CommonError::InvalidVersionedMessage);
+    };
+    let (ephemeral_public, rest) = split_key(rest)?;
+    let (sender, ciphertext) = split_key(rest)?;
+    let ephemeral_dh = shared_secret(secret, &ephemeral_public)?;
+    let static_dh = shared_secret(secret, &sender)?;
+    let plaintext = auth_box_cipher(
+        &ephemeral_dh,
+        &static_dh,
+        &ephemeral_public,
+        &sender,
+        &public_key(secret),
+    )?
+    .decrypt(
+        &Nonce::default(),
+        Payload {
+            msg: ciphertext,
+            aad,
+        },
+    )
+    .map_err(|_| CommonError::DecryptError)?;
+    Ok((sender, plaintext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn sealed_box_test() {
+        let secret = generate(&mut OsRng);
+        let sealed = seal(&public_key(&secret), b"aad", b"hello", &mut OsRng).unwrap();
+        assert_eq!(sealed[0], SEALED_BOX_V1);
+        assert_eq!(open(&secret, b"aad", &sealed).unwrap(), b"hello");
+        assert!(matches!(
+            open(&secret, b"other", &sealed),
+            Err(CommonError::DecryptError)
+        ));
+        let other = generate(&mut OsRng);
+        assert!(open(&other, b"aad", &sealed).is_err());
+    }
+
+    #[test]
+    fn auth_box_test() {
+        let sender = generate(&mut OsRng);
+        let recipient = generate(&mut OsRng);
+        let boxed = auth_seal(&sender, &public_key(&recipient), b"", b"hi", &mut OsRng).unwrap();
+        let (from, plaintext) = auth_open(&recipient, b"", &boxed).unwrap();
+        assert_eq!(from, public_key(&sender));
+        assert_eq!(plaintext, b"hi");
+
+        // Swapping the claimed sender breaks authentication
+        let mut forged = boxed.clone();
+        forged[1 + KEY_SIZE..1 + 2 * KEY_SIZE].copy_from_slice(&public_key(&generate(&mut OsRng)));
+        assert!(auth_open(&recipient, b"", &forged).is_err());
+    }
+
+    #[test]
+    fn wire_version_test() {
+        let secret = generate(&mut OsRng);
+        let sealed = seal(&public_key(&secret), b"", b"x", &mut OsRng).unwrap();
+        assert!(matches!(
+            auth_open(&secret, b"", &sealed),
+            Err(CommonError::InvalidVersionedMessage)
+        ));
+        assert!(matches!(
+            open(&secret, b"", &[SEALED_BOX_V1, 1, 2]),
+            Err(CommonError::InvalidVersionedMessage)
+        ));
+        assert!(open(&secret, b"", &[]).is_err());
+    }
+
+    #[test]
+    fn low_order_key_test() {
+        let secret = generate(&mut OsRng);
+        assert!(matches!(
+            shared_secret(&secret, &[0u8; KEY_SIZE]),
+            Err(CommonError::WeakSharedSecret)
+        ));
+    }
+
+    #[test]
+    fn ed25519_conversion_test() {
+        let alice = SigningKey::from_bytes(&[1u8; 32]);
+        let bob = SigningKey::from_bytes(&[2u8; 32]);
+        let alice_secret = ed25519_secret_to_x25519(alice.as_bytes());
+        let bob_public = ed25519_public_to_x25519(bob.verifying_key().as_bytes()).unwrap();
+        assert_eq!(
+            public_key(&alice_secret),
+            ed25519_public_to_x25519(alice.verifying_key().as_bytes()).unwrap()
+        );
+        let bob_secret = ed25519_secret_to_x25519(bob.as_bytes());
+        let alice_public = public_key(&alice_secret);
+        assert_eq!(
+            *shared_secret(&alice_secret, &bob_public).unwrap(),
+            *shared_secret(&bob_secret, &alice_public).unwrap()
+        );
+    }
+
+    #[test]
+    fn hkdf_rfc5869_test() {
+        // RFC 5869 test case 1
+        let ikm = [0x0bu8; 22];
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        let mut okm = [0u8; 42];
+        hkdf_sha256(&ikm, &salt, &info, &mut okm).unwrap();
+        assert_eq!(&okm[..8], &[0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a]);
+    }
+}
@@ -0,0 +1,236 @@
+//! BIP-39 mnemonics and SLIP-10 Ed25519 key derivation.
+//!
+//! Signer keys are derived at
+//! `m/44'/7337'/{identity}'/{purpose}'/{rotation}'/{slot}'`, all hardened
+//! as SLIP-10 Ed25519 requires:
+//! - `identity`: account of the identity in the phrase, from 0
+//! - `purpose`: index of the key's primary purpose
+//! - `rotation`: 0 for the inception signers, `n` for the keys committed
+//!   as next signers before the `n`th rotation, which then reveals them
+//! - `slot`: position of the key in its set
+//!
+//! 7337 is not a registered SLIP-44 coin type, it only keeps idp2p keys
+//! apart from wallet keys derived from the same phrase.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use bip39::Mnemonic;
+use core::{fmt, str::FromStr};
+use hmac::{Hmac, KeyInit, Mac};
+use rand_core::CryptoRngCore;
+use sha2::Sha512;
+use zeroize::Zeroizing;
+
+use super::signer::{SECRET_SIZE, Signer};
+use crate::{ED_CODE, error::CommonError};
+
+pub const HARDENED: u32 = 0x8000_0000;
+pub const BIP44_PURPOSE: u32 = 44;
+pub const COIN_TYPE: u32 = 7337;
+
+const SLIP10_ED25519_KEY: &[u8] = b"ed25519 seed";
+
+/// Derivation path of a signer key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HdPath {
+    pub identity: u32,
+    pub purpose: u32,
+    pub rotation: u32,
+    pub slot: u32,
+}
+
+impl HdPath {
+    /// Unhardened indexes of the path.
+    pub fn indexes(&self) -> [u32; 6] {
+        [
+            BIP44_PURPOSE,
+            COIN_TYPE,
+            self.identity,
+            self.purpose,
+            self.rotation,
+            self.slot,
+        ]
+    }
+}
+
+impl fmt::Display for HdPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for index in self.indexes() {
+            write!(f, "/{index}'")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for HdPath {
+    type Err = CommonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || CommonError::InvalidDerivationPath(s.into());
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(invalid());
+        }
+        let indexes = parts
+            .map(|p| p.strip_suffix('\'').and_then(|i| i.parse::<u32>().ok()))
+            .collect::<Option<Vec<u32>>>()
+            .ok_or_else(invalid)?;
+        match indexes.as_slice() {
+            &[BIP44_PURPOSE, COIN_TYPE, identity, purpose, rotation, slot]
+                if indexes.iter().all(|i| *i < HARDENED) =>
+            {
+                Ok(Self {
+                    identity,
+                    purpose,
+                    rotation,
+                    slot,
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Generates an English mnemonic of 12, 15, 18, 21 or 24 words.
+pub fn generate_mnemonic(
+    words: usize,
+    rng: &mut impl CryptoRngCore,
+) -> Result<Zeroizing<String>, CommonError> {
+    if !(12..=24).contains(&words) || !words.is_multiple_of(3) {
+        return Err(CommonError::InvalidMnemonic);
+    }
+    let mut entropy = Zeroizing::new([0u8; 32]);
+    let entropy = &mut entropy[..words / 3 * 4];
+    rng.fill_bytes(entropy);
+    let mnemonic = Mnemonic::from_entropy(entropy).map_err(|_| CommonError::InvalidMnemonic)?;
+    Ok(Zeroizing::new(mnemonic.to_string()))
+}
+
+/// Root seed all signer keys are derived from.
+pub struct HdSeed(Zeroizing<Vec<u8>>);
+
+impl HdSeed {
+    /// BIP-39 seed of a mnemonic, the checksum is verified.
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> Result<Self, CommonError> {
+        let mnemonic = Mnemonic::parse(phrase).map_err(|_| CommonError::InvalidMnemonic)?;
+        Ok(Self(Zeroizing::new(mnemonic.to_seed(passphrase).to_vec())))
+    }
+
+    pub fn from_bytes(seed: &[u8]) -> Self {
+        Self(Zeroizing::new(seed.to_vec()))
+    }
+
+    /// SLIP-10 Ed25519 key at unhardened indexes, every index is hardened.
+    pub fn derive(&self, indexes: &[u32]) -> Result<Zeroizing<[u8; SECRET_SIZE]>, CommonError> {
+        let mut key = Zeroizing::new([0u8; SECRET_SIZE]);
+        let mut chain = Zeroizing::new([0u8; SECRET_SIZE]);
+        let mut mac = Hmac::<Sha512>::new_from_slice(SLIP10_ED25519_KEY).expect("any key size");
+        mac.update(&self.0);
+        split(&mac.finalize().into_bytes(), &mut key, &mut chain);
+        for index in indexes {
+            if *index >= HARDENED {
+                return Err(CommonError::InvalidDerivationPath(format!("{index}")));
+            }
+            let mut mac = Hmac::<Sha512>::new_from_slice(chain.as_ref()).expect("any key size");
+            mac.update(&[0]);
+            mac.update(key.as_ref());
+            mac.update(&(index | HARDENED).to_be_bytes());
+            split(&mac.finalize().into_bytes(), &mut key, &mut chain);
+        }
+        Ok(key)
+    }
+
+    /// Ed25519 signer at the path.
+    pub fn signer(&self, path: &HdPath) -> Result<Signer, CommonError> {
+        Signer::new(ED_CODE, &*self.derive(&path.indexes())?)
+    }
+}
+
+fn split(output: &[u8], key: &mut [u8; SECRET_SIZE], chain: &mut [u8; SECRET_SIZE]) {
+    key.copy_from_slice(&output[..SECRET_SIZE]);
+    chain.copy_from_slice(&output[SECRET_SIZE..]);
+}
+
+impl fmt::Debug for HdSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HdSeed(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn bip39_seed_test() {
+        // BIP-39 test vector with the "TREZOR" passphrase
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = HdSeed::from_mnemonic(phrase, "TREZOR").unwrap();
+        assert_eq!(
+            hex(&seed.0),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+        let bad = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon";
+        assert!(matches!(
+            HdSeed::from_mnemonic(bad, ""),
+            Err(CommonError::InvalidMnemonic)
+        ));
+    }
+
+    #[test]
+    fn slip10_ed25519_test() {
+        // SLIP-10 Ed25519 test vector 1
+        let seed = HdSeed::from_bytes(&[
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+            0x0e, 0x0f,
+        ]);
+        assert_eq!(
+            hex(seed.derive(&[]).unwrap().as_ref()),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            hex(seed.derive(&[0]).unwrap().as_ref()),
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+        );
+        assert!(seed.derive(&[HARDENED]).is_err());
+    }
+
+    #[test]
+    fn path_test() {
+        let path = HdPath {
+            identity: 1,
+            purpose: 0,
+            rotation: 2,
+            slot: 3,
+        };
+        assert_eq!(path.to_string(), "m/44'/7337'/1'/0'/2'/3'");
+        assert_eq!(HdPath::from_str(&path.to_string()).unwrap(), path);
+        assert!(HdPath::from_str("m/44'/0'/1'/0'/2'/3'").is_err());
+        assert!(HdPath::from_str("m/44'/7337'/1'/0'/2'/3").is_err());
+    }
+
+    #[test]
+    fn mnemonic_recovery_test() {
+        let phrase = generate_mnemonic(24, &mut OsRng).unwrap();
+        assert_eq!(phrase.split(' ').count(), 24);
+        let path = HdPath {
+            identity: 0,
+            purpose: 0,
+            rotation: 1,
+            slot: 0,
+        };
+        let signer = HdSeed::from_mnemonic(&phrase, "").unwrap().signer(&path).unwrap();
+        let recovered = HdSeed::from_mnemonic(&phrase, "").unwrap().signer(&path).unwrap();
+        assert_eq!(signer.id, recovered.id);
+        assert!(generate_mnemonic(13, &mut OsRng).is_err());
+    }
+}
@@ -0,0 +1,446 @@
+//! Password encrypted store of signer keys.
+//!
+//! The store key is derived from the password with Argon2id, every secret is
+//! sealed with ChaCha20-Poly1305 under its own random nonce. The entry
+//! metadata and public key are the associated data, so they can be listed
+//! without the password but not changed without it.
+//!
+//! The file is the CBOR encoding of `Keystore`, `version` names the format.
+
+use alloc::{string::String, vec::Vec};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use cid::Cid;
+use rand_core::CryptoRngCore;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use zeroize::Zeroizing;
+
+use super::signer::{SECRET_SIZE, Signer};
+use crate::{bytes::Bytes, cbor, error::CommonError};
+
+pub const KEYSTORE_V1: u64 = 1;
+
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+const SALT_SIZE: usize = 16;
+const CHECK_AAD: &[u8] = b"idp2p/keystore/v1/check";
+
+/// Largest Argon2id costs accepted from a store, so a crafted file can't
+/// make `unlock` allocate or run without bound. Memory is 256 MiB in KiB.
+pub const MAX_M_COST: u32 = 256 * 1024;
+pub const MAX_T_COST: u32 = 16;
+pub const MAX_P_COST: u32 = 16;
+
+/// What a stored key is kept for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyRole {
+    /// Signs for the identity now
+    Current,
+    /// Pre-rotated key, only its commitment is published
+    Next,
+}
+
+/// Public information about a stored key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyMetadata {
+    /// Identity the key belongs to
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub did: Option<String>,
+    /// Purposes the key is declared with
+    pub purposes: Vec<String>,
+    pub role: KeyRole,
+    /// Commitment CID published for a next key
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub commitment: Option<String>,
+    /// Derivation path of a key derived from a mnemonic, see `crypto::hd`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub path: Option<String>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyEntry {
+    /// Signer CID
+    pub id: String,
+    pub code: u64,
+    #[serde_as(as = "Bytes")]
+    pub public_key: Vec<u8>,
+    pub metadata: KeyMetadata,
+    /// Nonce followed by the sealed secret
+    #[serde_as(as = "Bytes")]
+    secret: Vec<u8>,
+}
+
+/// Argon2id cost parameters, stored with the salt.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+    #[serde_as(as = "Bytes")]
+    pub salt: Vec<u8>,
+}
+
+impl KdfParams {
+    /// OWASP recommended costs with a fresh salt.
+    pub fn new(rng: &mut impl CryptoRngCore) -> Self {
+        let mut salt = vec![0u8; SALT_SIZE];
+        rng.fill_bytes(&mut salt);
+        Self {
+            m_cost: 19 * 1024,
+            t_cost: 2,
+            p_cost: 1,
+            salt,
+        }
+    }
+
+    /// Rejects costs above the limits and salts of another size.
+    fn check(&self) -> Result<(), CommonError> {
+        if self.m_cost > MAX_M_COST
+            || self.t_cost > MAX_T_COST
+            || self.p_cost > MAX_P_COST
+            || self.salt.len() != SALT_SIZE
+        {
+            return Err(CommonError::InvalidKeystore);
+        }
+        Ok(())
+    }
+
+    fn derive(&self, password: &[u8]) -> Result<StoreKey, CommonError> {
+        self.check()?;
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(KEY_SIZE))
+            .map_err(|_| CommonError::InvalidKeystore)?;
+        let mut key = Zeroizing::new([0u8; KEY_SIZE]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password, &self.salt, key.as_mut())
+            .map_err(|_| CommonError::InvalidKeystore)?;
+        Ok(StoreKey(key))
+    }
+}
+
+/// Key derived from the password, proof that a store was unlocked.
+pub struct StoreKey(Zeroizing<[u8; KEY_SIZE]>);
+
+impl StoreKey {
+    fn seal(
+        &self,
+        aad: &[u8],
+        plaintext: &[u8],
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<Vec<u8>, CommonError> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        rng.fill_bytes(&mut nonce);
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&self.0[..]))
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+            .map_err(|_| CommonError::EncryptError)?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn open(&self, aad: &[u8], sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>, CommonError> {
+        if sealed.len() < NONCE_SIZE {
+            return Err(CommonError::DecryptError);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+        ChaCha20Poly1305::new(Key::from_slice(&self.0[..]))
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map(Zeroizing::new)
+            .map_err(|_| CommonError::DecryptError)
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u64,
+    pub kdf: KdfParams,
+    /// Empty message sealed with the store key to check the password
+    #[serde_as(as = "Bytes")]
+    check: Vec<u8>,
+    pub entries: Vec<KeyEntry>,
+}
+
+impl KeyEntry {
+    fn aad(&self) -> Result<Vec<u8>, CommonError> {
+        cbor::encode(&(KEYSTORE_V1, &self.id, self.code, &self.public_key, &self.metadata))
+    }
+}
+
+impl Keystore {
+    /// Creates an empty store locked with the password.
+    pub fn new(
+        password: &[u8],
+        kdf: KdfParams,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<(Self, StoreKey), CommonError> {
+        let key = kdf.derive(password)?;
+        let store = Self {
+            version: KEYSTORE_V1,
+            kdf,
+            check: key.seal(CHECK_AAD, &[], rng)?,
+            entries: vec![],
+        };
+        Ok((store, key))
+    }
+
+    /// Derives the store key, failing on a wrong password.
+    pub fn unlock(&self, password: &[u8]) -> Result<StoreKey, CommonError> {
+        let key = self.kdf.derive(password)?;
+        key.open(CHECK_AAD, &self.check)?;
+        Ok(key)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&KeyEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    /// Stores the secret of a signer.
+    pub fn insert(
+        &mut self,
+        key: &StoreKey,
+        signer: &Signer,
+        metadata: KeyMetadata,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<(), CommonError> {
+        key.open(CHECK_AAD, &self.check)?;
+        if self.get(&signer.id).is_some() {
+            return Err(CommonError::DuplicateKey(signer.id.clone()));
+        }
+        let mut entry = KeyEntry {
+            id: signer.id.clone(),
+            code: signer.code,
+            public_key: signer.public_key.clone(),
+            metadata,
+            secret: vec![],
+        };
+        entry.secret = key.seal(&entry.aad()?, signer.secret(), rng)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Replaces the metadata of a key, resealing its secret.
+    pub fn update(
+        &mut self,
+        key: &StoreKey,
+        id: &str,
+        metadata: KeyMetadata,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<(), CommonError> {
+        let signer = self.signer(key, id)?;
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.id == id)
+            .expect("entry exists");
+        entry.metadata = metadata;
+        entry.secret = key.seal(&entry.aad()?, signer.secret(), rng)?;
+        Ok(())
+    }
+
+    /// Removes a key, e.g. once it has been rotated out.
+    pub fn remove(&mut self, id: &str) -> Option<KeyEntry> {
+        let index = self.entries.iter().position(|e| e.id == id)?;
+        Some(self.entries.remove(index))
+    }
+
+    /// Decrypts a stored key into the signer that builds proofs.
+    pub fn signer(&self, key: &StoreKey, id: &str) -> Result<Signer, CommonError> {
+        let entry = self
+            .get(id)
+            .ok_or_else(|| CommonError::KeyNotFound(id.into()))?;
+        let secret = key.open(&entry.aad()?, &entry.secret)?;
+        let secret: &[u8; SECRET_SIZE] = secret
+            .as_slice()
+            .try_into()
+            .map_err(|_| CommonError::InvalidPrivateKey)?;
+        // Ids keep the hash they were created with
+        let cid = Cid::try_from(entry.id.as_str())
+            .map_err(|_| CommonError::InvalidIdentifier(entry.id.clone()))?;
+        Signer::from_parts(entry.code, &entry.public_key, secret)?.with_hash(cid.hash().code())
+    }
+
+    /// Re-encrypts every key under a new password.
+    pub fn change_password(
+        &mut self,
+        key: &StoreKey,
+        password: &[u8],
+        kdf: KdfParams,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<StoreKey, CommonError> {
+        let signers = self
+            .entries
+            .iter()
+            .map(|e| self.signer(key, &e.id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (mut store, new_key) = Self::new(password, kdf, rng)?;
+        for (signer, entry) in signers.iter().zip(self.entries.iter()) {
+            store.insert(&new_key, signer, entry.metadata.clone(), rng)?;
+        }
+        *self = store;
+        Ok(new_key)
+    }
+
+    /// Encodes the store, secrets stay encrypted.
+    pub fn export(&self) -> Result<Vec<u8>, CommonError> {
+        cbor::encode(self)
+    }
+
+    /// Decodes an exported store.
+    pub fn import(bytes: &[u8]) -> Result<Self, CommonError> {
+        let store: Self = cbor::decode(bytes)?;
+        if store.version != KEYSTORE_V1 {
+            return Err(CommonError::InvalidVersionedMessage);
+        }
+        store.kdf.check()?;
+        Ok(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ED_CODE, ED_MLDSA65_CODE, MLDSA65_CODE, P256_CODE, verification};
+    use rand::rngs::OsRng;
+
+    // Cheap costs keep tests fast
+    fn kdf() -> KdfParams {
+        KdfParams {
+            m_cost: 64,
+            t_cost: 1,
+            p_cost: 1,
+            salt: vec![1u8; SALT_SIZE],
+        }
+    }
+
+    fn metadata(role: KeyRole) -> KeyMetadata {
+        KeyMetadata {
+            did: Some("did:idp2p:test".into()),
+            purposes: vec!["event-signing".into()],
+            role,
+            commitment: None,
+            path: None,
+        }
+    }
+
+    #[test]
+    fn roundtrip_test() {
+        let (mut store, key) = Keystore::new(b"secret", kdf(), &mut OsRng).unwrap();
+        let current = Signer::generate(ED_CODE, &mut OsRng).unwrap();
+        let next = Signer::generate(P256_CODE, &mut OsRng).unwrap();
+        store.insert(&key, &current, metadata(KeyRole::Current), &mut OsRng).unwrap();
+        let mut next_meta = metadata(KeyRole::Next);
+        next_meta.commitment = Some(next.id.clone());
+        store.insert(&key, &next, next_meta.clone(), &mut OsRng).unwrap();
+
+        let imported = Keystore::import(&store.export().unwrap()).unwrap();
+        assert_eq!(imported, store);
+        assert_eq!(imported.get(&next.id).unwrap().metadata, next_meta);
+
+        let key = imported.unlock(b"secret").unwrap();
+        let signer = imported.signer(&key, &current.id).unwrap();
+        let sig = signer.sign(b"content").unwrap();
+        assert!(verification::verify(ED_CODE, &current.public_key, b"content", &sig).is_ok());
+    }
+
+    #[test]
+    fn post_quantum_keys_test() {
+        let (mut store, key) = Keystore::new(b"secret", kdf(), &mut OsRng).unwrap();
+        for code in [MLDSA65_CODE, ED_MLDSA65_CODE] {
+            let signer = Signer::generate(code, &mut OsRng).unwrap();
+            store.insert(&key, &signer, metadata(KeyRole::Current), &mut OsRng).unwrap();
+            let restored = store.signer(&key, &signer.id).unwrap();
+            assert_eq!(restored.public_key, signer.public_key);
+            let sig = restored.sign(b"content").unwrap();
+            assert!(verification::verify(code, &signer.public_key, b"content", &sig).is_ok());
+        }
+    }
+
+    #[test]
+    fn wrong_password_test() {
+        let (store, _) = Keystore::new(b"secret", kdf(), &mut OsRng).unwrap();
+        assert!(matches!(store.unlock(b"wrong"), Err(CommonError::DecryptError)));
+    }
+
+    #[test]
+    fn tampered_metadata_test() {
+        let (mut store, key) = Keystore::new(b"secret", kdf(), &mut OsRng).unwrap();
+        let signer = Signer::generate(ED_CODE, &mut OsRng).unwrap();
+        store.insert(&key, &signer, metadata(KeyRole::Next), &mut OsRng).unwrap();
+        store.entries[0].metadata.role = KeyRole::Current;
+        assert!(matches!(
+            store.signer(&key, &signer.id),
+            Err(CommonError::DecryptError)
+        ));
+    }
+
+    #[test]
+    fn update_test() {
+        let (mut store, key) = Keystore::new(b"secret", kdf(), &mut OsRng).unwrap();
+        let signer = Signer::generate(ED_CODE, &mut OsRng).unwrap();
+        store.insert(&key, &signer, metadata(KeyRole::Next), &mut OsRng).unwrap();
+        store.update(&key, &signer.id, metadata(KeyRole::Current), &mut OsRng).unwrap();
+        assert_eq!(store.get(&signer.id).unwrap().metadata.role, KeyRole::Current);
+        assert!(store.signer(&key, &signer.id).is_ok());
+    }
+
+    #[test]
+    fn duplicate_and_remove_test() {
+        let (mut store, key) = Keystore::new(b"secret", kdf(), &mut OsRng).unwrap();
+        let signer = Signer::generate(ED_CODE, &mut OsRng).unwrap();
+        store.insert(&key, &signer, metadata(KeyRole::Current), &mut OsRng).unwrap();
+        assert!(matches!(
+            store.insert(&key, &signer, metadata(KeyRole::Current), &mut OsRng),
+            Err(CommonError::DuplicateKey(_))
+        ));
+        assert!(store.remove(&signer.id).is_some());
+        assert!(matches!(
+            store.signer(&key, &signer.id),
+            Err(CommonError::KeyNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn change_password_test() {
+        let (mut store, key) = Keystore::new(b"old", kdf(), &mut OsRng).unwrap();
+        let signer = Signer::generate(ED_CODE, &mut OsRng).unwrap();
+        store.insert(&key, &signer, metadata(KeyRole::Current), &mut OsRng).unwrap();
+        store.change_password(&key, b"new", kdf(), &mut OsRng).unwrap();
+        assert!(store.unlock(b"old").is_err());
+        let key = store.unlock(b"new").unwrap();
+        assert_eq!(store.signer(&key, &signer.id).unwrap().id, signer.id);
+    }
+
+    #[test]
+    fn kdf_limits_test() {
+        let (mut store, _) = Keystore::new(b"secret", kdf(), &mut OsRng).unwrap();
+        store.kdf.m_cost = MAX_M_COST + 1;
+        assert!(matches!(
+            Keystore::import(&store.export().unwrap()),
+            Err(CommonError::InvalidKeystore)
+        ));
+        assert!(matches!(store.unlock(b"secret"), Err(CommonError::InvalidKeystore)));
+        store.kdf.m_cost = 64;
+        store.kdf.p_cost = u32::MAX;
+        assert!(Keystore::import(&store.export().unwrap()).is_err());
+        store.kdf.p_cost = 1;
+        store.kdf.t_cost = MAX_T_COST + 1;
+        assert!(Keystore::import(&store.export().unwrap()).is_err());
+        assert!(KdfParams::new(&mut OsRng).check().is_ok());
+    }
+
+    #[test]
+    fn unknown_version_test() {
+        let (mut store, _) = Keystore::new(b"secret", kdf(), &mut OsRng).unwrap();
+        store.version = 2;
+        assert!(matches!(
+            Keystore::import(&store.export().unwrap()),
+            Err(CommonError::InvalidVersionedMessage)
+        ));
+    }
+}
@@ -0,0 +1,158 @@
+//! Shamir secret sharing over GF(2^8).
+//!
+//! Every byte of the secret is the constant term of its own random
+//! polynomial of degree `threshold - 1`, share `x` holds the evaluations at
+//! `x`. Any `threshold` shares interpolate the secret, fewer reveal nothing.
+
+use alloc::vec::Vec;
+use rand_core::CryptoRngCore;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use zeroize::Zeroizing;
+
+use crate::{bytes::Bytes, error::CommonError};
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Share {
+    /// Evaluation point, never 0
+    pub index: u8,
+    pub threshold: u8,
+    #[serde_as(as = "Bytes")]
+    pub data: Vec<u8>,
+}
+
+/// Multiplication in GF(2^8) with the AES polynomial, without branches on data.
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    product
+}
+
+/// Inverse as `a^254`, 0 has none.
+fn inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Splits a secret into `count` shares, any `threshold` of them recover it.
+pub fn split(
+    secret: &[u8],
+    threshold: u8,
+    count: u8,
+    rng: &mut impl CryptoRngCore,
+) -> Result<Vec<Share>, CommonError> {
+    if threshold == 0 || count < threshold || secret.is_empty() {
+        return Err(CommonError::InvalidShare);
+    }
+    let mut shares: Vec<Share> = (1..=count)
+        .map(|index| Share {
+            index,
+            threshold,
+            data: Vec::with_capacity(secret.len()),
+        })
+        .collect();
+    let mut coefficients = Zeroizing::new(vec![0u8; threshold as usize]);
+    for byte in secret {
+        coefficients[0] = *byte;
+        rng.fill_bytes(&mut coefficients[1..]);
+        for share in shares.iter_mut() {
+            // Horner evaluation at the share index
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0u8, |acc, c| mul(acc, share.index) ^ c);
+            share.data.push(y);
+        }
+    }
+    Ok(shares)
+}
+
+/// Recovers the secret from at least `threshold` distinct shares.
+///
+/// Shares are not authenticated, callers check the result.
+pub fn combine(shares: &[Share]) -> Result<Zeroizing<Vec<u8>>, CommonError> {
+    let first = shares.first().ok_or(CommonError::InvalidShare)?;
+    let threshold = first.threshold as usize;
+    let shares = &shares[..threshold.min(shares.len())];
+    let distinct = shares
+        .iter()
+        .enumerate()
+        .all(|(i, s)| s.index != 0 && shares[..i].iter().all(|o| o.index != s.index));
+    let consistent = shares
+        .iter()
+        .all(|s| s.threshold == first.threshold && s.data.len() == first.data.len());
+    if shares.len() < threshold || !distinct || !consistent {
+        return Err(CommonError::InvalidShare);
+    }
+    // Lagrange basis polynomials evaluated at 0
+    let basis: Vec<u8> = shares
+        .iter()
+        .map(|s| {
+            shares
+                .iter()
+                .filter(|o| o.index != s.index)
+                .fold(1u8, |acc, o| mul(acc, mul(o.index, inv(o.index ^ s.index))))
+        })
+        .collect();
+    let mut secret = Zeroizing::new(vec![0u8; first.data.len()]);
+    for (i, byte) in secret.iter_mut().enumerate() {
+        *byte = shares
+            .iter()
+            .zip(basis.iter())
+            .fold(0u8, |acc, (s, l)| acc ^ mul(s.data[i], *l));
+    }
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn field_test() {
+        assert_eq!(mul(0x53, 0xca), 0x01);
+        assert_eq!(inv(0x53), 0xca);
+        for a in 1..=255u8 {
+            assert_eq!(mul(a, inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn split_combine_test() {
+        let secret = b"recovery secret of an identity";
+        let shares = split(secret, 3, 5, &mut OsRng).unwrap();
+        assert_eq!(shares.len(), 5);
+        for set in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<Share> = set.iter().map(|i| shares[*i].clone()).collect();
+            assert_eq!(combine(&subset).unwrap().as_slice(), secret);
+        }
+        // Too few shares
+        assert!(combine(&shares[..2]).is_err());
+        // Duplicate shares
+        let dup = [shares[0].clone(), shares[0].clone(), shares[1].clone()];
+        assert!(combine(&dup).is_err());
+    }
+
+    #[test]
+    fn invalid_parameters_test() {
+        assert!(split(b"x", 0, 1, &mut OsRng).is_err());
+        assert!(split(b"x", 3, 2, &mut OsRng).is_err());
+        assert!(split(b"", 1, 1, &mut OsRng).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use cid::Cid;
+use k256::schnorr;
+use ml_dsa::{KeyGen, MlDsa44, MlDsa65, MlDsa87, MlDsaParams};
+use rand_core::CryptoRngCore;
+use zeroize::Zeroizing;
+
+use crate::{
+    ED_CODE, ED_MLDSA65_CODE, MLDSA44_CODE, MLDSA65_CODE, MLDSA87_CODE, P256_CODE,
+    SECP256K1_CODE, SECP256K1_SCHNORR_CODE, cid::CidExt, error::CommonError, utils::sha256_hash,
+};
+
+pub const SECRET_SIZE: usize = 32;
+
+/// Domain of the ML-DSA-65 seed of a hybrid secret.
+const HYBRID_MLDSA_DOMAIN: &[u8] = b"idp2p/hybrid/ml-dsa-65/v1";
+
+/// Private key of a signer.
+///
+/// Ed25519 secrets are seeds, P-256 and secp256k1 secrets are scalars and
+/// ML-DSA secrets are the FIPS 204 key generation seed. A hybrid secret is
+/// the Ed25519 seed, its ML-DSA-65 seed is the SHA-256 of
+/// `HYBRID_MLDSA_DOMAIN` followed by the secret.
+/// `SECP256K1_CODE` signers sign ECDSA and `SECP256K1_SCHNORR_CODE`
+/// signers BIP-340 Schnorr, the same way verification picks the scheme.
+pub struct Signer {
+    pub id: String,
+    pub code: u64,
+    pub public_key: Vec<u8>,
+    secret: Zeroizing<[u8; SECRET_SIZE]>,
+}
+
+impl core::fmt::Debug for Signer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Signer")
+            .field("id", &self.id)
+            .field("code", &self.code)
+            .finish_non_exhaustive()
+    }
+}
+
+fn hybrid_mldsa_seed(secret: &[u8; SECRET_SIZE]) -> Zeroizing<[u8; SECRET_SIZE]> {
+    Zeroizing::new(sha256_hash(&[HYBRID_MLDSA_DOMAIN, secret.as_ref()].concat()))
+}
+
+fn mldsa_public_key<P: MlDsaParams>(seed: &[u8; SECRET_SIZE]) -> Vec<u8> {
+    P::key_gen_internal(&(*seed).into())
+        .verifying_key()
+        .encode()
+        .to_vec()
+}
+
+fn mldsa_sign<P: MlDsaParams>(seed: &[u8; SECRET_SIZE], content: &[u8]) -> Vec<u8> {
+    use ml_dsa::signature::Signer as _;
+    P::key_gen_internal(&(*seed).into())
+        .signing_key()
+        .sign(content)
+        .encode()
+        .to_vec()
+}
+
+fn public_key(code: u64, secret: &[u8; SECRET_SIZE]) -> Result<Vec<u8>, CommonError> {
+    let public = match code {
+        ED_CODE => ed25519_dalek::SigningKey::from_bytes(secret)
+            .verifying_key()
+            .to_bytes()
+            .to_vec(),
+        P256_CODE => p256::ecdsa::SigningKey::from_slice(secret)
+            .map_err(|_| CommonError::InvalidPrivateKey)?
+            .verifying_key()
+            .to_encoded_point(true)
+            .as_bytes()
+            .to_vec(),
+        SECP256K1_SCHNORR_CODE => schnorr::SigningKey::from_bytes(secret)
+            .map_err(|_| CommonError::InvalidPrivateKey)?
+            .verifying_key()
+            .to_bytes()
+            .to_vec(),
+        SECP256K1_CODE => k256::ecdsa::SigningKey::from_slice(secret)
+            .map_err(|_| CommonError::InvalidPrivateKey)?
+            .verifying_key()
+            .to_encoded_point(true)
+            .as_bytes()
+            .to_vec(),
+        MLDSA44_CODE => mldsa_public_key::<MlDsa44>(secret),
+        MLDSA65_CODE => mldsa_public_key::<MlDsa65>(secret),
+        MLDSA87_CODE => mldsa_public_key::<MlDsa87>(secret),
+        ED_MLDSA65_CODE => {
+            let mut public = public_key(ED_CODE, secret)?;
+            public.extend(mldsa_public_key::<MlDsa65>(&hybrid_mldsa_seed(secret)));
+            public
+        }
+        _ => return Err(CommonError::UnsupportedCodec(code)),
+    };
+    Ok(public)
+}
+
+impl Signer {
+    /// Signer of a secret key.
+    pub fn new(code: u64, secret: &[u8; SECRET_SIZE]) -> Result<Self, CommonError> {
+        Self::with_public_key(code, public_key(code, secret)?, secret)
+    }
+
+    /// BIP-340 Schnorr signer of a secp256k1 secret key.
+    pub fn new_schnorr(secret: &[u8; SECRET_SIZE]) -> Result<Self, CommonError> {
+        Self::new(SECP256K1_SCHNORR_CODE, secret)
+    }
+
+    /// Generates a signer.
+    pub fn generate(code: u64, rng: &mut impl CryptoRngCore) -> Result<Self, CommonError> {
+        loop {
+            let mut secret = Zeroizing::new([0u8; SECRET_SIZE]);
+            rng.fill_bytes(secret.as_mut());
+            match Self::new(code, &secret) {
+                // Scalars out of the curve order are rare, try again
+                Err(CommonError::InvalidPrivateKey) => continue,
+                result => return result,
+            }
+        }
+    }
+
+    /// Signer of a stored key, the public key must belong to the secret.
+    pub fn from_parts(
+        code: u64,
+        public: &[u8],
+        secret: &[u8; SECRET_SIZE],
+    ) -> Result<Self, CommonError> {
+        let derived = public_key(code, secret)?;
+        if derived != public {
+            return Err(CommonError::InvalidPrivateKey);
+        }
+        Self::with_public_key(code, derived, secret)
+    }
+
+    fn with_public_key(
+        code: u64,
+        public_key: Vec<u8>,
+        secret: &[u8; SECRET_SIZE],
+    ) -> Result<Self, CommonError> {
+        Ok(Self {
+            id: Cid::create(code, &public_key)?.to_string(),
+            code,
+            public_key,
+            secret: Zeroizing::new(*secret),
+        })
+    }
+
+    /// The same signer with its id hashed with `hash_code`, one of
+    /// `HASH_CODES`.
+    pub fn with_hash(mut self, hash_code: u64) -> Result<Self, CommonError> {
+        self.id = Cid::create_with(self.code, hash_code, &self.public_key)?.to_string();
+        Ok(self)
+    }
+
+    pub fn secret(&self) -> &[u8; SECRET_SIZE] {
+        &self.secret
+    }
+
+    /// Signs content with the scheme `verification::verify` checks.
+    pub fn sign(&self, content: &[u8]) -> Result<Vec<u8>, CommonError> {
+        use k256::ecdsa::signature::Signer as _;
+        let invalid = |_| CommonError::InvalidPrivateKey;
+        let sig = match self.code {
+            ED_CODE => ed25519_dalek::SigningKey::from_bytes(&self.secret)
+                .sign(content)
+                .to_bytes()
+                .to_vec(),
+            P256_CODE => {
+                let sk = p256::ecdsa::SigningKey::from_slice(self.secret.as_ref()).map_err(invalid)?;
+                let sig: p256::ecdsa::Signature = sk.sign(content);
+                sig.to_bytes().to_vec()
+            }
+            SECP256K1_SCHNORR_CODE => {
+                let sk = schnorr::SigningKey::from_bytes(self.secret.as_ref()).map_err(invalid)?;
+                let sig: schnorr::Signature = sk.sign(content);
+                sig.to_bytes().to_vec()
+            }
+            SECP256K1_CODE => {
+                let sk = k256::ecdsa::SigningKey::from_slice(self.secret.as_ref()).map_err(invalid)?;
+                let sig: k256::ecdsa::Signature = sk.sign(content);
+                sig.to_bytes().to_vec()
+            }
+            MLDSA44_CODE => mldsa_sign::<MlDsa44>(&self.secret, content),
+            MLDSA65_CODE => mldsa_sign::<MlDsa65>(&self.secret, content),
+            MLDSA87_CODE => mldsa_sign::<MlDsa87>(&self.secret, content),
+            // Ed25519 part followed by the ML-DSA-65 part, as verified
+            ED_MLDSA65_CODE => {
+                let mut sig = ed25519_dalek::SigningKey::from_bytes(&self.secret)
+                    .sign(content)
+                    .to_bytes()
+                    .to_vec();
+                sig.extend(mldsa_sign::<MlDsa65>(&hybrid_mldsa_seed(&self.secret), content));
+                sig
+            }
+            code => return Err(CommonError::UnsupportedCodec(code)),
+        };
+        Ok(sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verification;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn sign_verify_test() {
+        for code in [
+            ED_CODE,
+            P256_CODE,
+            SECP256K1_CODE,
+            SECP256K1_SCHNORR_CODE,
+            MLDSA44_CODE,
+            MLDSA65_CODE,
+            MLDSA87_CODE,
+            ED_MLDSA65_CODE,
+        ] {
+            let signer = Signer::generate(code, &mut OsRng).unwrap();
+            let sig = signer.sign(b"content").unwrap();
+            assert!(verification::verify(code, &signer.public_key, b"content", &sig).is_ok());
+            let kid = Cid::try_from(signer.id.as_str()).unwrap();
+            assert!(kid.ensure(&signer.public_key, vec![code]).is_ok());
+        }
+    }
+
+    #[test]
+    fn schnorr_sign_verify_test() {
+        let signer = Signer::new_schnorr(&[7u8; 32]).unwrap();
+        assert_eq!(signer.public_key.len(), 32);
+        let sig = signer.sign(b"content").unwrap();
+        let code = SECP256K1_SCHNORR_CODE;
+        assert!(verification::verify(code, &signer.public_key, b"content", &sig).is_ok());
+        assert!(verification::verify(SECP256K1_CODE, &signer.public_key, b"content", &sig).is_err());
+        let restored = Signer::from_parts(code, &signer.public_key, &[7u8; 32]).unwrap();
+        assert_eq!(restored.id, signer.id);
+        // The same secret signs ECDSA under its own codec
+        assert!(Signer::from_parts(SECP256K1_CODE, &signer.public_key, &[7u8; 32]).is_err());
+    }
+
+    #[test]
+    fn hybrid_sign_verify_test() {
+        let signer = Signer::new(ED_MLDSA65_CODE, &[7u8; 32]).unwrap();
+        let ed = Signer::new(ED_CODE, &[7u8; 32]).unwrap();
+        assert!(signer.public_key.starts_with(&ed.public_key));
+        // The ML-DSA part doesn't reuse the Ed25519 seed
+        let pq = Signer::new(MLDSA65_CODE, &[7u8; 32]).unwrap();
+        assert!(!signer.public_key.ends_with(&pq.public_key));
+
+        let sig = signer.sign(b"content").unwrap();
+        let code = ED_MLDSA65_CODE;
+        assert!(verification::verify(code, &signer.public_key, b"content", &sig).is_ok());
+        let restored = Signer::from_parts(code, &signer.public_key, &[7u8; 32]).unwrap();
+        assert_eq!(restored.id, signer.id);
+    }
+
+    #[test]
+    fn from_parts_mismatch_test() {
+        let signer = Signer::new(ED_CODE, &[1u8; 32]).unwrap();
+        assert!(matches!(
+            Signer::from_parts(ED_CODE, &signer.public_key, &[2u8; 32]),
+            Err(CommonError::InvalidPrivateKey)
+        ));
+    }
+}
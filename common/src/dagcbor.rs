@@ -0,0 +1,202 @@
+//! DAG-CBOR payloads with CID links.
+//!
+//! The encoding is the deterministic CBOR of `cbor::encode` where the named
+//! top level fields hold tag 42 links instead of CID strings or bytes, so
+//! IPLD tooling can walk from a payload to the blocks it references.
+
+use alloc::{boxed::Box, string::ToString, vec::Vec};
+use ciborium::Value;
+use cid::Cid;
+use core::str::FromStr;
+
+use crate::{
+    cbor::{canonical_value, canonicalize, write},
+    error::CommonError,
+};
+
+/// CBOR tag of a CID link.
+pub const CID_TAG: u64 = 42;
+
+/// Top level field holding a CID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Link<'a> {
+    /// CID string
+    Text(&'a str),
+    /// `Cid` value, serialized as its bytes
+    Cid(&'a str),
+}
+
+impl Link<'_> {
+    fn name(&self) -> &str {
+        match self {
+            Link::Text(name) | Link::Cid(name) => name,
+        }
+    }
+}
+
+/// Tag 42 link to a CID.
+pub(crate) fn link(cid: &Cid) -> Value {
+    // Links carry the multibase identity prefix
+    let mut bytes = vec![0];
+    bytes.extend_from_slice(&cid.to_bytes());
+    Value::Tag(CID_TAG, Box::new(Value::Bytes(bytes)))
+}
+
+/// CID of a tag 42 link.
+pub(crate) fn link_cid(value: Value) -> Result<Cid, CommonError> {
+    let invalid = || CommonError::DecodeError("Invalid CID link".to_string());
+    let Value::Tag(CID_TAG, inner) = value else {
+        return Err(invalid());
+    };
+    let Value::Bytes(bytes) = *inner else {
+        return Err(invalid());
+    };
+    match bytes.split_first() {
+        Some((0, cid)) => Cid::try_from(cid).map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
+fn to_link(value: Value) -> Result<Value, CommonError> {
+    let cid = match value {
+        Value::Null => return Ok(Value::Null),
+        Value::Text(s) => Cid::from_str(&s).map_err(|_| CommonError::InvalidIdentifier(s))?,
+        Value::Bytes(b) => Cid::try_from(b.as_slice()).map_err(|_| CommonError::EncodeError)?,
+        _ => return Err(CommonError::EncodeError),
+    };
+    Ok(link(&cid))
+}
+
+fn from_link(value: Value, link: &Link) -> Result<Value, CommonError> {
+    if value.is_null() {
+        return Ok(Value::Null);
+    }
+    let cid = link_cid(value)?;
+    Ok(match link {
+        Link::Text(_) => Value::Text(cid.to_string()),
+        Link::Cid(_) => Value::Bytes(cid.to_bytes()),
+    })
+}
+
+fn map_links(
+    value: Value,
+    links: &[Link],
+    f: impl Fn(Value, &Link) -> Result<Value, CommonError>,
+) -> Result<Value, CommonError> {
+    let Value::Map(entries) = value else {
+        return Err(CommonError::DecodeError("Expected a map".to_string()));
+    };
+    let entries = entries
+        .into_iter()
+        .map(|(key, value)| {
+            let link = key
+                .as_text()
+                .and_then(|name| links.iter().find(|l| l.name() == name));
+            let value = match link {
+                Some(link) => f(value, link)?,
+                None => value,
+            };
+            Ok((key, value))
+        })
+        .collect::<Result<_, CommonError>>()?;
+    Ok(Value::Map(entries))
+}
+
+/// Encodes a value as DAG-CBOR with its link fields as tag 42 CIDs.
+pub fn encode<T: serde::Serialize>(value: &T, links: &[Link]) -> Result<Vec<u8>, CommonError> {
+    let value = Value::serialized(value).map_err(|_| CommonError::EncodeError)?;
+    write(&canonicalize(map_links(value, links, |v, _| to_link(v))?)?)
+}
+
+/// Decodes deterministic DAG-CBOR, links come back in the form of their field.
+pub fn decode<T: serde::de::DeserializeOwned>(
+    bytes: &[u8],
+    links: &[Link],
+) -> Result<T, CommonError> {
+    let value = map_links(canonical_value(bytes)?, links, from_link)?;
+    // Read back from bytes, `Value` doesn't hand byte strings to sequences
+    ciborium::de::from_reader(write(&value)?.as_slice())
+        .map_err(|e| CommonError::DecodeError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CBOR_CODE, bytes::Bytes, cbor, cid::CidExt};
+    use alloc::string::String;
+    use serde::{Deserialize, Serialize};
+    use serde_with::serde_as;
+
+    #[serde_as]
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Node {
+        sn: u64,
+        #[serde_as(as = "Bytes")]
+        data: Vec<u8>,
+        patch: Cid,
+        previous: String,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        prior: Option<String>,
+    }
+
+    const LINKS: &[Link] = &[
+        Link::Cid("patch"),
+        Link::Text("previous"),
+        Link::Text("prior"),
+    ];
+
+    fn node() -> Node {
+        Node {
+            sn: 1,
+            data: b"data".to_vec(),
+            patch: Cid::create(CBOR_CODE, b"patch").unwrap(),
+            previous: Cid::create(CBOR_CODE, b"previous").unwrap().to_string(),
+            prior: None,
+        }
+    }
+
+    #[test]
+    fn link_roundtrip_test() {
+        let node = node();
+        let bytes = encode(&node, LINKS).unwrap();
+        assert_eq!(decode::<Node>(&bytes, LINKS).unwrap(), node);
+        // Links are tag 42 (0xd8 0x2a) over 0x00 || CID bytes
+        let previous = Cid::from_str(&node.previous).unwrap().to_bytes();
+        let link = [
+            &[0xd8, 0x2a, 0x58, previous.len() as u8 + 1, 0x00][..],
+            &previous,
+        ]
+        .concat();
+        assert!(bytes.windows(link.len()).any(|w| w == link.as_slice()));
+
+        let with_prior = Node {
+            prior: Some(node.previous.clone()),
+            ..node
+        };
+        let bytes = encode(&with_prior, LINKS).unwrap();
+        assert_eq!(decode::<Node>(&bytes, LINKS).unwrap(), with_prior);
+    }
+
+    #[test]
+    fn plain_cbor_rejected_test() {
+        let bytes = cbor::encode(&node()).unwrap();
+        assert!(decode::<Node>(&bytes, LINKS).is_err());
+        assert_ne!(encode(&node(), LINKS).unwrap(), bytes);
+    }
+
+    #[test]
+    fn invalid_link_test() {
+        let invalid = Node {
+            previous: "not-a-cid".into(),
+            ..node()
+        };
+        assert!(encode(&invalid, LINKS).is_err());
+        // Link without the identity prefix
+        let value = Value::Map(vec![(
+            Value::Text("previous".into()),
+            Value::Tag(CID_TAG, Box::new(Value::Bytes(node().patch.to_bytes()))),
+        )]);
+        let bytes = write(&value).unwrap();
+        assert!(decode::<Node>(&bytes, LINKS).is_err());
+    }
+}
@@ -7,17 +7,59 @@ pub enum CommonError {
     DecodeError(String),
     #[error("Encoding error occurred")]
     EncodeError,
+    #[error("Payload is not in deterministic CBOR encoding")]
+    NonCanonicalEncoding,
+    #[error("Payload too large: {0} bytes")]
+    PayloadTooLarge(usize),
+    #[error("Nesting deeper than {0} levels")]
+    NestingTooDeep(usize),
+    #[error("Collection too large: {0} items")]
+    CollectionTooLarge(u64),
     #[error("Invalid public key provided")]
     InvalidPublicKey,
+    #[error("Invalid private key provided")]
+    InvalidPrivateKey,
     #[error("Invalid signature provided")]
     InvalidSignature,
     #[error("Signature verification failed")]
     SignatureVerifyError,
+    #[error("Batch signature verification failed at item {0}")]
+    BatchVerifyError(usize),
     #[error("Invalid versioned message")]
     InvalidVersionedMessage,
+    #[error("Key agreement produced a weak shared secret")]
+    WeakSharedSecret,
+    #[error("Encryption failed")]
+    EncryptError,
+    #[error("Decryption failed")]
+    DecryptError,
+    #[error("Invalid mnemonic")]
+    InvalidMnemonic,
+    #[error("Invalid derivation path: {0}")]
+    InvalidDerivationPath(String),
+    #[error("Invalid secret share")]
+    InvalidShare,
+    #[error("Invalid keystore parameters")]
+    InvalidKeystore,
+    #[error("Key not found: {0}")]
+    KeyNotFound(String),
+    #[error("Key already exists: {0}")]
+    DuplicateKey(String),
+    #[error("Invalid Merkle proof")]
+    InvalidMerkleProof,
+    #[error("Merkle proof does not lead to the root")]
+    MerkleRootMismatch,
+    #[error("Block not found: {0}")]
+    BlockNotFound(String),
+    #[error("Storage error: {0}")]
+    StorageError(String),
+    #[error("Invalid CAR archive: {0}")]
+    InvalidCar(String),
+    #[error("Invalid CESR stream: {0}")]
+    InvalidCesr(String),
     #[error("Payload hash does not match the CID hash")]
     PayloadHashMismatch,
-    #[error("Unsupported hash algorithm: {0}. Expected SHA2-256")]
+    #[error("Unsupported hash algorithm: {0}")]
     UnsupportedHashAlgorithm(u64),
     #[error("Invalid ID: {0}")]
     InvalidIdentifier(String),
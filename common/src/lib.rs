@@ -1,15 +1,39 @@
 #![cfg_attr(not(test), no_std)]
 #[macro_use]
 extern crate alloc;
+#[cfg(any(test, feature = "std"))]
+extern crate std;
 
 pub const ED_CODE: u64  = 0xed;
+pub const X25519_CODE: u64 = 0xec;
+pub const SECP256K1_CODE: u64 = 0xe7;
+pub const P256_CODE: u64 = 0x1200;
+pub const MLDSA44_CODE: u64 = 0x1210;
+pub const MLDSA65_CODE: u64 = 0x1211;
+pub const MLDSA87_CODE: u64 = 0x1212;
+/// Ed25519 + ML-DSA-65 hybrid key, from the multicodec private use range
+pub const ED_MLDSA65_CODE: u64 = 0x300000;
+/// secp256k1 BIP-340 Schnorr x-only key, from the multicodec private use range
+pub const SECP256K1_SCHNORR_CODE: u64 = 0x300001;
 pub const SHA2_256_CODE: u64 = 0x12;
+pub const SHA2_512_CODE: u64 = 0x13;
+pub const SHA3_256_CODE: u64 = 0x16;
+pub const BLAKE3_CODE: u64 = 0x1e;
+pub const RAW_CODE: u64 = 0x55;
 pub const CBOR_CODE: u64 = 0x51;
+pub const DAG_CBOR_CODE: u64 = 0x71;
 
 pub mod verification;
+pub mod crypto;
 pub mod utils;
 pub mod cbor;
+pub mod dagcbor;
+pub mod car;
+pub mod cesr;
 pub mod bytes;
 pub mod error;
 pub mod cid;
+pub mod multikey;
+pub mod merkle;
+pub mod blockstore;
 pub mod wasmsg;
@@ -0,0 +1,326 @@
+//! SHA-256 Merkle tree of claim commitments.
+//!
+//! The tree follows RFC 9162: leaves are hashed as `SHA-256(0x00 || data)`
+//! and nodes as `SHA-256(0x01 || left || right)`, so a leaf can never be
+//! passed off as a node. A tree of `n` leaves splits at the largest power of
+//! two below `n`, the root of an empty tree is `SHA-256("")`.
+//!
+//! Roots go into `merkle_proof` of inception and interaction events as
+//! base32 multibase strings.
+
+use alloc::{string::String, vec::Vec};
+use core::{fmt, str::FromStr};
+
+use crate::{
+    error::CommonError,
+    utils::{decode, encode, sha256_hash},
+};
+
+pub const HASH_SIZE: usize = 32;
+/// Version byte of the serialized proof.
+pub const MERKLE_PROOF_V1: u8 = 0x01;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+const PROOF_HEADER_SIZE: usize = 17;
+
+pub type Hash = [u8; HASH_SIZE];
+
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut content = Vec::with_capacity(data.len() + 1);
+    content.push(LEAF_PREFIX);
+    content.extend_from_slice(data);
+    sha256_hash(&content)
+}
+
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut content = [0u8; 2 * HASH_SIZE + 1];
+    content[0] = NODE_PREFIX;
+    content[1..=HASH_SIZE].copy_from_slice(left);
+    content[HASH_SIZE + 1..].copy_from_slice(right);
+    sha256_hash(&content)
+}
+
+/// Largest power of two below `n`, `n` > 1.
+fn split_point(n: usize) -> usize {
+    1 << (usize::BITS - (n - 1).leading_zeros() - 1)
+}
+
+fn subtree_root(hashes: &[Hash]) -> Hash {
+    match hashes.len() {
+        0 => sha256_hash(&[]),
+        1 => hashes[0],
+        n => {
+            let k = split_point(n);
+            node_hash(&subtree_root(&hashes[..k]), &subtree_root(&hashes[k..]))
+        }
+    }
+}
+
+fn subtree_path(index: usize, hashes: &[Hash], path: &mut Vec<Hash>) {
+    if hashes.len() <= 1 {
+        return;
+    }
+    let k = split_point(hashes.len());
+    if index < k {
+        subtree_path(index, &hashes[..k], path);
+        path.push(subtree_root(&hashes[k..]));
+    } else {
+        subtree_path(index - k, &hashes[k..], path);
+        path.push(subtree_root(&hashes[..k]));
+    }
+}
+
+/// Tree over the leaf hashes of its data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleTree {
+    leaves: Vec<Hash>,
+}
+
+impl MerkleTree {
+    pub fn new<T: AsRef<[u8]>>(leaves: &[T]) -> Self {
+        Self {
+            leaves: leaves.iter().map(|l| leaf_hash(l.as_ref())).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn root(&self) -> Hash {
+        subtree_root(&self.leaves)
+    }
+
+    /// Inclusion proof of the leaf at `index`.
+    pub fn proof(&self, index: usize) -> Result<MerkleProof, CommonError> {
+        if index >= self.leaves.len() {
+            return Err(CommonError::InvalidMerkleProof);
+        }
+        let mut path = Vec::new();
+        subtree_path(index, &self.leaves, &mut path);
+        Ok(MerkleProof {
+            index: index as u64,
+            size: self.leaves.len() as u64,
+            path,
+        })
+    }
+}
+
+/// Inclusion proof of a leaf, sibling hashes from the leaf up.
+///
+/// Serialized as `version || index (u64 BE) || size (u64 BE) || path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub index: u64,
+    pub size: u64,
+    pub path: Vec<Hash>,
+}
+
+impl MerkleProof {
+    /// Root the proof leads to from the leaf data.
+    pub fn root(&self, leaf: &[u8]) -> Result<Hash, CommonError> {
+        if self.index >= self.size {
+            return Err(CommonError::InvalidMerkleProof);
+        }
+        let mut index = self.index;
+        let mut last = self.size - 1;
+        let mut hash = leaf_hash(leaf);
+        for sibling in &self.path {
+            if last == 0 {
+                return Err(CommonError::InvalidMerkleProof);
+            }
+            if index & 1 == 1 || index == last {
+                hash = node_hash(sibling, &hash);
+                // Skip levels where the node has no right sibling
+                while index & 1 == 0 && index != 0 {
+                    index >>= 1;
+                    last >>= 1;
+                }
+            } else {
+                hash = node_hash(&hash, sibling);
+            }
+            index >>= 1;
+            last >>= 1;
+        }
+        if last != 0 {
+            return Err(CommonError::InvalidMerkleProof);
+        }
+        Ok(hash)
+    }
+
+    /// Checks that the leaf data is included under the root.
+    pub fn verify(&self, leaf: &[u8], root: &Hash) -> Result<(), CommonError> {
+        if self.root(leaf)? != *root {
+            return Err(CommonError::MerkleRootMismatch);
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PROOF_HEADER_SIZE + self.path.len() * HASH_SIZE);
+        bytes.push(MERKLE_PROOF_V1);
+        bytes.extend_from_slice(&self.index.to_be_bytes());
+        bytes.extend_from_slice(&self.size.to_be_bytes());
+        for hash in &self.path {
+            bytes.extend_from_slice(hash);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CommonError> {
+        if bytes.len() < PROOF_HEADER_SIZE
+            || bytes[0] != MERKLE_PROOF_V1
+            || !(bytes.len() - PROOF_HEADER_SIZE).is_multiple_of(HASH_SIZE)
+        {
+            return Err(CommonError::InvalidMerkleProof);
+        }
+        let u64_at = |i: usize| u64::from_be_bytes(bytes[i..i + 8].try_into().expect("8 bytes"));
+        let path = bytes[PROOF_HEADER_SIZE..]
+            .chunks_exact(HASH_SIZE)
+            .map(|c| c.try_into().expect("hash size"))
+            .collect();
+        Ok(Self {
+            index: u64_at(1),
+            size: u64_at(9),
+            path,
+        })
+    }
+}
+
+impl fmt::Display for MerkleProof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode(self.to_bytes()))
+    }
+}
+
+impl FromStr for MerkleProof {
+    type Err = CommonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bytes(&decode(s)?)
+    }
+}
+
+/// Root of the leaves, as it goes into `merkle_proof` of an event.
+pub fn commitment<T: AsRef<[u8]>>(leaves: &[T]) -> String {
+    encode_root(&MerkleTree::new(leaves).root())
+}
+
+pub fn encode_root(root: &Hash) -> String {
+    encode(root)
+}
+
+/// Parses the root committed in `merkle_proof` of an event.
+pub fn decode_root(s: &str) -> Result<Hash, CommonError> {
+    decode(s)?
+        .try_into()
+        .map_err(|_| CommonError::InvalidMerkleProof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    fn leaves(n: usize) -> Vec<Vec<u8>> {
+        (0..n).map(|i| format!("claim-{i}").into_bytes()).collect()
+    }
+
+    #[test]
+    fn root_test() {
+        // RFC 6962 empty tree and domain separated leaf of empty data
+        assert_eq!(
+            hex(&MerkleTree::new::<&[u8]>(&[]).root()),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&MerkleTree::new(&[b""]).root()),
+            "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d"
+        );
+        let tree = MerkleTree::new(&leaves(3));
+        let hashes: Vec<Hash> = leaves(3).iter().map(|l| leaf_hash(l)).collect();
+        assert_eq!(
+            tree.root(),
+            node_hash(&node_hash(&hashes[0], &hashes[1]), &hashes[2])
+        );
+    }
+
+    #[test]
+    fn inclusion_proof_test() {
+        for n in 1..=17 {
+            let leaves = leaves(n);
+            let tree = MerkleTree::new(&leaves);
+            let root = tree.root();
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(i).unwrap();
+                assert!(proof.verify(leaf, &root).is_ok());
+                assert!(matches!(
+                    proof.verify(b"other", &root),
+                    Err(CommonError::MerkleRootMismatch)
+                ));
+            }
+        }
+        assert!(MerkleTree::new(&leaves(2)).proof(2).is_err());
+    }
+
+    #[test]
+    fn invalid_proof_test() {
+        let leaves = leaves(5);
+        let tree = MerkleTree::new(&leaves);
+        let root = tree.root();
+        let proof = tree.proof(4).unwrap();
+        // Wrong position or size
+        let moved = MerkleProof {
+            index: 3,
+            ..proof.clone()
+        };
+        assert!(moved.verify(&leaves[4], &root).is_err());
+        let resized = MerkleProof {
+            size: 8,
+            ..proof.clone()
+        };
+        assert!(resized.verify(&leaves[4], &root).is_err());
+        // Truncated and extended paths
+        let mut short = proof.clone();
+        short.path.pop();
+        assert!(short.verify(&leaves[4], &root).is_err());
+        let mut long = proof.clone();
+        long.path.push([0; HASH_SIZE]);
+        assert!(long.verify(&leaves[4], &root).is_err());
+    }
+
+    #[test]
+    fn proof_format_test() {
+        let tree = MerkleTree::new(&leaves(6));
+        let proof = tree.proof(5).unwrap();
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes.len(), PROOF_HEADER_SIZE + 2 * HASH_SIZE);
+        assert_eq!(
+            &bytes[..17],
+            &[1, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 6]
+        );
+        assert_eq!(MerkleProof::from_bytes(&bytes).unwrap(), proof);
+        assert_eq!(MerkleProof::from_str(&proof.to_string()).unwrap(), proof);
+        assert!(MerkleProof::from_bytes(&bytes[..20]).is_err());
+        let mut unknown = bytes.clone();
+        unknown[0] = 2;
+        assert!(MerkleProof::from_bytes(&unknown).is_err());
+    }
+
+    #[test]
+    fn commitment_test() {
+        let leaves = leaves(4);
+        let commitment = commitment(&leaves);
+        let root = decode_root(&commitment).unwrap();
+        assert_eq!(root, MerkleTree::new(&leaves).root());
+        assert!(decode_root(&encode(b"short")).is_err());
+    }
+}
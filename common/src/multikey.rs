@@ -0,0 +1,116 @@
+use alloc::{string::String, vec::Vec};
+use cid::multibase::{self, Base};
+
+use crate::error::CommonError;
+
+pub const DID_KEY_PREFIX: &str = "did:key:";
+
+pub(crate) fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub(crate) fn read_varint(bytes: &[u8]) -> Result<(u64, &[u8]), CommonError> {
+    let mut value = 0u64;
+    for (i, b) in bytes.iter().enumerate().take(9) {
+        value |= ((b & 0x7f) as u64) << (7 * i);
+        if b & 0x80 == 0 {
+            // Minimal encoding only, as the multiformats spec requires
+            if i > 0 && *b == 0 {
+                break;
+            }
+            return Ok((value, &bytes[i + 1..]));
+        }
+    }
+    Err(CommonError::DecodeError("Invalid multicodec prefix".into()))
+}
+
+/// Multikey encoding of a public key.
+///
+/// The key is prefixed with its multicodec code as a varint and encoded in
+/// base58btc, e.g. `z6Mk...` for Ed25519. Keys are taken as they are, so
+/// ECDSA keys should be compressed SEC1 to match other implementations.
+pub fn encode(code: u64, public: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(public.len() + 4);
+    write_varint(code, &mut bytes);
+    bytes.extend_from_slice(public);
+    multibase::encode(Base::Base58Btc, bytes)
+}
+
+/// Decodes a Multikey into its multicodec code and public key.
+pub fn decode(multikey: &str) -> Result<(u64, Vec<u8>), CommonError> {
+    let (base, bytes) = multibase::decode(multikey)
+        .map_err(|_| CommonError::DecodeError("Invalid multikey".into()))?;
+    if base != Base::Base58Btc {
+        return Err(CommonError::DecodeError("Multikey must be base58btc".into()));
+    }
+    let (code, public) = read_varint(&bytes)?;
+    if public.is_empty() {
+        return Err(CommonError::InvalidPublicKey);
+    }
+    Ok((code, public.to_vec()))
+}
+
+/// did:key of a public key.
+pub fn to_did_key(code: u64, public: &[u8]) -> String {
+    format!("{DID_KEY_PREFIX}{}", encode(code, public))
+}
+
+/// Parses a did:key or a did:key URL whose fragment is the key itself.
+pub fn from_did_key(did: &str) -> Result<(u64, Vec<u8>), CommonError> {
+    let id = did
+        .strip_prefix(DID_KEY_PREFIX)
+        .ok_or_else(|| CommonError::InvalidIdentifier(did.into()))?;
+    let multikey = match id.split_once('#') {
+        Some((multikey, fragment)) if multikey == fragment => multikey,
+        Some(_) => return Err(CommonError::InvalidIdentifier(did.into())),
+        None => id,
+    };
+    decode(multikey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ED_CODE, MLDSA65_CODE, P256_CODE};
+
+    #[test]
+    fn ed25519_did_key_test() {
+        // Test vector from the did:key specification
+        let did = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp";
+        let (code, public) = from_did_key(did).unwrap();
+        assert_eq!(code, ED_CODE);
+        assert_eq!(public.len(), 32);
+        assert_eq!(to_did_key(code, &public), did);
+    }
+
+    #[test]
+    fn multikey_roundtrip_test() {
+        for (code, public) in [(P256_CODE, vec![2u8; 33]), (MLDSA65_CODE, vec![7u8; 1952])] {
+            let multikey = encode(code, &public);
+            assert!(multikey.starts_with('z'));
+            assert_eq!(decode(&multikey).unwrap(), (code, public));
+        }
+        assert!(encode(P256_CODE, &[2u8; 33]).starts_with("zDn"));
+    }
+
+    #[test]
+    fn did_key_url_test() {
+        let did = to_did_key(ED_CODE, &[1u8; 32]);
+        let multikey = did.strip_prefix(DID_KEY_PREFIX).unwrap();
+        assert!(from_did_key(&format!("{did}#{multikey}")).is_ok());
+        assert!(from_did_key(&format!("{did}#other")).is_err());
+        assert!(from_did_key("did:web:example.com").is_err());
+        assert!(decode(&multibase::encode(Base::Base32Lower, [0xed, 0x01, 1])).is_err());
+    }
+
+    #[test]
+    fn non_minimal_varint_test() {
+        // 0xed encoded with a redundant zero continuation
+        let bytes = [0xed, 0x81, 0x00, 1, 2];
+        assert!(decode(&multibase::encode(Base::Base58Btc, bytes)).is_err());
+    }
+}
@@ -1,8 +1,9 @@
 use alloc::{string::{String, ToString}, vec::Vec};
 use cid::multibase::{self, Base};
-use sha2::{Digest, Sha256};
+use sha2::{Digest, Sha256, Sha512};
+use sha3::Sha3_256;
 
-use crate::error::CommonError;
+use crate::{BLAKE3_CODE, SHA2_256_CODE, SHA2_512_CODE, SHA3_256_CODE, error::CommonError};
 
 pub fn encode<T: AsRef<[u8]>>(input: T) -> String {
     multibase::encode(Base::Base32Lower, input)
@@ -16,4 +17,15 @@ pub fn sha256_hash(content: &[u8]) -> [u8; 32] {
     let digest: [u8; 32] = Sha256::digest(content)
         .try_into().expect("Digest must be 32 bytes");
     digest
-}
\ No newline at end of file
+}
+
+/// Hashes content with the hash function of a multihash code.
+pub fn digest(hash_code: u64, content: &[u8]) -> Result<Vec<u8>, CommonError> {
+    match hash_code {
+        SHA2_256_CODE => Ok(sha256_hash(content).to_vec()),
+        SHA2_512_CODE => Ok(Sha512::digest(content).to_vec()),
+        SHA3_256_CODE => Ok(Sha3_256::digest(content).to_vec()),
+        BLAKE3_CODE => Ok(blake3::hash(content).as_bytes().to_vec()),
+        _ => Err(CommonError::UnsupportedHashAlgorithm(hash_code)),
+    }
+}
@@ -1 +1,34 @@
 pub mod ed25519;
+pub mod mldsa;
+pub mod p256;
+pub mod secp256k1;
+
+use crate::{
+    ED_CODE, ED_MLDSA65_CODE, MLDSA44_CODE, MLDSA65_CODE, MLDSA87_CODE, P256_CODE,
+    SECP256K1_CODE, SECP256K1_SCHNORR_CODE, error::CommonError,
+};
+
+/// Key codecs a signer may use.
+pub const SIGNER_CODES: [u64; 8] = [
+    ED_CODE,
+    P256_CODE,
+    SECP256K1_CODE,
+    SECP256K1_SCHNORR_CODE,
+    MLDSA44_CODE,
+    MLDSA65_CODE,
+    MLDSA87_CODE,
+    ED_MLDSA65_CODE,
+];
+
+/// Verifies a signature with the scheme of the key codec.
+pub fn verify(code: u64, public: &[u8], content: &[u8], sig: &[u8]) -> Result<(), CommonError> {
+    match code {
+        ED_CODE => ed25519::verify(public, content, sig),
+        P256_CODE => self::p256::verify(public, content, sig),
+        SECP256K1_CODE => secp256k1::verify(public, content, sig),
+        SECP256K1_SCHNORR_CODE => secp256k1::verify_schnorr(public, content, sig),
+        MLDSA44_CODE | MLDSA65_CODE | MLDSA87_CODE => mldsa::verify(code, public, content, sig),
+        ED_MLDSA65_CODE => mldsa::verify_hybrid(public, content, sig),
+        _ => Err(CommonError::UnsupportedCodec(code)),
+    }
+}
@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+use curve25519_dalek::edwards::CompressedEdwardsY;
 use ed25519_dalek::{Signature, VerifyingKey};
 
 use crate::error::CommonError;
@@ -5,7 +7,7 @@ use crate::error::CommonError;
 const ED25519_PUBKEY_SIZE: usize = 32;
 const ED25519_SIG_SIZE: usize = 64;
 
-pub fn verify(public: &[u8], content: &[u8], sig: &[u8]) -> Result<(), CommonError> {
+fn parse(public: &[u8], sig: &[u8]) -> Result<(VerifyingKey, Signature), CommonError> {
     let public: [u8; ED25519_PUBKEY_SIZE] = public
         .try_into()
         .map_err(|_| CommonError::InvalidPublicKey)?;
@@ -14,14 +16,126 @@ pub fn verify(public: &[u8], content: &[u8], sig: &[u8]) -> Result<(), CommonErr
         .map_err(|_| CommonError::InvalidSignature)?;
     let pk = VerifyingKey::from_bytes(&public)
         .map_err(|_| CommonError::InvalidPublicKey)?;
-    let signature = Signature::from(&sig);
+    Ok((pk, Signature::from(&sig)))
+}
+
+/// Checks `verify_strict` makes besides the signature equation.
+///
+/// The key and R must not be of small order and R must be canonically
+/// encoded, so a signature accepted in a batch is accepted alone too.
+fn check_strict(pk: &VerifyingKey, signature: &Signature) -> Result<(), CommonError> {
+    let r = CompressedEdwardsY(*signature.r_bytes());
+    let point = r.decompress().ok_or(CommonError::InvalidSignature)?;
+    if pk.is_weak() || point.is_small_order() || point.compress() != r {
+        return Err(CommonError::SignatureVerifyError);
+    }
+    Ok(())
+}
+
+pub fn verify(public: &[u8], content: &[u8], sig: &[u8]) -> Result<(), CommonError> {
+    let (pk, signature) = parse(public, sig)?;
+    check_strict(&pk, &signature)?;
     pk.verify_strict(content, &signature)
         .map_err(|_| CommonError::SignatureVerifyError)?;
     return Ok(());
 }
 
+/// Verifies `(public, content, sig)` items in one batch.
+///
+/// Every item gets the `verify_strict` checks up front, so batches accept
+/// exactly what single verification accepts. When the batch
+/// fails, items are verified one by one so the error carries the index of
+/// the first bad item.
+pub fn verify_batch(items: &[(&[u8], &[u8], &[u8])]) -> Result<(), CommonError> {
+    if let [(public, content, sig)] = items {
+        return verify(public, content, sig).map_err(|_| CommonError::BatchVerifyError(0));
+    }
+    let mut keys = Vec::with_capacity(items.len());
+    let mut signatures = Vec::with_capacity(items.len());
+    let mut messages = Vec::with_capacity(items.len());
+    for (i, (public, content, sig)) in items.iter().enumerate() {
+        let (pk, signature) = parse(public, sig)
+            .and_then(|(pk, signature)| check_strict(&pk, &signature).map(|_| (pk, signature)))
+            .map_err(|_| CommonError::BatchVerifyError(i))?;
+        keys.push(pk);
+        signatures.push(signature);
+        messages.push(*content);
+    }
+    if ed25519_dalek::verify_batch(&messages, &signatures, &keys).is_ok() {
+        return Ok(());
+    }
+    for (i, (public, content, sig)) in items.iter().enumerate() {
+        verify(public, content, sig).map_err(|_| CommonError::BatchVerifyError(i))?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey, Verifier};
+
+    fn signed(seed: u8, msg: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let sk = SigningKey::from_bytes(&[seed; 32]);
+        (sk.verifying_key().to_bytes().to_vec(), sk.sign(msg).to_vec())
+    }
+
     #[test]
     fn to_bytes_test() {}
+
+    #[test]
+    fn verify_batch_test() {
+        let msgs: Vec<Vec<u8>> = (0..4u8).map(|i| vec![i; 8]).collect();
+        let signed: Vec<_> = msgs.iter().enumerate().map(|(i, m)| signed(i as u8, m)).collect();
+        let mut items: Vec<(&[u8], &[u8], &[u8])> = signed
+            .iter()
+            .zip(msgs.iter())
+            .map(|((pk, sig), m)| (pk.as_slice(), m.as_slice(), sig.as_slice()))
+            .collect();
+        assert!(verify_batch(&items).is_ok());
+
+        items[2].1 = b"tampered";
+        let result = verify_batch(&items);
+        assert!(matches!(result, Err(CommonError::BatchVerifyError(2))));
+    }
+
+    #[test]
+    fn verify_batch_weak_key_test() {
+        let (pk, sig) = signed(1, b"msg");
+        let mut weak = [0u8; 32];
+        weak[0] = 1; // identity point
+        let items: [(&[u8], &[u8], &[u8]); 2] = [(&pk, b"msg", &sig), (&weak, b"msg", &sig)];
+        let result = verify_batch(&items);
+        assert!(matches!(result, Err(CommonError::BatchVerifyError(1))));
+    }
+
+    #[test]
+    fn verify_batch_small_order_r_test() {
+        // R is the identity point and S = k * a, so R + kA = SB holds for a
+        // valid key without the signer's nonce. `verify_strict` rejects it
+        // and the cofactorless batch equation would accept it.
+        use curve25519_dalek::Scalar;
+        use sha2::{Digest, Sha512};
+
+        let sk = SigningKey::from_bytes(&[3u8; 32]);
+        let pk = sk.verifying_key().to_bytes();
+        let mut r = [0u8; 32];
+        r[0] = 1;
+        let hash: [u8; 64] = Sha512::new()
+            .chain_update(r)
+            .chain_update(pk)
+            .chain_update(b"msg")
+            .finalize()
+            .into();
+        let k = Scalar::from_bytes_mod_order_wide(&hash);
+        let mut sig = [0u8; 64];
+        sig[..32].copy_from_slice(&r);
+        sig[32..].copy_from_slice((k * sk.to_scalar()).as_bytes());
+        assert!(sk.verifying_key().verify(b"msg", &Signature::from(&sig)).is_ok());
+        assert!(verify(&pk, b"msg", &sig).is_err());
+
+        let (pk1, good) = signed(1, b"msg");
+        let items: [(&[u8], &[u8], &[u8]); 2] = [(&pk1, b"msg", &good), (&pk, b"msg", &sig)];
+        assert!(matches!(verify_batch(&items), Err(CommonError::BatchVerifyError(1))));
+    }
 }
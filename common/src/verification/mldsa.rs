@@ -0,0 +1,88 @@
+use ml_dsa::{
+    EncodedSignature, EncodedVerifyingKey, MlDsa44, MlDsa65, MlDsa87, MlDsaParams, Signature,
+    VerifyingKey, signature::Verifier,
+};
+
+use crate::{
+    MLDSA44_CODE, MLDSA65_CODE, MLDSA87_CODE, error::CommonError, verification::ed25519,
+};
+
+const ED25519_PUBKEY_SIZE: usize = 32;
+const ED25519_SIG_SIZE: usize = 64;
+
+/// Verifies an ML-DSA (FIPS 204) signature with an empty context.
+///
+/// The parameter set is chosen by the key codec.
+pub fn verify(code: u64, public: &[u8], content: &[u8], sig: &[u8]) -> Result<(), CommonError> {
+    match code {
+        MLDSA44_CODE => verify_with::<MlDsa44>(public, content, sig),
+        MLDSA65_CODE => verify_with::<MlDsa65>(public, content, sig),
+        MLDSA87_CODE => verify_with::<MlDsa87>(public, content, sig),
+        _ => Err(CommonError::UnsupportedCodec(code)),
+    }
+}
+
+/// Verifies a hybrid Ed25519 + ML-DSA-65 signature.
+///
+/// Both the public key and the signature are the Ed25519 part followed by
+/// the ML-DSA-65 part, and both signatures must verify.
+pub fn verify_hybrid(public: &[u8], content: &[u8], sig: &[u8]) -> Result<(), CommonError> {
+    if public.len() <= ED25519_PUBKEY_SIZE {
+        return Err(CommonError::InvalidPublicKey);
+    }
+    if sig.len() <= ED25519_SIG_SIZE {
+        return Err(CommonError::InvalidSignature);
+    }
+    let (ed_public, pq_public) = public.split_at(ED25519_PUBKEY_SIZE);
+    let (ed_sig, pq_sig) = sig.split_at(ED25519_SIG_SIZE);
+    ed25519::verify(ed_public, content, ed_sig)?;
+    verify(MLDSA65_CODE, pq_public, content, pq_sig)
+}
+
+fn verify_with<P: MlDsaParams>(public: &[u8], content: &[u8], sig: &[u8]) -> Result<(), CommonError> {
+    let public = EncodedVerifyingKey::<P>::try_from(public)
+        .map_err(|_| CommonError::InvalidPublicKey)?;
+    let pk = VerifyingKey::<P>::decode(&public);
+    let sig = EncodedSignature::<P>::try_from(sig)
+        .map_err(|_| CommonError::InvalidSignature)?;
+    let signature = Signature::<P>::decode(&sig).ok_or(CommonError::InvalidSignature)?;
+    pk.verify(content, &signature)
+        .map_err(|_| CommonError::SignatureVerifyError)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use ml_dsa::{KeyGen, signature::Signer};
+
+    #[test]
+    fn verify_test() {
+        let kp = MlDsa65::key_gen_internal(&[7u8; 32].into());
+        let public = kp.verifying_key().encode();
+        let sig = kp.signing_key().sign(b"content").encode();
+        assert!(verify(MLDSA65_CODE, &public, b"content", &sig).is_ok());
+        assert!(verify(MLDSA65_CODE, &public, b"other", &sig).is_err());
+        assert!(verify(MLDSA44_CODE, &public, b"content", &sig).is_err());
+    }
+
+    #[test]
+    fn verify_hybrid_test() {
+        let ed = SigningKey::from_bytes(&[7u8; 32]);
+        let pq = MlDsa65::key_gen_internal(&[7u8; 32].into());
+        let mut public = ed.verifying_key().to_bytes().to_vec();
+        public.extend_from_slice(&pq.verifying_key().encode());
+        let mut sig = ed25519_dalek::Signer::sign(&ed, b"content").to_bytes().to_vec();
+        sig.extend_from_slice(&pq.signing_key().sign(b"content").encode());
+        assert!(verify_hybrid(&public, b"content", &sig).is_ok());
+
+        // Both parts are required
+        let classical_only = &sig[..ED25519_SIG_SIZE];
+        assert!(verify_hybrid(&public, b"content", classical_only).is_err());
+        let mut tampered = sig.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0xFF;
+        assert!(verify_hybrid(&public, b"content", &tampered).is_err());
+    }
+}
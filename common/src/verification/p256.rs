@@ -0,0 +1,35 @@
+use ::p256::ecdsa::{Signature, VerifyingKey, signature::Verifier};
+
+use crate::error::CommonError;
+
+/// Verifies an ECDSA P-256 (SHA-256) signature.
+///
+/// The public key is SEC1 encoded, the signature is either fixed size
+/// `r || s` or DER as produced by most secure elements.
+pub fn verify(public: &[u8], content: &[u8], sig: &[u8]) -> Result<(), CommonError> {
+    let pk = VerifyingKey::from_sec1_bytes(public).map_err(|_| CommonError::InvalidPublicKey)?;
+    let signature = match sig.len() {
+        64 => Signature::from_slice(sig),
+        _ => Signature::from_der(sig),
+    }
+    .map_err(|_| CommonError::InvalidSignature)?;
+    pk.verify(content, &signature)
+        .map_err(|_| CommonError::SignatureVerifyError)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::p256::ecdsa::{SigningKey, signature::Signer};
+
+    #[test]
+    fn verify_test() {
+        let sk = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let public = sk.verifying_key().to_encoded_point(true);
+        let sig: Signature = sk.sign(b"content");
+        assert!(verify(public.as_bytes(), b"content", &sig.to_bytes()).is_ok());
+        assert!(verify(public.as_bytes(), b"content", sig.to_der().as_bytes()).is_ok());
+        assert!(verify(public.as_bytes(), b"other", &sig.to_bytes()).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use k256::{ecdsa, schnorr, ecdsa::signature::Verifier};
+
+use crate::error::CommonError;
+
+/// Verifies a secp256k1 signature of a SEC1 encoded key, which is ECDSA.
+///
+/// BIP-340 Schnorr keys have their own codec and go to `verify_schnorr`.
+pub fn verify(public: &[u8], content: &[u8], sig: &[u8]) -> Result<(), CommonError> {
+    verify_ecdsa(public, content, sig)
+}
+
+/// Verifies an ECDSA (SHA-256) signature, high S values are rejected.
+pub fn verify_ecdsa(public: &[u8], content: &[u8], sig: &[u8]) -> Result<(), CommonError> {
+    let pk = ecdsa::VerifyingKey::from_sec1_bytes(public)
+        .map_err(|_| CommonError::InvalidPublicKey)?;
+    let signature = ecdsa::Signature::from_slice(sig)
+        .map_err(|_| CommonError::InvalidSignature)?;
+    pk.verify(content, &signature)
+        .map_err(|_| CommonError::SignatureVerifyError)?;
+    Ok(())
+}
+
+/// Verifies a BIP-340 Schnorr signature over the SHA-256 digest of content.
+pub fn verify_schnorr(public: &[u8], content: &[u8], sig: &[u8]) -> Result<(), CommonError> {
+    let pk = schnorr::VerifyingKey::from_bytes(public)
+        .map_err(|_| CommonError::InvalidPublicKey)?;
+    let signature = schnorr::Signature::try_from(sig)
+        .map_err(|_| CommonError::InvalidSignature)?;
+    pk.verify(content, &signature)
+        .map_err(|_| CommonError::SignatureVerifyError)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::signature::Signer;
+
+    #[test]
+    fn verify_ecdsa_test() {
+        let sk = ecdsa::SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let public = sk.verifying_key().to_encoded_point(true);
+        let sig: ecdsa::Signature = sk.sign(b"content");
+        assert!(verify(public.as_bytes(), b"content", &sig.to_bytes()).is_ok());
+        assert!(verify(public.as_bytes(), b"other", &sig.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn verify_schnorr_test() {
+        let sk = schnorr::SigningKey::from_bytes(&[7u8; 32]).unwrap();
+        let public = sk.verifying_key().to_bytes();
+        let sig: schnorr::Signature = sk.sign(b"content");
+        assert!(verify_schnorr(&public, b"content", &sig.to_bytes()).is_ok());
+        assert!(verify_schnorr(&public, b"other", &sig.to_bytes()).is_err());
+        // An x-only key is never taken for an ECDSA key
+        assert!(verify(&public, b"content", &sig.to_bytes()).is_err());
+    }
+}
@@ -2,4 +2,11 @@ pub mod utils;
 pub mod signer;
 pub mod error;
 pub mod inception;
-pub mod event;
\ No newline at end of file
+pub mod event;
+pub mod checkpoint;
+pub mod proof;
+pub mod key_manager;
+pub mod recovery;
+pub mod disclosure;
+pub mod archive;
+pub mod cesr;
\ No newline at end of file
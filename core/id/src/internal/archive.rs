@@ -0,0 +1,211 @@
+use alloc::{collections::BTreeMap, string::String};
+use cid::Cid;
+use core::str::FromStr;
+use idp2p_common::{CBOR_CODE, RAW_CODE, car::Car, cbor, error::CommonError};
+
+use super::{error::IdEventError, event::IdEvent};
+use crate::types::{IdEventReceipt, IdState};
+
+/// Full history of an identity, moved around as a CARv1 archive.
+///
+/// The archive holds every payload under its event id, every receipt as a
+/// CBOR block and the verifier component as a raw block. Its root is the
+/// latest event, so DAG-CBOR logs can be walked through `previous` links.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdArchive {
+    pub inception: IdEventReceipt,
+    pub events: Vec<IdEventReceipt>,
+    pub component: Option<Vec<u8>>,
+}
+
+impl IdArchive {
+    pub fn export(&self) -> Result<Vec<u8>, IdEventError> {
+        let latest = self.events.last().unwrap_or(&self.inception);
+        let mut car = Car::new(vec![Cid::from_str(&latest.id)?]);
+        for receipt in core::iter::once(&self.inception).chain(&self.events) {
+            let id = Cid::from_str(&receipt.id)?;
+            let payload_id = car.put(id.codec(), &receipt.payload)?;
+            if payload_id != id {
+                return Err(IdEventError::PayloadAndIdNotMatch);
+            }
+            car.put(CBOR_CODE, &cbor::encode(receipt)?)?;
+        }
+        if let Some(component) = &self.component {
+            car.put(RAW_CODE, component)?;
+        }
+        Ok(car.to_bytes()?)
+    }
+
+    /// Reads an archive and replays its log from the root back to the
+    /// inception, the archive is only returned when the whole log verifies.
+    pub fn import(bytes: &[u8]) -> Result<(Self, IdState), IdEventError> {
+        let car = Car::from_bytes(bytes)?;
+        let [root] = car.roots.as_slice() else {
+            return Err(CommonError::InvalidCar("Expected a single root".into()).into());
+        };
+        let mut receipts: BTreeMap<String, IdEventReceipt> = BTreeMap::new();
+        let mut component = None;
+        for (cid, block) in &car.blocks {
+            match cid.codec() {
+                CBOR_CODE => {
+                    // Payload blocks don't decode as receipts
+                    if let Ok(receipt) = cbor::decode::<IdEventReceipt>(block) {
+                        receipts.insert(receipt.id.clone(), receipt);
+                    }
+                }
+                RAW_CODE if component.is_none() => component = Some(block.clone()),
+                RAW_CODE => {
+                    return Err(CommonError::InvalidCar("More than one component".into()).into());
+                }
+                _ => {}
+            }
+        }
+        let mut events = vec![];
+        let mut id = root.to_string();
+        let inception = loop {
+            let receipt = receipts
+                .remove(&id)
+                .ok_or_else(|| IdEventError::InvalidEventId(id.clone()))?;
+            let codec = Cid::from_str(&receipt.id)?.codec();
+            match IdEvent::from_payload(&receipt.payload, codec) {
+                Ok(event) => {
+                    id = event.previous;
+                    events.push(receipt);
+                }
+                Err(_) => break receipt,
+            }
+        };
+        events.reverse();
+        let state = inception.verify_log(&events)?;
+        let archive = Self {
+            inception,
+            events,
+            component,
+        };
+        Ok((archive, state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        VERSION,
+        internal::{
+            event::IdEventKind,
+            inception::IdInception,
+            proof::IdProofPurpose,
+            signer::{IdKeyPurpose, IdSigner},
+        },
+        types::IdProof,
+    };
+    use alloc::collections::BTreeSet;
+    use chrono::Utc;
+    use idp2p_common::{DAG_CBOR_CODE, ED_CODE, cid::CidExt, crypto::signer::Signer};
+    use rand::rngs::OsRng;
+
+    fn receipt(signer: &Signer, did: Option<&str>, payload: Vec<u8>, codec: u64) -> IdEventReceipt {
+        let id = Cid::create(codec, &payload).unwrap().to_string();
+        let did = did.unwrap_or(&id);
+        let proof = IdProof::create(
+            did,
+            signer,
+            &Utc::now(),
+            IdProofPurpose::EventSigning,
+            &payload,
+        )
+        .unwrap();
+        IdEventReceipt {
+            id,
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload,
+            proofs: vec![proof],
+        }
+    }
+
+    fn log(codec: u64, len: u64) -> IdArchive {
+        let signer = Signer::generate(ED_CODE, &mut OsRng).unwrap();
+        let inception = IdInception {
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: Utc::now().timestamp(),
+            prior_id: None,
+            threshold: 1,
+            next_threshold: 1,
+            signers: BTreeSet::from([IdSigner {
+                id: signer.id.clone(),
+                public_key: signer.public_key.clone(),
+                purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
+            }]),
+            next_signers: BTreeSet::from([signer.id.clone()]),
+            delegated_signers: BTreeSet::new(),
+            merkle_proof: "inception-proof".into(),
+        };
+        let inception = receipt(&signer, None, inception.to_payload(codec).unwrap(), codec);
+        let mut events: Vec<IdEventReceipt> = vec![];
+        for sn in 1..=len {
+            let event = IdEvent {
+                sn,
+                version: VERSION.into(),
+                patch: Cid::default(),
+                timestamp: Utc::now().timestamp(),
+                previous: events.last().unwrap_or(&inception).id.clone(),
+                body: IdEventKind::Interaction {
+                    merkle_proof: format!("proof-{sn}"),
+                },
+            };
+            let payload = event.to_payload(codec).unwrap();
+            events.push(receipt(&signer, Some(&inception.id), payload, codec));
+        }
+        IdArchive {
+            inception,
+            events,
+            component: Some(b"\0asm component".to_vec()),
+        }
+    }
+
+    #[test]
+    fn export_import_test() {
+        for codec in [CBOR_CODE, DAG_CBOR_CODE] {
+            let archive = log(codec, 3);
+            let bytes = archive.export().unwrap();
+            let car = Car::from_bytes(&bytes).unwrap();
+            assert_eq!(car.roots[0].to_string(), archive.events[2].id);
+            let (imported, state) = IdArchive::import(&bytes).unwrap();
+            assert_eq!(imported, archive);
+            assert_eq!(state.sn, 3);
+            assert_eq!(state.event_id, archive.events[2].id);
+        }
+        let inception_only = IdArchive {
+            component: None,
+            ..log(CBOR_CODE, 0)
+        };
+        let (imported, state) = IdArchive::import(&inception_only.export().unwrap()).unwrap();
+        assert_eq!(imported, inception_only);
+        assert_eq!(state.sn, 0);
+    }
+
+    #[test]
+    fn tampered_receipt_rejected_test() {
+        let mut archive = log(CBOR_CODE, 2);
+        archive.events[0].proofs[0].signature[0] ^= 1;
+        let bytes = archive.export().unwrap();
+        assert!(matches!(
+            IdArchive::import(&bytes),
+            Err(IdEventError::InvalidProof { .. })
+        ));
+    }
+
+    #[test]
+    fn missing_event_rejected_test() {
+        let archive = log(CBOR_CODE, 2);
+        let mut car = Car::from_bytes(&archive.export().unwrap()).unwrap();
+        let receipt = cbor::encode(&archive.events[0]).unwrap();
+        car.blocks.retain(|(_, block)| *block != receipt);
+        assert!(matches!(
+            IdArchive::import(&car.to_bytes().unwrap()),
+            Err(IdEventError::InvalidEventId(_))
+        ));
+    }
+}
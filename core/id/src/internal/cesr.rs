@@ -0,0 +1,338 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use cid::Cid;
+use core::str::FromStr;
+use idp2p_common::{
+    CBOR_CODE, cbor,
+    cesr::{self, Domain, Group, Indexer, IndexerCode},
+    cid::CidExt,
+    error::CommonError,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{error::IdEventError, proof::IdProofPurpose};
+use crate::types::{IdEventReceipt, IdProof, IdSigner};
+
+/// An event body followed by its signatures as a CESR attachment group.
+///
+/// Signatures are indexed by the position of their key in the signer list
+/// of the state, the way KERI indexes controller signatures. They are the
+/// proof signatures of the receipt, which also cover the identity and the
+/// proof creation times, so these travel next to the stream as an
+/// `IdCesrReceiptInfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdCesrMessage {
+    pub payload: Vec<u8>,
+    pub signatures: Vec<Indexer>,
+}
+
+/// Receipt fields an `IdCesrMessage` doesn't carry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdCesrReceiptInfo {
+    pub id: String,
+    pub version: String,
+    pub created_at: String,
+    /// Identity the proofs were made for
+    pub did: String,
+    /// Creation time of each proof, in signature order
+    pub created: Vec<String>,
+}
+
+impl IdCesrReceiptInfo {
+    /// Only raw event signing proofs of one identity can be streamed.
+    pub fn from_receipt(receipt: &IdEventReceipt) -> Result<Self, IdEventError> {
+        let did = receipt.proofs.first().map(|p| p.did.clone()).unwrap_or_default();
+        let created = receipt
+            .proofs
+            .iter()
+            .map(|proof| {
+                let raw = proof.did == did
+                    && proof.format.is_none()
+                    && proof.previous.is_none()
+                    && proof.purpose()? == IdProofPurpose::EventSigning;
+                if !raw {
+                    return Err(IdEventError::invalid_proof(&proof.key_id, "not a raw event proof"));
+                }
+                Ok(proof.created.clone())
+            })
+            .collect::<Result<_, IdEventError>>()?;
+        Ok(Self {
+            id: receipt.id.clone(),
+            version: receipt.version.clone(),
+            created_at: receipt.created_at.clone(),
+            did,
+            created,
+        })
+    }
+}
+
+impl IdCesrMessage {
+    pub fn from_receipt(
+        receipt: &IdEventReceipt,
+        signers: &[IdSigner],
+    ) -> Result<Self, IdEventError> {
+        let signatures = receipt
+            .proofs
+            .iter()
+            .map(|proof| {
+                let index = signers
+                    .iter()
+                    .position(|s| s.id == proof.key_id)
+                    .ok_or_else(|| IdEventError::InvalidSigner(proof.key_id.clone()))?;
+                let codec = Cid::from_str(&proof.key_id)?.codec();
+                let code = IndexerCode::from_key(codec, &signers[index].public_key)
+                    .ok_or_else(|| IdEventError::invalid_proof(&proof.key_id, "no CESR code"))?;
+                Ok(Indexer::new(code, index, &proof.signature)?)
+            })
+            .collect::<Result<_, IdEventError>>()?;
+        Ok(Self {
+            payload: receipt.payload.clone(),
+            signatures,
+        })
+    }
+
+    /// CBOR body followed by a `-V` group holding the `-A` signatures.
+    pub fn to_bytes(&self, domain: Domain) -> Result<Vec<u8>, IdEventError> {
+        let group = Group::ControllerIdxSigs(self.signatures.clone());
+        let mut bytes = self.payload.clone();
+        bytes.extend(cesr::encode_attachments(&[group], domain)?);
+        Ok(bytes)
+    }
+
+    /// Rebuilds the receipt the message was made from.
+    ///
+    /// `signers` are the ones the signatures are indexed by, as given to
+    /// `from_receipt`. The receipt is not verified.
+    pub fn into_receipt(
+        self,
+        signers: &[IdSigner],
+        info: &IdCesrReceiptInfo,
+    ) -> Result<IdEventReceipt, IdEventError> {
+        if info.created.len() != self.signatures.len() {
+            return Err(CommonError::InvalidCesr("proof count mismatch".into()).into());
+        }
+        let proof_id = Cid::create(CBOR_CODE, &self.payload)?.to_string();
+        let proofs = self
+            .signatures
+            .into_iter()
+            .zip(&info.created)
+            .map(|(sig, created)| {
+                let signer = signers
+                    .get(sig.index as usize)
+                    .ok_or_else(|| IdEventError::InvalidSigner(sig.index.to_string()))?;
+                Ok(IdProof {
+                    id: proof_id.clone(),
+                    did: info.did.clone(),
+                    key_id: signer.id.clone(),
+                    created: created.clone(),
+                    purpose: IdProofPurpose::EventSigning.as_ref().into(),
+                    signature: sig.raw,
+                    previous: None,
+                    format: None,
+                })
+            })
+            .collect::<Result<_, IdEventError>>()?;
+        Ok(IdEventReceipt {
+            id: info.id.clone(),
+            version: info.version.clone(),
+            created_at: info.created_at.clone(),
+            payload: self.payload,
+            proofs,
+        })
+    }
+
+    /// Reads a message from the start of a stream in either domain, returns
+    /// it with the number of bytes consumed.
+    pub fn from_bytes(stream: &[u8]) -> Result<(Self, usize), IdEventError> {
+        let body = cbor::item_len(stream)?;
+        let (groups, attached) = cesr::parse_attachments(&stream[body..])?;
+        let signatures = groups
+            .into_iter()
+            .filter_map(|group| match group {
+                Group::ControllerIdxSigs(sigs) => Some(sigs),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        let message = Self {
+            payload: stream[..body].to_vec(),
+            signatures,
+        };
+        Ok((message, body + attached))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        VERSION,
+        internal::{
+            event::{IdEvent, IdEventKind},
+            inception::IdInception,
+            signer::{IdKeyPurpose, IdSigner as InternalSigner},
+        },
+        types::IdState,
+    };
+    use alloc::collections::BTreeSet;
+    use chrono::Utc;
+    use idp2p_common::{ED_CODE, P256_CODE, SECP256K1_CODE, crypto::signer::Signer};
+    use rand::rngs::OsRng;
+
+    fn inception(signers: &[Signer]) -> IdEventReceipt {
+        let inception = IdInception {
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: Utc::now().timestamp(),
+            prior_id: None,
+            threshold: signers.len() as u8,
+            next_threshold: 1,
+            signers: signers
+                .iter()
+                .map(|s| InternalSigner {
+                    id: s.id.clone(),
+                    public_key: s.public_key.clone(),
+                    purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
+                })
+                .collect(),
+            next_signers: BTreeSet::from([signers[0].id.clone()]),
+            delegated_signers: BTreeSet::new(),
+            merkle_proof: "inception-proof".into(),
+        };
+        let payload = inception.to_payload(CBOR_CODE).unwrap();
+        let id = Cid::create(CBOR_CODE, &payload).unwrap().to_string();
+        let proofs = signers
+            .iter()
+            .map(|signer| {
+                let purpose = IdProofPurpose::EventSigning;
+                IdProof::create(&id, signer, &Utc::now(), purpose, &payload).unwrap()
+            })
+            .collect();
+        IdEventReceipt {
+            id,
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload,
+            proofs,
+        }
+    }
+
+    fn interaction(state: &IdState, signers: &[Signer]) -> IdEventReceipt {
+        let event = IdEvent {
+            sn: state.sn + 1,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: Utc::now().timestamp(),
+            previous: state.event_id.clone(),
+            body: IdEventKind::Interaction {
+                merkle_proof: "interaction-proof".into(),
+            },
+        };
+        let payload = event.to_payload(CBOR_CODE).unwrap();
+        let proofs = signers
+            .iter()
+            .map(|signer| {
+                let purpose = IdProofPurpose::EventSigning;
+                IdProof::create(&state.id, signer, &Utc::now(), purpose, &payload).unwrap()
+            })
+            .collect();
+        IdEventReceipt {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload,
+            proofs,
+        }
+    }
+
+    /// Streams a receipt and rebuilds it on the other side.
+    fn roundtrip(receipt: &IdEventReceipt, signers: &[IdSigner], domain: Domain) -> IdEventReceipt {
+        let bytes = IdCesrMessage::from_receipt(receipt, signers)
+            .unwrap()
+            .to_bytes(domain)
+            .unwrap();
+        let info = IdCesrReceiptInfo::from_receipt(receipt).unwrap();
+        let (message, _) = IdCesrMessage::from_bytes(&bytes).unwrap();
+        message.into_receipt(signers, &info).unwrap()
+    }
+
+    #[test]
+    fn receipt_roundtrip_test() {
+        let signers = [
+            Signer::generate(ED_CODE, &mut OsRng).unwrap(),
+            Signer::generate(P256_CODE, &mut OsRng).unwrap(),
+        ];
+        let inception = inception(&signers);
+        let state = inception.verify_inception().unwrap();
+        let event = interaction(&state, &signers);
+        let next = event.verify_event(&mut state.clone()).unwrap();
+        for domain in [Domain::Text, Domain::Binary] {
+            let received = roundtrip(&inception, &state.signers, domain);
+            assert_eq!(received, inception);
+            assert_eq!(received.verify_inception().unwrap(), state);
+            let received = roundtrip(&event, &state.signers, domain);
+            assert_eq!(received, event);
+            assert_eq!(received.verify_event(&mut state.clone()).unwrap(), next);
+        }
+
+        // The proof creation times are signed
+        let message = IdCesrMessage::from_receipt(&event, &state.signers).unwrap();
+        let mut info = IdCesrReceiptInfo::from_receipt(&event).unwrap();
+        info.created[0] = "2025-01-02T00:00:00+00:00".into();
+        let forged = message.clone().into_receipt(&state.signers, &info).unwrap();
+        assert!(forged.verify_event(&mut state.clone()).is_err());
+        info.created.pop();
+        assert!(message.into_receipt(&state.signers, &info).is_err());
+    }
+
+    #[test]
+    fn receipt_attachment_test() {
+        let signers = [
+            Signer::generate(ED_CODE, &mut OsRng).unwrap(),
+            Signer::generate(P256_CODE, &mut OsRng).unwrap(),
+            Signer::generate(SECP256K1_CODE, &mut OsRng).unwrap(),
+        ];
+        let receipt = inception(&signers);
+        let state = receipt.verify_log(&[]).unwrap();
+        let message = IdCesrMessage::from_receipt(&receipt, &state.signers).unwrap();
+        for (proof, sig) in receipt.proofs.iter().zip(&message.signatures) {
+            assert_eq!(state.signers[sig.index as usize].id, proof.key_id);
+            assert_eq!(sig.raw, proof.signature);
+        }
+        for domain in [Domain::Text, Domain::Binary] {
+            let bytes = message.to_bytes(domain).unwrap();
+            assert!(bytes.starts_with(&receipt.payload));
+            // Messages follow each other in a stream
+            let stream = [bytes.clone(), bytes.clone()].concat();
+            let (decoded, len) = IdCesrMessage::from_bytes(&stream).unwrap();
+            assert_eq!(len, bytes.len());
+            assert_eq!(decoded, message);
+            assert_eq!(
+                IdCesrMessage::from_bytes(&stream[len..]).unwrap().0,
+                message
+            );
+            assert!(IdCesrMessage::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        }
+        let text = message.to_bytes(Domain::Text).unwrap();
+        assert!(text[receipt.payload.len()..].starts_with(b"-VBD-AAD"));
+    }
+
+    #[test]
+    fn unsupported_signer_test() {
+        let signer = Signer::generate(ED_CODE, &mut OsRng).unwrap();
+        let receipt = inception(&[signer]);
+        assert!(matches!(
+            IdCesrMessage::from_receipt(&receipt, &[]),
+            Err(IdEventError::InvalidSigner(_))
+        ));
+        // Schnorr keys have no CESR code
+        let receipt = inception(&[Signer::new_schnorr(&[7u8; 32]).unwrap()]);
+        let state = receipt.verify_log(&[]).unwrap();
+        assert!(matches!(
+            IdCesrMessage::from_receipt(&receipt, &state.signers),
+            Err(IdEventError::InvalidProof { .. })
+        ));
+    }
+}
@@ -0,0 +1,23 @@
+use alloc::string::String;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct IdCheckpoint {
+    /// Identifier
+    pub id: String,
+
+    /// Checkpoint version
+    pub version: String,
+
+    /// Event number of the checkpointed event
+    pub sn: u64,
+
+    /// Checkpointed event id
+    pub event_id: String,
+
+    /// CID of the state after the checkpointed event
+    pub state_cid: String,
+
+    /// Timestamp of checkpoint
+    pub timestamp: i64,
+}
@@ -0,0 +1,243 @@
+use alloc::string::String;
+use idp2p_common::{
+    bytes::Bytes,
+    cbor,
+    merkle::{self, MerkleProof, MerkleTree},
+};
+use rand_core::CryptoRngCore;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use super::error::IdEventError;
+use crate::types::IdState;
+
+const CLAIM_DOMAIN: &[u8] = b"idp2p/claim/v1";
+pub const SALT_SIZE: usize = 32;
+
+/// A committed attribute, e.g. the mediator of an identity.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdClaim {
+    pub key: String,
+    #[serde_as(as = "Bytes")]
+    pub value: Vec<u8>,
+}
+
+/// A claim with the random salt of its leaf.
+///
+/// The salt keeps unrevealed claims from being guessed from the root or
+/// the sibling hashes of a proof.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdSaltedClaim {
+    pub claim: IdClaim,
+    #[serde_as(as = "Bytes")]
+    pub salt: Vec<u8>,
+}
+
+impl IdSaltedClaim {
+    pub fn new(key: &str, value: &[u8], rng: &mut impl CryptoRngCore) -> Self {
+        let mut salt = vec![0u8; SALT_SIZE];
+        rng.fill_bytes(&mut salt);
+        Self {
+            claim: IdClaim {
+                key: key.into(),
+                value: value.to_vec(),
+            },
+            salt,
+        }
+    }
+
+    /// Leaf data of the claim in the tree.
+    pub fn leaf(&self) -> Result<Vec<u8>, IdEventError> {
+        Ok([CLAIM_DOMAIN, &cbor::encode(self)?].concat())
+    }
+}
+
+/// Claims committed together in `merkle_proof` of an event, kept by the
+/// identity holder.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdClaimSet {
+    pub claims: Vec<IdSaltedClaim>,
+}
+
+impl IdClaimSet {
+    pub fn add(&mut self, key: &str, value: &[u8], rng: &mut impl CryptoRngCore) {
+        self.claims.push(IdSaltedClaim::new(key, value, rng));
+    }
+
+    fn tree(&self) -> Result<MerkleTree, IdEventError> {
+        let leaves = self
+            .claims
+            .iter()
+            .map(IdSaltedClaim::leaf)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(MerkleTree::new(&leaves))
+    }
+
+    /// Root for `merkle_proof` of an inception or interaction event.
+    pub fn commitment(&self) -> Result<String, IdEventError> {
+        Ok(merkle::encode_root(&self.tree()?.root()))
+    }
+
+    /// Discloses the claims with the given keys, committed by the event.
+    pub fn disclose(
+        &self,
+        id: &str,
+        event_id: &str,
+        keys: &[&str],
+    ) -> Result<IdDisclosure, IdEventError> {
+        let tree = self.tree()?;
+        let mut claims = vec![];
+        for key in keys {
+            let (index, salted) = self
+                .claims
+                .iter()
+                .enumerate()
+                .find(|(_, c)| c.claim.key == *key)
+                .ok_or_else(|| IdEventError::InvalidClaim((*key).into()))?;
+            claims.push(IdDisclosedClaim {
+                claim: salted.clone(),
+                proof: tree.proof(index)?.to_bytes(),
+            });
+        }
+        Ok(IdDisclosure {
+            id: id.into(),
+            event_id: event_id.into(),
+            claims,
+        })
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdDisclosedClaim {
+    pub claim: IdSaltedClaim,
+    /// Serialized `MerkleProof` of the leaf
+    #[serde_as(as = "Bytes")]
+    pub proof: Vec<u8>,
+}
+
+/// Claims revealed from the commitment of an identity at an event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdDisclosure {
+    pub id: String,
+    pub event_id: String,
+    pub claims: Vec<IdDisclosedClaim>,
+}
+
+impl IdDisclosure {
+    /// Verifies the claims against the root of the state resolved at the
+    /// disclosed event.
+    pub fn verify(&self, state: &IdState) -> Result<Vec<IdClaim>, IdEventError> {
+        if state.id != self.id {
+            return Err(IdEventError::IdNotMatch(self.id.clone()));
+        }
+        if state.event_id != self.event_id {
+            return Err(IdEventError::InvalidEventId(self.event_id.clone()));
+        }
+        let root = merkle::decode_root(&state.merkle_proof)?;
+        self.claims
+            .iter()
+            .map(|disclosed| {
+                let salted = &disclosed.claim;
+                if salted.salt.len() != SALT_SIZE {
+                    return Err(IdEventError::InvalidClaim(salted.claim.key.clone()));
+                }
+                MerkleProof::from_bytes(&disclosed.proof)?
+                    .verify(&salted.leaf()?, &root)
+                    .map_err(|_| IdEventError::InvalidClaim(salted.claim.key.clone()))?;
+                Ok(salted.claim.clone())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn claims() -> IdClaimSet {
+        let mut set = IdClaimSet::default();
+        set.add("mediator", b"did:p2p:mediator", &mut OsRng);
+        set.add(
+            "key-agreement",
+            b"z6LSbysY2xFMRpGMhb7tFTLMpeuPRaqaWM1yECx2AtzE3KCc",
+            &mut OsRng,
+        );
+        set.add("email", b"alice@example.com", &mut OsRng);
+        set
+    }
+
+    fn state(merkle_proof: String) -> IdState {
+        IdState {
+            id: "did:p2p:alice".into(),
+            sn: 1,
+            event_id: "event".into(),
+            event_timestamp: String::new(),
+            prior_id: None,
+            next_id_proof: None,
+            threshold: 1,
+            next_threshold: 1,
+            signers: vec![],
+            next_signers: vec![],
+            delegated_signers: vec![],
+            merkle_proof,
+            revoked: false,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn disclosure_test() {
+        let set = claims();
+        let state = state(set.commitment().unwrap());
+        let disclosure = set.disclose(&state.id, "event", &["mediator"]).unwrap();
+        let revealed = disclosure.verify(&state).unwrap();
+        assert_eq!(revealed.len(), 1);
+        assert_eq!(revealed[0].key, "mediator");
+        assert_eq!(revealed[0].value, b"did:p2p:mediator");
+
+        let encoded = cbor::encode(&disclosure).unwrap();
+        let decoded: IdDisclosure = cbor::decode(&encoded).unwrap();
+        assert_eq!(decoded.verify(&state).unwrap(), revealed);
+        assert!(set.disclose(&state.id, "event", &["phone"]).is_err());
+    }
+
+    #[test]
+    fn tampered_claim_test() {
+        let set = claims();
+        let state = state(set.commitment().unwrap());
+        let disclosure = set.disclose(&state.id, "event", &["email"]).unwrap();
+
+        let mut forged = disclosure.clone();
+        forged.claims[0].claim.claim.value = b"mallory@example.com".to_vec();
+        assert!(matches!(
+            forged.verify(&state),
+            Err(IdEventError::InvalidClaim(_))
+        ));
+
+        let mut unsalted = disclosure.clone();
+        unsalted.claims[0].claim.salt = vec![];
+        assert!(unsalted.verify(&state).is_err());
+    }
+
+    #[test]
+    fn other_event_test() {
+        let set = claims();
+        let disclosure = set.disclose("did:p2p:alice", "event", &["email"]).unwrap();
+        let mut other = state(set.commitment().unwrap());
+        other.event_id = "later".into();
+        assert!(matches!(
+            disclosure.verify(&other),
+            Err(IdEventError::InvalidEventId(_))
+        ));
+        // Same claims with fresh salts commit to another root
+        assert!(
+            disclosure
+                .verify(&state(claims().commitment().unwrap()))
+                .is_err()
+        );
+    }
+}
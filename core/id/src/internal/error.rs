@@ -17,6 +17,8 @@ pub enum IdEventError {
     PayloadAndIdNotMatch,
     #[error("Previous not match")]
     PreviousNotMatch,
+    #[error("Invalid sequence number: {0}")]
+    InvalidSn(u64),
     #[error("Threshold not match")]
     ThresholdNotMatch,
     #[error("Next threshold not match")]
@@ -29,8 +31,16 @@ pub enum IdEventError {
     InvalidSigner(String),
     #[error("Invalid next signer: {0}")]
     InvalidNextSigner(String),
+    #[error("Key can not be recovered: {0}")]
+    UnrecoverableKey(String),
+    #[error("Recovery failed: {0}")]
+    RecoveryFailed(String),
     #[error("Invalid claim: {0}")]
     InvalidClaim(String),
+    #[error("Identifier not match: {0}")]
+    IdNotMatch(String),
+    #[error("Invalid checkpoint: {0}")]
+    InvalidCheckpoint(String),
     #[error("Invalid delegation id: {0}")]
     InvalidDelegationId(String),
     #[error("Invalid CID: {0}")]
@@ -1,13 +1,18 @@
 use alloc::collections::BTreeSet;
 
-use crate::internal::signer::IdSigner;
+use crate::internal::{
+    error::IdEventError,
+    signer::IdSigner,
+    utils::{decode_payload, encode_payload},
+};
 use alloc::string::String;
 use cid::Cid;
+use idp2p_common::dagcbor::Link;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum IdEventKind {
-    /// Should be signed with state.current_signers
+    /// Should be signed with state.signers
     /// The total number of signers should be greater than or equal the state.threshold
     Interaction {
         merkle_proof: String
@@ -66,3 +71,17 @@ pub struct IdEvent {
     /// Event body
     pub body: IdEventKind,
 }
+
+/// Fields of an event that are CID links in DAG-CBOR.
+pub const EVENT_LINKS: &[Link] = &[Link::Cid("patch"), Link::Text("previous")];
+
+impl IdEvent {
+    /// Payload of the event in `CBOR_CODE` or `DAG_CBOR_CODE`.
+    pub fn to_payload(&self, codec: u64) -> Result<Vec<u8>, IdEventError> {
+        encode_payload(self, codec, EVENT_LINKS)
+    }
+
+    pub fn from_payload(bytes: &[u8], codec: u64) -> Result<Self, IdEventError> {
+        decode_payload(bytes, codec, EVENT_LINKS)
+    }
+}
@@ -1,7 +1,12 @@
-use super::signer::IdSigner;
+use super::{
+    error::IdEventError,
+    signer::IdSigner,
+    utils::{decode_payload, encode_payload},
+};
 use alloc::collections::BTreeSet;
 use alloc::string::String;
 use cid::Cid;
+use idp2p_common::dagcbor::Link;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -19,3 +24,17 @@ pub struct IdInception {
     pub delegated_signers: BTreeSet<String>,
     pub merkle_proof: String
 }
+
+/// Fields of an inception that are CID links in DAG-CBOR.
+pub const INCEPTION_LINKS: &[Link] = &[Link::Cid("patch"), Link::Text("prior_id")];
+
+impl IdInception {
+    /// Payload of the inception in `CBOR_CODE` or `DAG_CBOR_CODE`.
+    pub fn to_payload(&self, codec: u64) -> Result<Vec<u8>, IdEventError> {
+        encode_payload(self, codec, INCEPTION_LINKS)
+    }
+
+    pub fn from_payload(bytes: &[u8], codec: u64) -> Result<Self, IdEventError> {
+        decode_payload(bytes, codec, INCEPTION_LINKS)
+    }
+}
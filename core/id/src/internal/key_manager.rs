@@ -0,0 +1,712 @@
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use chrono::{DateTime, Utc};
+use core::str::FromStr;
+use idp2p_common::{
+    ED_CODE, SHA2_256_CODE,
+    cid::CidExt,
+    crypto::{
+        hd::{HdPath, HdSeed},
+        keystore::{KeyMetadata, KeyRole, Keystore, StoreKey},
+        signer::Signer,
+    },
+};
+use cid::Cid;
+use rand_core::CryptoRngCore;
+
+use super::{
+    error::IdEventError,
+    event::IdEventKind,
+    proof::IdProofPurpose,
+    signer::{IdKeyPurpose, IdSigner},
+};
+use crate::types::{IdEventReceipt, IdProof, IdState};
+
+/// Keys per set searched when recovering from a mnemonic.
+pub const MAX_HD_SLOTS: u32 = 16;
+
+/// Thresholds and keys to generate for an inception or rotation.
+#[derive(Debug, Clone)]
+pub struct IdKeyPlan {
+    pub threshold: u8,
+    /// Current keys to generate, on rotation in addition to the revealed ones
+    pub new_signers: u8,
+    pub next_threshold: u8,
+    /// Next keys to commit to
+    pub next_signers: u8,
+    pub purposes: BTreeSet<IdKeyPurpose>,
+}
+
+/// Key sets of a new identity.
+#[derive(Debug, Clone)]
+pub struct IdKeySet {
+    pub threshold: u8,
+    pub next_threshold: u8,
+    pub signers: BTreeSet<IdSigner>,
+    /// Commitments of the next signers
+    pub next_signers: BTreeSet<String>,
+}
+
+/// Body of a key event and the signers that must sign it.
+#[derive(Debug)]
+pub struct IdKeyEvent {
+    pub body: IdEventKind,
+    pub signers: Vec<Signer>,
+}
+
+impl IdKeyEvent {
+    /// Event signing proofs of every signer over the event payload.
+    pub fn proofs(
+        &self,
+        did: &str,
+        created: &DateTime<Utc>,
+        payload: &[u8],
+    ) -> Result<Vec<IdProof>, IdEventError> {
+        self.signers
+            .iter()
+            .map(|s| IdProof::create(did, s, created, IdProofPurpose::EventSigning, payload))
+            .collect()
+    }
+}
+
+/// Tracks the current and pre-rotated next keys of identities in a keystore.
+///
+/// Next keys are Ed25519, only their CIDs (`CidExt::create(ED_CODE, ..)`)
+/// are published as commitments. Events are built from what the keystore
+/// holds for the given state, so revealed and next sets can't be mixed up.
+/// After an event is verified, `sync` with the new state promotes revealed
+/// keys and retires spent ones.
+///
+/// With a mnemonic seed keys are derived at the `crypto::hd` paths of one
+/// identity account instead of being random, so `recover` can rebuild them.
+///
+/// Signer ids, next key commitments and `event_id`s are hashed with the
+/// manager's hash, SHA2-256 unless set with `with_hash`. Verifiers read the
+/// hash from each CID, so new identities can move to another hash.
+pub struct IdKeyManager {
+    pub keystore: Keystore,
+    key: StoreKey,
+    hd: Option<(HdSeed, u32)>,
+    hash_code: u64,
+}
+
+fn metadata(did: Option<&str>, purposes: &BTreeSet<IdKeyPurpose>, role: KeyRole) -> KeyMetadata {
+    KeyMetadata {
+        did: did.map(Into::into),
+        purposes: purposes.iter().map(|p| p.as_ref().into()).collect(),
+        role,
+        commitment: None,
+        path: None,
+    }
+}
+
+fn entry_path(metadata: &KeyMetadata) -> Option<HdPath> {
+    metadata.path.as_deref().and_then(|p| HdPath::from_str(p).ok())
+}
+
+impl IdKeyManager {
+    pub fn new(keystore: Keystore, key: StoreKey) -> Self {
+        Self {
+            keystore,
+            key,
+            hd: None,
+            hash_code: SHA2_256_CODE,
+        }
+    }
+
+    /// Manager deriving the keys of identity account `identity` from a seed.
+    pub fn with_seed(keystore: Keystore, key: StoreKey, seed: HdSeed, identity: u32) -> Self {
+        Self {
+            keystore,
+            key,
+            hd: Some((seed, identity)),
+            hash_code: SHA2_256_CODE,
+        }
+    }
+
+    /// Hashes new ids with `hash_code`, one of `HASH_CODES`.
+    pub fn with_hash(mut self, hash_code: u64) -> Self {
+        self.hash_code = hash_code;
+        self
+    }
+
+    /// Id of an inception or event payload encoded with `codec`.
+    pub fn event_id(&self, codec: u64, payload: &[u8]) -> Result<String, IdEventError> {
+        Ok(Cid::create_with(codec, self.hash_code, payload)?.to_string())
+    }
+
+    /// Stored HD paths of the identity account.
+    fn paths(&self) -> impl Iterator<Item = (HdPath, &KeyMetadata)> {
+        let identity = self.hd.as_ref().map(|(_, i)| *i);
+        self.keystore.entries.iter().filter_map(move |e| {
+            entry_path(&e.metadata)
+                .filter(|p| Some(p.identity) == identity)
+                .map(|p| (p, &e.metadata))
+        })
+    }
+
+    /// Rotation index of the stored next keys of an identity.
+    fn rotation_index(&self, did: &str) -> u32 {
+        self.paths()
+            .filter(|(_, m)| m.did.as_deref() == Some(did))
+            .map(|(p, _)| p.rotation)
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn generate(
+        &mut self,
+        did: Option<&str>,
+        purposes: &BTreeSet<IdKeyPurpose>,
+        role: KeyRole,
+        rotation: u32,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<Signer, IdEventError> {
+        let mut metadata = metadata(did, purposes, role);
+        let signer = match &self.hd {
+            Some((seed, identity)) => {
+                let slot = self
+                    .paths()
+                    .filter(|(p, _)| p.rotation == rotation)
+                    .map(|(p, _)| p.slot + 1)
+                    .max()
+                    .unwrap_or_default();
+                let path = HdPath {
+                    identity: *identity,
+                    purpose: purposes.first().map_or(0, |p| p.hd_index()),
+                    rotation,
+                    slot,
+                };
+                metadata.path = Some(path.to_string());
+                seed.signer(&path)?
+            }
+            None => Signer::generate(ED_CODE, rng)?,
+        }
+        .with_hash(self.hash_code)?;
+        if role == KeyRole::Next {
+            metadata.commitment = Some(signer.id.clone());
+        }
+        self.keystore.insert(&self.key, &signer, metadata, rng)?;
+        Ok(signer)
+    }
+
+    fn generate_next(
+        &mut self,
+        did: Option<&str>,
+        count: u8,
+        purposes: &BTreeSet<IdKeyPurpose>,
+        rotation: u32,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<BTreeSet<String>, IdEventError> {
+        let mut commitments = BTreeSet::new();
+        for _ in 0..count {
+            commitments.insert(self.generate(did, purposes, KeyRole::Next, rotation, rng)?.id);
+        }
+        Ok(commitments)
+    }
+
+    fn id_signer(&self, signer: &Signer) -> Result<IdSigner, IdEventError> {
+        let entry = self
+            .keystore
+            .get(&signer.id)
+            .ok_or_else(|| IdEventError::InvalidSigner(signer.id.clone()))?;
+        let purposes = entry
+            .metadata
+            .purposes
+            .iter()
+            .map(|p| IdKeyPurpose::from_str(p))
+            .collect::<Result<_, _>>()
+            .map_err(|_| IdEventError::InvalidSigner(signer.id.clone()))?;
+        Ok(IdSigner {
+            id: signer.id.clone(),
+            public_key: signer.public_key.clone(),
+            purposes,
+        })
+    }
+
+    /// Stored signers of the identity with the role.
+    pub fn signers(&self, did: &str, role: KeyRole) -> Result<Vec<Signer>, IdEventError> {
+        self.keystore
+            .entries
+            .iter()
+            .filter(|e| e.metadata.did.as_deref() == Some(did) && e.metadata.role == role)
+            .map(|e| Ok(self.keystore.signer(&self.key, &e.id)?))
+            .collect()
+    }
+
+    /// Next keys committed to in the state, at least `next_threshold` of them.
+    fn revealed(&self, state: &IdState) -> Result<Vec<Signer>, IdEventError> {
+        let revealed: Vec<Signer> = self
+            .signers(&state.id, KeyRole::Next)?
+            .into_iter()
+            .filter(|s| state.next_signers.contains(&s.id))
+            .collect();
+        if revealed.len() < state.next_threshold as usize {
+            return Err(IdEventError::ThresholdNotMatch);
+        }
+        Ok(revealed)
+    }
+
+    /// Generates the current and next keys of a new identity.
+    ///
+    /// The keys have no identity until `sync` with the inception state.
+    pub fn incept(
+        &mut self,
+        plan: &IdKeyPlan,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<IdKeySet, IdEventError> {
+        if plan.new_signers < plan.threshold {
+            return Err(IdEventError::ThresholdNotMatch);
+        }
+        if plan.next_signers < plan.next_threshold {
+            return Err(IdEventError::NextThresholdNotMatch);
+        }
+        let mut signers = BTreeSet::new();
+        for _ in 0..plan.new_signers {
+            let signer = self.generate(None, &plan.purposes, KeyRole::Current, 0, rng)?;
+            signers.insert(self.id_signer(&signer)?);
+        }
+        Ok(IdKeySet {
+            threshold: plan.threshold,
+            next_threshold: plan.next_threshold,
+            signers,
+            next_signers: self.generate_next(None, plan.next_signers, &plan.purposes, 1, rng)?,
+        })
+    }
+
+    /// Rotation revealing the committed next keys.
+    ///
+    /// New current keys can be added next to the revealed ones, e.g. to
+    /// raise the threshold.
+    pub fn rotation(
+        &mut self,
+        state: &IdState,
+        plan: &IdKeyPlan,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<IdKeyEvent, IdEventError> {
+        let mut signers = self.revealed(state)?;
+        if signers.len() + (plan.new_signers as usize) < plan.threshold as usize {
+            return Err(IdEventError::ThresholdNotMatch);
+        }
+        if plan.next_signers < plan.next_threshold {
+            return Err(IdEventError::NextThresholdNotMatch);
+        }
+        let revealed_signers = signers
+            .iter()
+            .map(|s| self.id_signer(s))
+            .collect::<Result<_, _>>()?;
+        let rotation = self.rotation_index(&state.id);
+        let mut new_signers = BTreeSet::new();
+        for _ in 0..plan.new_signers {
+            let signer =
+                self.generate(Some(&state.id), &plan.purposes, KeyRole::Current, rotation, rng)?;
+            new_signers.insert(self.id_signer(&signer)?);
+            signers.push(signer);
+        }
+        let next_signers = self.generate_next(
+            Some(&state.id),
+            plan.next_signers,
+            &plan.purposes,
+            rotation + 1,
+            rng,
+        )?;
+        Ok(IdKeyEvent {
+            body: IdEventKind::Rotation {
+                threshold: plan.threshold,
+                next_threshold: plan.next_threshold,
+                revealed_signers,
+                new_signers,
+                next_signers,
+            },
+            signers,
+        })
+    }
+
+    /// Revocation revealing the committed next keys.
+    pub fn revocation(&self, state: &IdState) -> Result<IdKeyEvent, IdEventError> {
+        let signers = self.revealed(state)?;
+        let revealed_signers = signers
+            .iter()
+            .map(|s| self.id_signer(s))
+            .collect::<Result<_, _>>()?;
+        Ok(IdKeyEvent {
+            body: IdEventKind::Revocation { revealed_signers },
+            signers,
+        })
+    }
+
+    /// Migration to the identity `next_id_proof` points at.
+    pub fn migration(&self, state: &IdState, next_id_proof: &str) -> Result<IdKeyEvent, IdEventError> {
+        let signers = self.revealed(state)?;
+        let revealed_signers = signers
+            .iter()
+            .map(|s| self.id_signer(s))
+            .collect::<Result<_, _>>()?;
+        Ok(IdKeyEvent {
+            body: IdEventKind::Migration {
+                revealed_signers,
+                next_id_proof: next_id_proof.into(),
+            },
+            signers,
+        })
+    }
+
+    /// Brings the stored keys in line with a verified state.
+    ///
+    /// Keys in `state.signers` become current and keys in `state.next_signers`
+    /// stay next, both are bound to the identity. Other keys of the identity
+    /// are spent and removed, all of them once it is revoked. Returns the
+    /// ids of the removed keys.
+    pub fn sync(
+        &mut self,
+        state: &IdState,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<Vec<String>, IdEventError> {
+        let mut retired = vec![];
+        let entries = self.keystore.entries.clone();
+        for entry in entries {
+            let role = if state.revoked {
+                None
+            } else if state.signers.iter().any(|s| s.id == entry.id) {
+                Some(KeyRole::Current)
+            } else if state.next_signers.contains(&entry.id) {
+                Some(KeyRole::Next)
+            } else {
+                None
+            };
+            let own = entry.metadata.did.as_deref() == Some(&state.id);
+            match role {
+                Some(role) if entry.metadata.did.is_none() || own => {
+                    let mut metadata = entry.metadata.clone();
+                    metadata.did = Some(state.id.clone());
+                    metadata.role = role;
+                    // A revealed key is no longer a commitment
+                    if role == KeyRole::Current {
+                        metadata.commitment = None;
+                    }
+                    if metadata != entry.metadata {
+                        self.keystore.update(&self.key, &entry.id, metadata, rng)?;
+                    }
+                }
+                None if own => {
+                    self.keystore.remove(&entry.id);
+                    retired.push(entry.id);
+                }
+                _ => {}
+            }
+        }
+        Ok(retired)
+    }
+
+    /// Rebuilds the keys of an identity from the seed and its verified log.
+    ///
+    /// Current and next keys of the final state are searched at every
+    /// purpose, rotation and the first `MAX_HD_SLOTS` slots and stored
+    /// with their paths, so the log can be continued.
+    pub fn recover(
+        &mut self,
+        inception: &IdEventReceipt,
+        events: &[IdEventReceipt],
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<IdState, IdEventError> {
+        let state = inception.verify_log(events)?;
+        let mut wanted: BTreeSet<&str> = state
+            .signers
+            .iter()
+            .map(|s| s.id.as_str())
+            .chain(state.next_signers.iter().map(String::as_str))
+            .collect();
+        let mut found = vec![];
+        if let Some((seed, identity)) = &self.hd {
+            'search: for rotation in 0..=(events.len() as u32 + 1) {
+                for purpose in IdKeyPurpose::ALL {
+                    for slot in 0..MAX_HD_SLOTS {
+                        let path = HdPath {
+                            identity: *identity,
+                            purpose: purpose.hd_index(),
+                            rotation,
+                            slot,
+                        };
+                        let signer = seed.signer(&path)?.with_hash(self.hash_code)?;
+                        if wanted.remove(signer.id.as_str()) {
+                            found.push((signer, path, purpose));
+                        }
+                        if wanted.is_empty() {
+                            break 'search;
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(id) = wanted.first() {
+            return Err(IdEventError::UnrecoverableKey(id.to_string()));
+        }
+        for (signer, path, purpose) in found {
+            if self.keystore.get(&signer.id).is_some() {
+                continue;
+            }
+            let current = state.signers.iter().find(|s| s.id == signer.id);
+            let mut metadata = KeyMetadata {
+                did: Some(state.id.clone()),
+                purposes: current.map_or_else(
+                    || vec![purpose.as_ref().into()],
+                    |s| s.purposes.clone(),
+                ),
+                role: KeyRole::Current,
+                commitment: None,
+                path: Some(path.to_string()),
+            };
+            if current.is_none() {
+                metadata.role = KeyRole::Next;
+                metadata.commitment = Some(signer.id.clone());
+            }
+            self.keystore.insert(&self.key, &signer, metadata, rng)?;
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        VERSION,
+        internal::{event::IdEvent, inception::IdInception},
+    };
+    use cid::Cid;
+    use idp2p_common::{
+        BLAKE3_CODE, CBOR_CODE, cbor,
+        cid::CidExt,
+        crypto::{hd::generate_mnemonic, keystore::KdfParams},
+    };
+    use rand::rngs::OsRng;
+
+    fn keystore() -> (Keystore, StoreKey) {
+        let kdf = KdfParams {
+            m_cost: 64,
+            t_cost: 1,
+            p_cost: 1,
+            salt: vec![0u8; 16],
+        };
+        Keystore::new(b"password", kdf, &mut OsRng).unwrap()
+    }
+
+    fn manager() -> IdKeyManager {
+        let (keystore, key) = keystore();
+        IdKeyManager::new(keystore, key)
+    }
+
+    fn hd_manager(phrase: &str) -> IdKeyManager {
+        let (keystore, key) = keystore();
+        let seed = HdSeed::from_mnemonic(phrase, "").unwrap();
+        IdKeyManager::with_seed(keystore, key, seed, 0)
+    }
+
+    fn timestamp() -> i64 {
+        let valid_from: DateTime<Utc> = crate::VALID_FROM.parse().unwrap();
+        valid_from.timestamp() + 1
+    }
+
+    fn plan(threshold: u8, new_signers: u8, next_signers: u8) -> IdKeyPlan {
+        IdKeyPlan {
+            threshold,
+            new_signers,
+            next_threshold: 1,
+            next_signers,
+            purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
+        }
+    }
+
+    fn incept(manager: &mut IdKeyManager) -> (IdState, IdEventReceipt) {
+        let keys = manager.incept(&plan(1, 1, 1), &mut OsRng).unwrap();
+        let inception = IdInception {
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: timestamp(),
+            prior_id: None,
+            threshold: keys.threshold,
+            next_threshold: keys.next_threshold,
+            signers: keys.signers.clone(),
+            next_signers: keys.next_signers.clone(),
+            delegated_signers: BTreeSet::new(),
+            merkle_proof: "inception-proof".into(),
+        };
+        let payload = cbor::encode(&inception).unwrap();
+        let id = manager.event_id(CBOR_CODE, &payload).unwrap();
+        let proofs = keys
+            .signers
+            .iter()
+            .map(|s| {
+                let signer = manager.keystore.signer(&manager.key, &s.id).unwrap();
+                IdProof::create(&id, &signer, &Utc::now(), IdProofPurpose::EventSigning, &payload)
+                    .unwrap()
+            })
+            .collect();
+        let receipt = IdEventReceipt {
+            id: id.clone(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            proofs,
+            payload,
+        };
+        let state = receipt.verify_inception().unwrap();
+        manager.sync(&state, &mut OsRng).unwrap();
+        (state, receipt)
+    }
+
+    fn apply(
+        manager: &mut IdKeyManager,
+        state: &IdState,
+        event: IdKeyEvent,
+    ) -> (IdState, IdEventReceipt) {
+        let event_payload = IdEvent {
+            sn: state.sn + 1,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: timestamp(),
+            previous: state.event_id.clone(),
+            body: event.body.clone(),
+        };
+        let payload = cbor::encode(&event_payload).unwrap();
+        let receipt = IdEventReceipt {
+            id: manager.event_id(CBOR_CODE, &payload).unwrap(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            proofs: event.proofs(&state.id, &Utc::now(), &payload).unwrap(),
+            payload,
+        };
+        let state = receipt.verify_event(&mut state.clone()).unwrap();
+        manager.sync(&state, &mut OsRng).unwrap();
+        (state, receipt)
+    }
+
+    #[test]
+    fn rotation_test() {
+        let mut manager = manager();
+        let (state, _) = incept(&mut manager);
+        let current = manager.signers(&state.id, KeyRole::Current).unwrap();
+        let next = manager.signers(&state.id, KeyRole::Next).unwrap();
+        assert_eq!(current.len(), 1);
+        assert_eq!(state.next_signers, vec![next[0].id.clone()]);
+        assert_eq!(
+            Cid::create(ED_CODE, &next[0].public_key).unwrap().to_string(),
+            next[0].id
+        );
+
+        let event = manager
+            .rotation(&state, &plan(2, 1, 2), &mut OsRng)
+            .unwrap();
+        let (state, _) = apply(&mut manager, &state, event);
+        assert_eq!(state.threshold, 2);
+        assert_eq!(state.next_signers.len(), 2);
+
+        // The revealed key is current now and the old one is retired
+        let current = manager.signers(&state.id, KeyRole::Current).unwrap();
+        assert_eq!(current.len(), 2);
+        assert!(current.iter().any(|s| s.id == next[0].id));
+        assert_eq!(manager.keystore.entries.len(), 4);
+    }
+
+    #[test]
+    fn revocation_retires_all_keys_test() {
+        let mut manager = manager();
+        let (state, _) = incept(&mut manager);
+        let event = manager.revocation(&state).unwrap();
+        let (state, _) = apply(&mut manager, &state, event);
+        assert!(state.revoked);
+        assert!(manager.keystore.entries.is_empty());
+    }
+
+    #[test]
+    fn migration_test() {
+        let mut manager = manager();
+        let (state, _) = incept(&mut manager);
+        let event = manager.migration(&state, "next-id-proof").unwrap();
+        let (state, _) = apply(&mut manager, &state, event);
+        assert_eq!(state.next_id_proof.as_deref(), Some("next-id-proof"));
+        // The revealed next key is spent
+        assert!(manager.signers(&state.id, KeyRole::Next).unwrap().is_empty());
+        assert_eq!(manager.signers(&state.id, KeyRole::Current).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn missing_next_keys_test() {
+        let mut manager = manager();
+        let (state, _) = incept(&mut manager);
+        let next = manager.signers(&state.id, KeyRole::Next).unwrap();
+        manager.keystore.remove(&next[0].id);
+        assert!(matches!(
+            manager.revocation(&state),
+            Err(IdEventError::ThresholdNotMatch)
+        ));
+    }
+
+    #[test]
+    fn hd_recovery_test() {
+        let phrase = generate_mnemonic(12, &mut OsRng).unwrap();
+        let mut manager = hd_manager(&phrase);
+        let (state, inception) = incept(&mut manager);
+        let event = manager.rotation(&state, &plan(1, 0, 1), &mut OsRng).unwrap();
+        let (state, rotation) = apply(&mut manager, &state, event);
+        let paths: BTreeSet<_> = manager
+            .keystore
+            .entries
+            .iter()
+            .map(|e| e.metadata.path.clone().unwrap())
+            .collect();
+        assert_eq!(
+            paths,
+            BTreeSet::from([
+                "m/44'/7337'/0'/0'/1'/0'".to_string(),
+                "m/44'/7337'/0'/0'/2'/0'".to_string(),
+            ])
+        );
+
+        // A fresh keystore gets the same keys back and continues the log
+        let mut recovered = hd_manager(&phrase);
+        let recovered_state = recovered
+            .recover(&inception, core::slice::from_ref(&rotation), &mut OsRng)
+            .unwrap();
+        assert_eq!(recovered_state, state);
+        assert_eq!(recovered.keystore.entries.len(), 2);
+        for entry in &manager.keystore.entries {
+            assert_eq!(recovered.keystore.get(&entry.id).unwrap().metadata, entry.metadata);
+        }
+        let event = recovered.rotation(&state, &plan(1, 0, 1), &mut OsRng).unwrap();
+        let (state, _) = apply(&mut recovered, &state, event);
+        assert_eq!(state.sn, 2);
+
+        // Another phrase recovers nothing
+        let other = generate_mnemonic(12, &mut OsRng).unwrap();
+        assert!(matches!(
+            hd_manager(&other).recover(&inception, &[rotation], &mut OsRng),
+            Err(IdEventError::UnrecoverableKey(_))
+        ));
+    }
+
+    #[test]
+    fn blake3_log_test() {
+        let phrase = generate_mnemonic(12, &mut OsRng).unwrap();
+        let mut manager = hd_manager(&phrase).with_hash(BLAKE3_CODE);
+        let (state, inception) = incept(&mut manager);
+        let event = manager.rotation(&state, &plan(2, 1, 2), &mut OsRng).unwrap();
+        let (state, rotation) = apply(&mut manager, &state, event);
+        let event = manager.rotation(&state, &plan(1, 0, 1), &mut OsRng).unwrap();
+        let (state, last) = apply(&mut manager, &state, event);
+
+        let hash = |id: &str| Cid::try_from(id).unwrap().hash().code();
+        let events = [rotation, last];
+        assert!(events.iter().chain([&inception]).all(|r| hash(&r.id) == BLAKE3_CODE));
+        assert!(state.signers.iter().all(|s| hash(&s.id) == BLAKE3_CODE));
+        assert!(state.next_signers.iter().all(|id| hash(id) == BLAKE3_CODE));
+        assert_eq!(inception.verify_log(&events).unwrap(), state);
+
+        // Recovery derives the ids with the same hash
+        let mut recovered = hd_manager(&phrase).with_hash(BLAKE3_CODE);
+        assert_eq!(recovered.recover(&inception, &events, &mut OsRng).unwrap(), state);
+        assert!(matches!(
+            hd_manager(&phrase).recover(&inception, &events, &mut OsRng),
+            Err(IdEventError::UnrecoverableKey(_))
+        ));
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, EnumString};
+
+use super::signer::IdKeyPurpose;
+
+/// What a proof is made for.
+///
+/// Every purpose signs under its own domain prefix, so a signature made for
+/// one purpose can never be replayed as another.
+#[derive(
+    Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, AsRefStr, EnumString,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum IdProofPurpose {
+    /// Signs an identity event or checkpoint with the identity's own keys
+    EventSigning,
+    /// Answers an authentication challenge
+    Authentication,
+    /// Signs a statement made by the identity
+    Assertion,
+    /// Made by a delegated identity, verified by the host
+    #[serde(rename = "id-delegation")]
+    #[strum(serialize = "id-delegation")]
+    Delegation,
+}
+
+/// How a proof signature is encoded.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, AsRefStr, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum IdProofFormat {
+    /// Raw signature over `IdProof::signing_input`
+    Idp2p,
+    /// Tagged COSE_Sign1 envelope with a detached payload
+    CoseSign1,
+    /// Detached compact JWS
+    Jws,
+    /// Raw Ed25519 signature of a W3C Data Integrity proof
+    #[strum(serialize = "eddsa-jcs-2022")]
+    EddsaJcs2022,
+}
+
+impl IdProofPurpose {
+    /// Prefix of the signing input.
+    pub fn domain(&self) -> &'static [u8] {
+        match self {
+            Self::EventSigning => b"idp2p/proof/event-signing/v1",
+            Self::Authentication => b"idp2p/proof/authentication/v1",
+            Self::Assertion => b"idp2p/proof/assertion/v1",
+            Self::Delegation => b"idp2p/proof/id-delegation/v1",
+        }
+    }
+
+    /// W3C verification relationship of the purpose.
+    ///
+    /// Event signing is internal to idp2p and has none.
+    pub fn w3c(&self) -> Option<&'static str> {
+        match self {
+            Self::EventSigning => None,
+            Self::Authentication => Some("authentication"),
+            Self::Assertion => Some("assertionMethod"),
+            Self::Delegation => Some("capabilityDelegation"),
+        }
+    }
+
+    pub fn from_w3c(purpose: &str) -> Option<Self> {
+        match purpose {
+            "authentication" => Some(Self::Authentication),
+            "assertionMethod" => Some(Self::Assertion),
+            "capabilityDelegation" => Some(Self::Delegation),
+            _ => None,
+        }
+    }
+
+    /// Key purpose a signer needs to make this proof.
+    pub fn key_purpose(&self) -> IdKeyPurpose {
+        match self {
+            Self::EventSigning => IdKeyPurpose::EventSigning,
+            Self::Authentication => IdKeyPurpose::Authentication,
+            Self::Assertion => IdKeyPurpose::Assertion,
+            Self::Delegation => IdKeyPurpose::CapabilityDelegation,
+        }
+    }
+}
@@ -0,0 +1,474 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use cid::Cid;
+use core::str::FromStr;
+use idp2p_common::{
+    ED_CODE, X25519_CODE,
+    bytes::Bytes,
+    cbor,
+    crypto::{
+        agreement,
+        shamir::{self, Share},
+    },
+    multikey,
+    utils::sha256_hash,
+};
+use rand_core::CryptoRngCore;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use zeroize::Zeroizing;
+
+use super::{error::IdEventError, signer::IdKeyPurpose};
+use crate::types::IdState;
+
+const SHARE_DOMAIN: &[u8] = b"idp2p/recovery/share/v1";
+const SECRET_DOMAIN: &[u8] = b"idp2p/recovery/secret/v1";
+const SALT_SIZE: usize = 32;
+
+/// A sealed recovery share on its way to a guardian or back to the owner.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdRecoveryShare {
+    /// Identity the secret belongs to
+    pub owner: String,
+    /// Identity holding the share
+    pub guardian: String,
+    /// Guardian key agreement signer id, or the multikey of the owner's
+    /// recovery key on the way back
+    pub recipient: String,
+    #[serde_as(as = "Bytes")]
+    pub sealed: Vec<u8>,
+}
+
+/// Content of a recovery share.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdRecoveryPayload {
+    pub owner: String,
+    pub guardian: String,
+    pub share: Share,
+}
+
+/// A guardian and the key agreement key its share was sealed to.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdRecoveryGuardian {
+    pub id: String,
+    /// Key agreement signer id
+    pub signer: String,
+    /// X25519 form of the signer key
+    #[serde_as(as = "Bytes")]
+    pub key: Vec<u8>,
+}
+
+/// What the owner fixed when splitting a secret.
+///
+/// Reconstruction only takes shares sealed by these guardians, at their
+/// index and threshold and matching their digest, and checks the secret
+/// against the commitment. Digests and commitment are salted, guardians
+/// never see them. A low entropy secret could still be guessed against the
+/// commitment, so the owner keeps the policy private, e.g. with the
+/// keystore backup.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdRecoveryPolicy {
+    pub owner: String,
+    pub threshold: u8,
+    /// Guardians in share index order
+    pub guardians: Vec<IdRecoveryGuardian>,
+    #[serde_as(as = "Bytes")]
+    pub salt: Vec<u8>,
+    /// Salted digests of all shares, by index
+    #[serde_as(as = "Vec<Bytes>")]
+    pub share_digests: Vec<Vec<u8>>,
+    /// Salted commitment to the secret
+    #[serde_as(as = "Bytes")]
+    pub commitment: Vec<u8>,
+}
+
+fn share_digest(salt: &[u8], share: &Share) -> Vec<u8> {
+    let mut content = [SHARE_DOMAIN, salt].concat();
+    content.push(share.index);
+    content.push(share.threshold);
+    content.extend_from_slice(&share.data);
+    sha256_hash(&content).to_vec()
+}
+
+fn commitment(salt: &[u8], secret: &[u8]) -> Vec<u8> {
+    sha256_hash(&[SECRET_DOMAIN, salt, secret].concat()).to_vec()
+}
+
+/// Ed25519 key agreement signer of an identity, as an X25519 key.
+pub fn key_agreement_key(state: &IdState) -> Result<(String, [u8; 32]), IdEventError> {
+    let signer = state
+        .signers
+        .iter()
+        .find(|s| {
+            s.purposes
+                .iter()
+                .any(|p| p == IdKeyPurpose::KeyAgreement.as_ref())
+                && Cid::from_str(&s.id).is_ok_and(|kid| kid.codec() == ED_CODE)
+        })
+        .ok_or_else(|| IdEventError::InvalidSigner(state.id.clone()))?;
+    let public = agreement::ed25519_public_to_x25519(&signer.public_key)?;
+    Ok((signer.id.clone(), public))
+}
+
+/// Recipient name of the owner's X25519 recovery key.
+pub fn recovery_recipient(public: &[u8; 32]) -> String {
+    multikey::encode(X25519_CODE, public)
+}
+
+impl IdRecoveryShare {
+    fn aad(owner: &str, guardian: &str, recipient: &str) -> Result<Vec<u8>, IdEventError> {
+        Ok(cbor::encode(&(SHARE_DOMAIN, owner, guardian, recipient))?)
+    }
+
+    fn payload(&self, plaintext: &[u8]) -> Result<IdRecoveryPayload, IdEventError> {
+        let payload: IdRecoveryPayload = cbor::decode(plaintext)?;
+        if payload.owner != self.owner || payload.guardian != self.guardian {
+            return Err(IdEventError::RecoveryFailed("share mismatch".into()));
+        }
+        Ok(payload)
+    }
+
+    /// Opens the share with the recipient X25519 secret.
+    ///
+    /// Guardians derive it from their key agreement signer with
+    /// `agreement::ed25519_secret_to_x25519`.
+    pub fn open(&self, secret: &[u8; 32]) -> Result<IdRecoveryPayload, IdEventError> {
+        let aad = Self::aad(&self.owner, &self.guardian, &self.recipient)?;
+        let plaintext = Zeroizing::new(agreement::open(secret, &aad, &self.sealed)?);
+        self.payload(&plaintext)
+    }
+
+    /// Opens a share returned by a guardian with the owner's recovery
+    /// secret, it must be sealed by the guardian `key`.
+    pub fn open_from(
+        &self,
+        secret: &[u8; 32],
+        key: &[u8],
+    ) -> Result<IdRecoveryPayload, IdEventError> {
+        let aad = Self::aad(&self.owner, &self.guardian, &self.recipient)?;
+        let (sender, plaintext) = agreement::auth_open(secret, &aad, &self.sealed)?;
+        let plaintext = Zeroizing::new(plaintext);
+        if sender.as_slice() != key {
+            return Err(IdEventError::RecoveryFailed("unknown sender".into()));
+        }
+        self.payload(&plaintext)
+    }
+}
+
+impl IdRecoveryPayload {
+    /// Seals the share to a recipient X25519 key.
+    pub fn seal(
+        &self,
+        recipient: &str,
+        recipient_key: &[u8; 32],
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<IdRecoveryShare, IdEventError> {
+        let aad = IdRecoveryShare::aad(&self.owner, &self.guardian, recipient)?;
+        let plaintext = Zeroizing::new(cbor::encode(self)?);
+        Ok(IdRecoveryShare {
+            owner: self.owner.clone(),
+            guardian: self.guardian.clone(),
+            recipient: recipient.into(),
+            sealed: agreement::seal(recipient_key, &aad, &plaintext, rng)?,
+        })
+    }
+
+    /// Seals the share back to the owner's recovery key, authenticated by
+    /// the guardian's X25519 key agreement secret.
+    pub fn seal_to_owner(
+        &self,
+        guardian_secret: &[u8; 32],
+        recovery_key: &[u8; 32],
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<IdRecoveryShare, IdEventError> {
+        let recipient = recovery_recipient(recovery_key);
+        let aad = IdRecoveryShare::aad(&self.owner, &self.guardian, &recipient)?;
+        let plaintext = Zeroizing::new(cbor::encode(self)?);
+        let sealed = agreement::auth_seal(guardian_secret, recovery_key, &aad, &plaintext, rng)?;
+        Ok(IdRecoveryShare {
+            owner: self.owner.clone(),
+            guardian: self.guardian.clone(),
+            recipient,
+            sealed,
+        })
+    }
+}
+
+/// Splits a recovery secret into one share per guardian, `threshold` of
+/// them recover it.
+///
+/// Each share is sealed to the key agreement key of its guardian. The
+/// returned policy is what `reconstruct` checks shares against.
+pub fn split(
+    owner: &str,
+    secret: &[u8],
+    threshold: u8,
+    guardians: &[IdState],
+    rng: &mut impl CryptoRngCore,
+) -> Result<(IdRecoveryPolicy, Vec<IdRecoveryShare>), IdEventError> {
+    let count = u8::try_from(guardians.len())
+        .map_err(|_| IdEventError::RecoveryFailed("too many guardians".into()))?;
+    let shares = shamir::split(secret, threshold, count, rng)?;
+    let mut salt = vec![0u8; SALT_SIZE];
+    rng.fill_bytes(&mut salt);
+    let mut policy = IdRecoveryPolicy {
+        owner: owner.into(),
+        threshold,
+        guardians: Vec::with_capacity(shares.len()),
+        share_digests: shares.iter().map(|s| share_digest(&salt, s)).collect(),
+        commitment: commitment(&salt, secret),
+        salt,
+    };
+    let mut sealed = Vec::with_capacity(shares.len());
+    for (guardian, share) in guardians.iter().zip(shares) {
+        let (kid, key) = key_agreement_key(guardian)?;
+        let payload = IdRecoveryPayload {
+            owner: owner.into(),
+            guardian: guardian.id.clone(),
+            share,
+        };
+        sealed.push(payload.seal(&kid, &key, rng)?);
+        policy.guardians.push(IdRecoveryGuardian {
+            id: guardian.id.clone(),
+            signer: kid,
+            key: key.to_vec(),
+        });
+    }
+    Ok((policy, sealed))
+}
+
+/// Rebuilds a recovery secret from shares returned by guardians.
+///
+/// Shares that don't open, aren't sealed by their guardian's key or don't
+/// match the index, threshold and digest the policy fixed are dropped. The
+/// secret is only returned when it matches the policy commitment.
+pub fn reconstruct(
+    policy: &IdRecoveryPolicy,
+    recovery_secret: &[u8; 32],
+    shares: &[IdRecoveryShare],
+) -> Result<Zeroizing<Vec<u8>>, IdEventError> {
+    let mut valid: BTreeMap<u8, Share> = BTreeMap::new();
+    for share in shares.iter().filter(|s| s.owner == policy.owner) {
+        let Some(position) = policy.guardians.iter().position(|g| g.id == share.guardian) else {
+            continue;
+        };
+        let guardian = &policy.guardians[position];
+        let Ok(payload) = share.open_from(recovery_secret, &guardian.key) else {
+            continue;
+        };
+        let matches = payload.share.index as usize == position + 1
+            && payload.share.threshold == policy.threshold
+            && policy.share_digests.get(position)
+                == Some(&share_digest(&policy.salt, &payload.share));
+        if matches {
+            valid.insert(payload.share.index, payload.share);
+        }
+    }
+    if policy.threshold == 0 || valid.len() < policy.threshold as usize {
+        return Err(IdEventError::RecoveryFailed(
+            "not enough valid shares".into(),
+        ));
+    }
+    let shares: Vec<Share> = valid.into_values().collect();
+    let secret = shamir::combine(&shares)?;
+    if commitment(&policy.salt, &secret) != policy.commitment {
+        return Err(IdEventError::RecoveryFailed("commitment mismatch".into()));
+    }
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::IdSigner;
+    use idp2p_common::{cid::CidExt, crypto::signer::Signer};
+    use rand::rngs::OsRng;
+
+    fn guardian(name: &str) -> (IdState, Signer) {
+        let signer = Signer::generate(ED_CODE, &mut OsRng).unwrap();
+        let state = IdState {
+            id: Cid::create(ED_CODE, name.as_bytes()).unwrap().to_string(),
+            sn: 0,
+            event_id: String::new(),
+            event_timestamp: String::new(),
+            prior_id: None,
+            next_id_proof: None,
+            threshold: 1,
+            next_threshold: 1,
+            signers: vec![IdSigner {
+                id: signer.id.clone(),
+                public_key: signer.public_key.clone(),
+                purposes: vec!["key-agreement".into()],
+                valid_from_sn: 0,
+                valid_until_sn: None,
+                valid_from: String::new(),
+                valid_until: None,
+            }],
+            next_signers: vec![],
+            delegated_signers: vec![],
+            merkle_proof: String::new(),
+            revoked: false,
+            revoked_at: None,
+        };
+        (state, signer)
+    }
+
+    /// Guardian opens its share and sends it back to the recovery key.
+    fn give_back(
+        share: &IdRecoveryShare,
+        signer: &Signer,
+        recovery_key: &[u8; 32],
+    ) -> IdRecoveryShare {
+        let secret = agreement::ed25519_secret_to_x25519(signer.secret());
+        share
+            .open(&secret)
+            .unwrap()
+            .seal_to_owner(&secret, recovery_key, &mut OsRng)
+            .unwrap()
+    }
+
+    fn guardians() -> (Vec<(IdState, Signer)>, Vec<IdState>) {
+        let guardians: Vec<(IdState, Signer)> =
+            ["a", "b", "c"].iter().map(|n| guardian(n)).collect();
+        let states = guardians.iter().map(|(s, _)| s.clone()).collect();
+        (guardians, states)
+    }
+
+    #[test]
+    fn recovery_test() {
+        let (guardians, states) = guardians();
+        let secret = b"next key seed of the identity";
+        let (policy, shares) = split("owner", secret, 2, &states, &mut OsRng).unwrap();
+        assert_eq!(shares.len(), 3);
+        assert_eq!(shares[0].recipient, guardians[0].1.id);
+        assert_eq!(policy.guardians[0].signer, guardians[0].1.id);
+
+        // A new device asks two guardians for their shares
+        let recovery_secret = agreement::generate(&mut OsRng);
+        let recovery_key = agreement::public_key(&recovery_secret);
+        let returned = vec![
+            give_back(&shares[0], &guardians[0].1, &recovery_key),
+            give_back(&shares[2], &guardians[2].1, &recovery_key),
+        ];
+        let recovered = reconstruct(&policy, &recovery_secret, &returned).unwrap();
+        assert_eq!(recovered.as_slice(), secret);
+
+        // One share is not enough
+        assert!(matches!(
+            reconstruct(&policy, &recovery_secret, &returned[..1]),
+            Err(IdEventError::RecoveryFailed(_))
+        ));
+    }
+
+    #[test]
+    fn wrong_guardian_key_test() {
+        let (state, _) = guardian("a");
+        let (_, other) = guardian("b");
+        let (_, shares) = split("owner", b"secret", 1, &[state], &mut OsRng).unwrap();
+        let secret = agreement::ed25519_secret_to_x25519(other.secret());
+        assert!(shares[0].open(&secret).is_err());
+    }
+
+    #[test]
+    fn tampered_share_dropped_test() {
+        let (guardians, states) = guardians();
+        let (policy, shares) = split("owner", b"secret", 2, &states, &mut OsRng).unwrap();
+        let recovery_secret = agreement::generate(&mut OsRng);
+        let recovery_key = agreement::public_key(&recovery_secret);
+
+        // A guardian returns a corrupted share
+        let secret = agreement::ed25519_secret_to_x25519(guardians[1].1.secret());
+        let mut payload = shares[1].open(&secret).unwrap();
+        payload.share.data[0] ^= 1;
+        let returned = vec![
+            payload.seal_to_owner(&secret, &recovery_key, &mut OsRng).unwrap(),
+            give_back(&shares[0], &guardians[0].1, &recovery_key),
+        ];
+        assert!(reconstruct(&policy, &recovery_secret, &returned).is_err());
+
+        let mut returned = returned;
+        returned.push(give_back(&shares[2], &guardians[2].1, &recovery_key));
+        assert_eq!(
+            reconstruct(&policy, &recovery_secret, &returned)
+                .unwrap()
+                .as_slice(),
+            b"secret"
+        );
+    }
+
+    #[test]
+    fn forged_share_dropped_test() {
+        let (guardians, states) = guardians();
+        let (policy, shares) = split("owner", b"secret", 2, &states, &mut OsRng).unwrap();
+        let recovery_secret = agreement::generate(&mut OsRng);
+        let recovery_key = agreement::public_key(&recovery_secret);
+        let forged_share = |guardian: &str| IdRecoveryPayload {
+            owner: "owner".into(),
+            guardian: guardian.into(),
+            share: shamir::split(b"attacker", 1, 1, &mut OsRng).unwrap().remove(0),
+        };
+
+        // A guardian sends a threshold 1 share of its own secret
+        let secret = agreement::ed25519_secret_to_x25519(guardians[0].1.secret());
+        let mut forged = forged_share(&states[0].id);
+        forged.share.index = 1;
+        let from_guardian = forged
+            .seal_to_owner(&secret, &recovery_key, &mut OsRng)
+            .unwrap();
+        // Anyone with the recovery key impersonates another guardian
+        let outsider = agreement::generate(&mut OsRng);
+        let from_outsider = forged_share(&states[1].id)
+            .seal_to_owner(&outsider, &recovery_key, &mut OsRng)
+            .unwrap();
+        for forged in [&from_guardian, &from_outsider] {
+            assert!(matches!(
+                reconstruct(&policy, &recovery_secret, core::slice::from_ref(forged)),
+                Err(IdEventError::RecoveryFailed(_))
+            ));
+        }
+
+        let returned = vec![
+            from_guardian,
+            from_outsider,
+            give_back(&shares[1], &guardians[1].1, &recovery_key),
+            give_back(&shares[2], &guardians[2].1, &recovery_key),
+        ];
+        assert_eq!(
+            reconstruct(&policy, &recovery_secret, &returned)
+                .unwrap()
+                .as_slice(),
+            b"secret"
+        );
+    }
+
+    #[test]
+    fn commitment_is_salted_test() {
+        let (guardians, states) = guardians();
+        let (policy, shares) = split("owner", b"secret", 2, &states, &mut OsRng).unwrap();
+        let (other, _) = split("owner", b"secret", 2, &states, &mut OsRng).unwrap();
+        assert_ne!(policy.commitment, other.commitment);
+        assert_ne!(policy.commitment, commitment(&[], b"secret"));
+
+        // Guardians receive their share only
+        let secret = agreement::ed25519_secret_to_x25519(guardians[0].1.secret());
+        let payload = shares[0].open(&secret).unwrap();
+        assert_eq!(payload.share.index, 1);
+        let plaintext = cbor::encode(&payload).unwrap();
+        assert!(!plaintext
+            .windows(policy.commitment.len())
+            .any(|w| w == policy.commitment.as_slice()));
+    }
+
+    #[test]
+    fn missing_key_agreement_key_test() {
+        let (mut state, _) = guardian("a");
+        state.signers[0].purposes = vec!["authentication".into()];
+        assert!(matches!(
+            split("owner", b"secret", 1, &[state], &mut OsRng),
+            Err(IdEventError::InvalidSigner(_))
+        ));
+    }
+}
@@ -1,21 +1,97 @@
-use serde::{Deserialize, Serialize};
-use idp2p_common::bytes::Bytes;
+use alloc::collections::BTreeSet;
+use cid::Cid;
+use core::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use idp2p_common::{bytes::Bytes, cid::CidExt, multikey, verification::SIGNER_CODES};
 use serde_with::serde_as;
+use strum_macros::{AsRefStr, EnumString};
+
+use super::error::IdEventError;
+
+/// What a signer key may be used for.
+#[derive(
+    Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, AsRefStr, EnumString,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum IdKeyPurpose {
+    /// Signing identity events (inception, interaction, rotation...)
+    EventSigning,
+    Authentication,
+    Assertion,
+    KeyAgreement,
+    CapabilityDelegation,
+}
+
+impl IdKeyPurpose {
+    /// Every purpose, in HD derivation path order.
+    pub const ALL: [Self; 5] = [
+        Self::EventSigning,
+        Self::Authentication,
+        Self::Assertion,
+        Self::KeyAgreement,
+        Self::CapabilityDelegation,
+    ];
+
+    /// Purpose level of HD derivation paths.
+    pub fn hd_index(&self) -> u32 {
+        *self as u32
+    }
+}
 
 #[serde_as]
 #[derive(Debug, Clone, Hash, Serialize, Deserialize, Eq, PartialEq)]
 pub struct IdSigner {
     pub id: String,
     #[serde_as(as = "Bytes")]
-    pub public_key: Vec<u8>
+    pub public_key: Vec<u8>,
+    #[serde(default = "default_purposes", deserialize_with = "non_empty_purposes")]
+    pub purposes: BTreeSet<IdKeyPurpose>,
+}
+
+/// Signers encoded before purposes existed sign events only.
+pub fn default_purposes() -> BTreeSet<IdKeyPurpose> {
+    BTreeSet::from([IdKeyPurpose::EventSigning])
+}
+
+fn non_empty_purposes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeSet<IdKeyPurpose>, D::Error> {
+    let purposes = BTreeSet::deserialize(deserializer)?;
+    if purposes.is_empty() {
+        return Err(D::Error::custom("signer has no purposes"));
+    }
+    Ok(purposes)
 }
 
 
 impl IdSigner {
+    /// Signer of a did:key, its id is the CID of the key.
+    pub fn from_did_key(did: &str, purposes: BTreeSet<IdKeyPurpose>) -> Result<Self, IdEventError> {
+        let (code, public_key) = multikey::from_did_key(did)?;
+        if !SIGNER_CODES.contains(&code) {
+            return Err(IdEventError::InvalidSigner(did.into()));
+        }
+        Ok(Self {
+            id: Cid::create(code, &public_key)?.to_string(),
+            public_key,
+            purposes,
+        })
+    }
+
+    /// did:key form of the signer.
+    pub fn did_key(&self) -> Result<String, IdEventError> {
+        let kid = Cid::from_str(&self.id)?;
+        kid.ensure(&self.public_key, SIGNER_CODES.to_vec())
+            .map_err(|_| IdEventError::InvalidSigner(self.id.clone()))?;
+        Ok(multikey::to_did_key(kid.codec(), &self.public_key))
+    }
+
     pub fn to_state(&self, valid_from_sn: u64, valid_from: &str) -> crate::types::IdSigner {
         crate::types::IdSigner {
             id: self.id.to_owned(),
             public_key: self.public_key.to_owned(),
+            purposes: self.purposes.iter().map(|p| p.as_ref().to_owned()).collect(),
             valid_from_sn: valid_from_sn,
             valid_until_sn: None,
             valid_from: valid_from.to_owned(),
@@ -24,6 +100,27 @@ impl IdSigner {
     }
 }
 
+impl TryFrom<&crate::types::IdSigner> for IdSigner {
+    type Error = IdEventError;
+
+    fn try_from(value: &crate::types::IdSigner) -> Result<Self, Self::Error> {
+        if value.purposes.is_empty() {
+            return Err(IdEventError::InvalidSigner(value.id.clone()));
+        }
+        let purposes = value
+            .purposes
+            .iter()
+            .map(|p| IdKeyPurpose::from_str(p))
+            .collect::<Result<_, _>>()
+            .map_err(|_| IdEventError::InvalidSigner(value.id.clone()))?;
+        Ok(Self {
+            id: value.id.clone(),
+            public_key: value.public_key.clone(),
+            purposes,
+        })
+    }
+}
+
 impl Ord for IdSigner {
     fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.id.cmp(&other.id)
@@ -34,4 +131,56 @@ impl PartialOrd for IdSigner {
     fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use idp2p_common::cbor;
+
+    /// Signer as encoded before purposes were added.
+    #[serde_as]
+    #[derive(Serialize)]
+    struct BaselineSigner {
+        id: String,
+        #[serde_as(as = "Bytes")]
+        public_key: Vec<u8>,
+    }
+
+    #[test]
+    fn baseline_signer_test() {
+        let baseline = BaselineSigner {
+            id: "signer".into(),
+            public_key: vec![1u8; 32],
+        };
+        let signer: IdSigner = cbor::decode(&cbor::encode(&baseline).unwrap()).unwrap();
+        assert_eq!(signer.purposes, default_purposes());
+
+        let state = signer.to_state(0, "2025-01-01T00:00:00Z");
+        let json = serde_json::to_value(&state).unwrap();
+        let mut baseline = json.as_object().unwrap().clone();
+        baseline.remove("purposes");
+        let decoded: crate::types::IdSigner =
+            serde_json::from_value(serde_json::Value::Object(baseline)).unwrap();
+        assert_eq!(decoded, state);
+    }
+
+    #[test]
+    fn empty_purposes_test() {
+        let mut signer = IdSigner {
+            id: "signer".into(),
+            public_key: vec![1u8; 32],
+            purposes: BTreeSet::new(),
+        };
+        let bytes = cbor::encode(&signer).unwrap();
+        assert!(cbor::decode::<IdSigner>(&bytes).is_err());
+
+        signer.purposes = default_purposes();
+        let mut state = signer.to_state(0, "2025-01-01T00:00:00Z");
+        state.purposes.clear();
+        assert!(matches!(
+            IdSigner::try_from(&state),
+            Err(IdEventError::InvalidSigner(_))
+        ));
+    }
+}
@@ -1,5 +1,10 @@
 use alloc::string::String;
 use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use idp2p_common::{
+    CBOR_CODE, DAG_CBOR_CODE, cbor,
+    dagcbor::{self, Link},
+    error::CommonError,
+};
 
 use super::error::IdEventError;
 
@@ -27,3 +32,32 @@ impl TryFrom<Timestamp> for DateTime<Utc> {
             .ok_or(IdEventError::InvalidTimestamp)
     }
 }
+
+/// Codecs of inception and event payloads.
+pub const PAYLOAD_CODES: [u64; 2] = [CBOR_CODE, DAG_CBOR_CODE];
+
+/// Encodes a payload as plain CBOR or as DAG-CBOR with its links.
+pub(crate) fn encode_payload<T: serde::Serialize>(
+    value: &T,
+    codec: u64,
+    links: &[Link],
+) -> Result<Vec<u8>, IdEventError> {
+    match codec {
+        CBOR_CODE => Ok(cbor::encode(value)?),
+        DAG_CBOR_CODE => Ok(dagcbor::encode(value, links)?),
+        _ => Err(CommonError::UnsupportedCodec(codec).into()),
+    }
+}
+
+/// Decodes a deterministic payload of either codec.
+pub(crate) fn decode_payload<T: serde::de::DeserializeOwned>(
+    bytes: &[u8],
+    codec: u64,
+    links: &[Link],
+) -> Result<T, IdEventError> {
+    match codec {
+        CBOR_CODE => Ok(cbor::decode_canonical(bytes)?),
+        DAG_CBOR_CODE => Ok(dagcbor::decode(bytes, links)?),
+        _ => Err(CommonError::UnsupportedCodec(codec).into()),
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
     exports::idp2p::core::id_verifier::Guest,
-    types::{IdEventReceipt, IdState, Idp2pError},
+    types::{
+        IdCheckpointReceipt, IdCheckpointTrust, IdEventReceipt, IdSignerHistory, IdState,
+        IdVerifiedState, Idp2pError,
+    },
 };
 
 extern crate alloc;
@@ -25,13 +28,42 @@ export!(GuestComponent);
 
 impl Guest for GuestComponent {
     #[doc = " Verifies an initial identity inception event."]
-    fn verify_inception(receipt: IdEventReceipt) -> Result<IdState, Idp2pError> {
-        Ok(receipt.verify_inception()?)
+    fn verify_inception(receipt: IdEventReceipt) -> Result<IdVerifiedState, Idp2pError> {
+        Ok(receipt.verify_inception()?.try_into()?)
     }
 
     #[doc = " Verifies an identity update event against the existing identity state."]
-    fn verify_event(state: IdState, receipt: IdEventReceipt) -> Result<IdState, Idp2pError> {
+    fn verify_event(
+        state: IdState,
+        receipt: IdEventReceipt,
+    ) -> Result<IdVerifiedState, Idp2pError> {
         let mut state = state.clone();
-        Ok(receipt.verify_event(&mut state)?)
+        Ok(receipt.verify_event(&mut state)?.try_into()?)
+    }
+
+    #[doc = " Replays an identity log from its inception, verifying all signatures in one batch."]
+    fn verify_log(
+        inception: IdEventReceipt,
+        events: Vec<IdEventReceipt>,
+    ) -> Result<IdVerifiedState, Idp2pError> {
+        Ok(inception.verify_log(&events)?.try_into()?)
+    }
+
+    #[doc = " Replays an identity log like `verify-log`, also returning every signer the identity has used."]
+    fn verify_log_with_history(
+        inception: IdEventReceipt,
+        events: Vec<IdEventReceipt>,
+    ) -> Result<(IdVerifiedState, IdSignerHistory), Idp2pError> {
+        let (state, history) = inception.verify_log_with_history(&events)?;
+        Ok((state.try_into()?, history))
+    }
+
+    #[doc = " Verifies a signed checkpoint of an identity state received from a peer."]
+    fn verify_checkpoint(
+        state: IdState,
+        checkpoint: IdCheckpointReceipt,
+        trust: IdCheckpointTrust,
+    ) -> Result<IdVerifiedState, Idp2pError> {
+        Ok(checkpoint.verify(&state, &trust)?.try_into()?)
     }
 }
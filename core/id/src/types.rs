@@ -2,10 +2,14 @@ mod event;
 mod state;
 mod error;
 mod proof;
+mod checkpoint;
+mod interop;
 
 pub use error::*;
 pub use event::*;
 pub use state::*;
 pub use proof::*;
+pub use checkpoint::*;
+pub use interop::*;
 
 
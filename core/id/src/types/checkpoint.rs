@@ -0,0 +1,368 @@
+use alloc::collections::BTreeSet;
+use alloc::str::FromStr;
+use cid::Cid;
+use idp2p_common::{CBOR_CODE, bytes::Bytes, cid::CidExt};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use crate::{
+    VERSION,
+    internal::{
+        checkpoint::IdCheckpoint,
+        error::IdEventError,
+        proof::IdProofPurpose,
+        signer::IdSigner,
+    },
+    types::{IdProof, IdState, PendingSignature, verify_proofs, verify_signatures},
+};
+
+/// A state snapshot signed by the current signers.
+///
+/// A peer holding a verified checkpoint can trust the referenced state
+/// without replaying the log and continue with `verify_event` from there.
+#[serde_as]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct IdCheckpointReceipt {
+    pub id: String,
+    pub version: String,
+    pub created_at: String,
+    #[serde_as(as = "Bytes")]
+    pub payload: Vec<u8>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub proofs: Vec<IdProof>,
+}
+
+/// Witness keys a verifier trusts and how many of them must sign.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct IdWitnesses {
+    /// Keys with the `assertion` purpose
+    pub signers: Vec<crate::types::IdSigner>,
+    pub threshold: u8,
+}
+
+/// What a verifier already trusts, the state a peer sends is not enough.
+// Mirrors the WIT variant, so the state can't be boxed
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum IdCheckpointTrust {
+    /// A state of the identity verified earlier, the checkpointed state must
+    /// have the same signers and commitments
+    State(IdState),
+    /// Witnesses that vouch for states they replayed
+    Witnesses(IdWitnesses),
+}
+
+/// Verifies proofs against `signers`, returns how many distinct signers
+/// made them.
+fn count_proofs(
+    proofs: &[IdProof],
+    payload: &[u8],
+    signers: &[crate::types::IdSigner],
+    purpose: IdProofPurpose,
+    pending: &mut Vec<PendingSignature>,
+) -> Result<usize, IdEventError> {
+    let signers: BTreeSet<IdSigner> = signers
+        .iter()
+        .map(IdSigner::try_from)
+        .collect::<Result<_, _>>()?;
+    verify_proofs(proofs, payload, &signers, purpose, pending)
+}
+
+/// Whether two states have the same signers, commitments and thresholds.
+fn same_keys(a: &IdState, b: &IdState) -> bool {
+    let ids = |state: &IdState| -> BTreeSet<String> {
+        state.signers.iter().map(|s| s.id.clone()).collect()
+    };
+    let next = |state: &IdState| -> BTreeSet<String> { state.next_signers.iter().cloned().collect() };
+    ids(a) == ids(b)
+        && next(a) == next(b)
+        && a.threshold == b.threshold
+        && a.next_threshold == b.next_threshold
+        && a.revoked == b.revoked
+        && a.next_id_proof == b.next_id_proof
+}
+
+impl IdCheckpointReceipt {
+    /// Verifies a checkpoint of `state`, a state received from a peer,
+    /// against what the verifier already trusts.
+    pub fn verify(
+        &self,
+        state: &IdState,
+        trust: &IdCheckpointTrust,
+    ) -> Result<IdState, IdEventError> {
+        if self.version != VERSION {
+            return Err(IdEventError::UnsupportedVersion);
+        }
+        let cid = Cid::from_str(&self.id)?;
+        cid.ensure(&self.payload, vec![CBOR_CODE])?;
+        let checkpoint: IdCheckpoint = idp2p_common::cbor::decode_canonical(&self.payload)?;
+
+        if checkpoint.version != VERSION {
+            return Err(IdEventError::UnsupportedVersion);
+        }
+        if checkpoint.id != state.id {
+            return Err(IdEventError::InvalidCheckpoint("id".into()));
+        }
+        if checkpoint.sn != state.sn {
+            return Err(IdEventError::InvalidCheckpoint("sn".into()));
+        }
+        if checkpoint.event_id != state.event_id {
+            return Err(IdEventError::InvalidCheckpoint("event id".into()));
+        }
+        if checkpoint.state_cid != state.state_cid()?.to_string() {
+            return Err(IdEventError::InvalidCheckpoint("state cid".into()));
+        }
+
+        let mut pending = vec![];
+        let controller_proofs: Vec<IdProof> = match trust {
+            IdCheckpointTrust::State(trusted) => {
+                if trusted.id != state.id || trusted.sn > state.sn {
+                    return Err(IdEventError::InvalidCheckpoint("trusted state".into()));
+                }
+                // Key changes are only trusted from the events that made them
+                if !same_keys(trusted, state) {
+                    return Err(IdEventError::InvalidCheckpoint("signers changed".into()));
+                }
+                self.proofs.clone()
+            }
+            IdCheckpointTrust::Witnesses(witnesses) => {
+                let (witness_proofs, controller_proofs): (Vec<IdProof>, Vec<IdProof>) = self
+                    .proofs
+                    .iter()
+                    .cloned()
+                    .partition(|p| witnesses.signers.iter().any(|w| w.id == p.key_id));
+                let signed = count_proofs(
+                    &witness_proofs,
+                    &self.payload,
+                    &witnesses.signers,
+                    IdProofPurpose::Assertion,
+                    &mut pending,
+                )?;
+                if witnesses.threshold == 0 || signed < witnesses.threshold as usize {
+                    return Err(IdEventError::LackOfMinProofs);
+                }
+                controller_proofs
+            }
+        };
+        // The current signers must vouch for the state too
+        let signed = count_proofs(
+            &controller_proofs,
+            &self.payload,
+            &state.signers,
+            IdProofPurpose::EventSigning,
+            &mut pending,
+        )?;
+        if signed < state.threshold as usize {
+            return Err(IdEventError::LackOfMinProofs);
+        }
+        verify_signatures(&pending)?;
+
+        Ok(state.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use ed25519_dalek::{Signer as _, SigningKey};
+    use idp2p_common::{ED_CODE, cbor as common_cbor};
+    use rand::rngs::OsRng;
+
+    fn create_signer() -> (String, SigningKey) {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let id = Cid::create(ED_CODE, signing_key.verifying_key().as_bytes())
+            .expect("cid")
+            .to_string();
+        (id, signing_key)
+    }
+
+    fn sign(payload: &[u8], creator: &str, kid: &str, sk: &SigningKey) -> IdProof {
+        sign_for(payload, creator, kid, sk, IdProofPurpose::EventSigning)
+    }
+
+    fn sign_for(
+        payload: &[u8],
+        creator: &str,
+        kid: &str,
+        sk: &SigningKey,
+        purpose: IdProofPurpose,
+    ) -> IdProof {
+        let created = Utc::now();
+        let data = IdProof::signing_input(creator, kid, &created, purpose, payload).unwrap();
+        IdProof {
+            id: Cid::create(CBOR_CODE, payload).expect("proof cid").to_string(),
+            did: creator.into(),
+            key_id: kid.into(),
+            created: created.to_rfc3339(),
+            purpose: purpose.as_ref().into(),
+            signature: sk.sign(&data).to_vec(),
+            previous: None,
+            format: None,
+        }
+    }
+
+    fn state_signer(sid: &str, sk: &SigningKey, purpose: &str) -> crate::types::IdSigner {
+        crate::types::IdSigner {
+            id: sid.to_string(),
+            public_key: sk.verifying_key().as_bytes().to_vec(),
+            purposes: vec![purpose.into()],
+            valid_from_sn: 0,
+            valid_until_sn: None,
+            valid_from: "2025-01-01T00:00:01Z".into(),
+            valid_until: None,
+        }
+    }
+
+    fn trusted(state: &IdState) -> IdCheckpointTrust {
+        IdCheckpointTrust::State(state.clone())
+    }
+
+    fn create_state(sid: &str, sk: &SigningKey) -> IdState {
+        IdState {
+            id: Cid::create(CBOR_CODE, b"idp2p-test").unwrap().to_string(),
+            sn: 3,
+            event_id: Cid::create(CBOR_CODE, b"last-event").unwrap().to_string(),
+            event_timestamp: "2025-01-01T00:00:01Z".into(),
+            prior_id: None,
+            next_id_proof: None,
+            threshold: 1,
+            next_threshold: 1,
+            signers: vec![state_signer(sid, sk, "event-signing")],
+            next_signers: vec![],
+            delegated_signers: vec![],
+            merkle_proof: "merkle-proof".into(),
+            revoked: false,
+            revoked_at: None,
+        }
+    }
+
+    fn create_receipt(state: &IdState, sid: &str, sk: &SigningKey) -> IdCheckpointReceipt {
+        let checkpoint = IdCheckpoint {
+            id: state.id.clone(),
+            version: VERSION.into(),
+            sn: state.sn,
+            event_id: state.event_id.clone(),
+            state_cid: state.state_cid().unwrap().to_string(),
+            timestamp: Utc::now().timestamp(),
+        };
+        let payload = common_cbor::encode(&checkpoint).unwrap();
+        IdCheckpointReceipt {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![sign(&payload, &state.id, sid, sk)],
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_success() {
+        let (sid, sk) = create_signer();
+        let state = create_state(&sid, &sk);
+        let receipt = create_receipt(&state, &sid, &sk);
+        let verified = receipt
+            .verify(&state, &trusted(&state))
+            .expect("checkpoint should pass");
+        assert_eq!(verified, state);
+
+        // Interactions since the trusted state don't change the keys
+        let mut earlier = state.clone();
+        earlier.sn = 1;
+        assert!(receipt.verify(&state, &trusted(&earlier)).is_ok());
+        let mut later = state.clone();
+        later.sn = 4;
+        assert!(receipt.verify(&state, &trusted(&later)).is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_state_mismatch() {
+        let (sid, sk) = create_signer();
+        let mut state = create_state(&sid, &sk);
+        let receipt = create_receipt(&state, &sid, &sk);
+        state.merkle_proof = "tampered".into();
+        let err = receipt.verify(&state, &trusted(&state)).unwrap_err();
+        assert!(matches!(err, IdEventError::InvalidCheckpoint(_)));
+    }
+
+    #[test]
+    fn test_checkpoint_signed_by_retired_signer() {
+        let (sid, sk) = create_signer();
+        let (new_sid, new_sk) = create_signer();
+        let mut state = create_state(&sid, &sk);
+        state.signers = vec![crate::types::IdSigner {
+            id: new_sid,
+            public_key: new_sk.verifying_key().as_bytes().to_vec(),
+            purposes: vec!["event-signing".into()],
+            valid_from_sn: 3,
+            valid_until_sn: None,
+            valid_from: "2025-01-01T00:00:01Z".into(),
+            valid_until: None,
+        }];
+        let receipt = create_receipt(&state, &sid, &sk);
+        let err = receipt.verify(&state, &trusted(&state)).unwrap_err();
+        assert!(matches!(err, IdEventError::InvalidSigner(_)));
+    }
+
+    #[test]
+    fn test_checkpoint_forged_state() {
+        let (sid, sk) = create_signer();
+        let honest = create_state(&sid, &sk);
+        // A peer sends a state of the same identity with its own keys
+        let (attacker_sid, attacker_sk) = create_signer();
+        let forged = create_state(&attacker_sid, &attacker_sk);
+        let receipt = create_receipt(&forged, &attacker_sid, &attacker_sk);
+        assert!(receipt.verify(&forged, &trusted(&forged)).is_ok());
+        let err = receipt.verify(&forged, &trusted(&honest)).unwrap_err();
+        assert!(matches!(err, IdEventError::InvalidCheckpoint(_)));
+        let witnesses = IdCheckpointTrust::Witnesses(IdWitnesses {
+            signers: vec![],
+            threshold: 1,
+        });
+        let err = receipt.verify(&forged, &witnesses).unwrap_err();
+        assert!(matches!(err, IdEventError::LackOfMinProofs));
+    }
+
+    #[test]
+    fn test_checkpoint_witnesses() {
+        let (sid, sk) = create_signer();
+        let state = create_state(&sid, &sk);
+        let (wid, wsk) = create_signer();
+        let (other_wid, other_wsk) = create_signer();
+        let witnesses = |threshold| {
+            IdCheckpointTrust::Witnesses(IdWitnesses {
+                signers: vec![
+                    state_signer(&wid, &wsk, "assertion"),
+                    state_signer(&other_wid, &other_wsk, "assertion"),
+                ],
+                threshold,
+            })
+        };
+        let mut receipt = create_receipt(&state, &sid, &sk);
+        let witness_proof = sign_for(
+            &receipt.payload,
+            "did:p2p:witness",
+            &wid,
+            &wsk,
+            IdProofPurpose::Assertion,
+        );
+        receipt.proofs.push(witness_proof.clone());
+        assert_eq!(receipt.verify(&state, &witnesses(1)).unwrap(), state);
+        assert!(matches!(
+            receipt.verify(&state, &witnesses(2)),
+            Err(IdEventError::LackOfMinProofs)
+        ));
+        // The same witness counts once
+        receipt.proofs.push(witness_proof);
+        assert!(receipt.verify(&state, &witnesses(2)).is_err());
+        receipt.proofs.pop();
+        assert!(receipt.verify(&state, &witnesses(0)).is_err());
+
+        // Witnesses alone are not enough
+        receipt.proofs.remove(0);
+        assert!(matches!(
+            receipt.verify(&state, &witnesses(1)),
+            Err(IdEventError::LackOfMinProofs)
+        ));
+    }
+}
@@ -2,17 +2,26 @@ use alloc::collections::BTreeSet;
 use alloc::str::FromStr;
 use chrono::{DateTime, Utc};
 use cid::Cid;
-use idp2p_common::{CBOR_CODE, ED_CODE, bytes::Bytes, cid::CidExt, error::CommonError};
+use idp2p_common::{
+    CBOR_CODE, blockstore::BlockStore, bytes::Bytes, cbor, cid::CidExt, error::CommonError,
+    verification::SIGNER_CODES,
+};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
 use crate::{
     VALID_FROM, VERSION,
     internal::{
-        error::IdEventError, event::IdEvent, inception::IdInception, signer::IdSigner,
-        utils::Timestamp,
+        error::IdEventError,
+        event::IdEvent,
+        inception::IdInception,
+        proof::IdProofPurpose,
+        signer::IdSigner,
+        utils::{PAYLOAD_CODES, Timestamp},
+    },
+    types::{
+        IdProof, IdSignerHistory, IdState, PendingSignature, verify_proofs, verify_signatures,
     },
-    types::{IdProof, IdState},
 };
 
 macro_rules! ensure {
@@ -48,41 +57,105 @@ impl PartialOrd for IdEventReceipt {
 }
 
 impl IdEventReceipt {
-    fn verify_proofs(&self, signers: &BTreeSet<IdSigner>) -> Result<(), IdEventError> {
-        let mut seen: BTreeSet<String> = BTreeSet::new();
-        for proof in self.proofs.iter() {
-            if !seen.insert(proof.key_id.clone()) {
-                return Err(IdEventError::invalid_proof(
-                    &proof.key_id,
-                    "duplicate proof",
-                ));
-            }
-            match proof.purpose.as_str() {
-                "id-delegation" => {
-                    proof.verify(&self.payload, &signers)?;
-                }
-                _ => {
-                    crate::host::verify_proof(&proof, &self.payload)
-                        .map_err(|e| IdEventError::invalid_proof(&proof.key_id, &e.code))?;
-                }
-            }
-        }
-        Ok(())
+    fn verify_proofs(
+        &self,
+        signers: &BTreeSet<IdSigner>,
+        pending: &mut Vec<PendingSignature>,
+    ) -> Result<usize, IdEventError> {
+        verify_proofs(
+            &self.proofs,
+            &self.payload,
+            signers,
+            IdProofPurpose::EventSigning,
+            pending,
+        )
     }
 
     pub fn verify_inception(&self) -> Result<IdState, IdEventError> {
+        let mut pending = vec![];
+        let state = self.check_inception(&mut pending)?;
+        verify_signatures(&pending)?;
+        Ok(state)
+    }
+
+    pub fn verify_event(&self, state: &mut IdState) -> Result<IdState, IdEventError> {
+        let mut pending = vec![];
+        let state = self.check_event(state, &mut pending)?;
+        verify_signatures(&pending)?;
+        Ok(state)
+    }
+
+    /// Verifies an inception and its events in order.
+    ///
+    /// Signatures of the whole log are verified in one batch at the end.
+    pub fn verify_log(&self, events: &[IdEventReceipt]) -> Result<IdState, IdEventError> {
+        Ok(self.verify_log_with_history(events)?.0)
+    }
+
+    /// Verifies a log like `verify_log`, recording every state in the
+    /// signer history.
+    pub fn verify_log_with_history(
+        &self,
+        events: &[IdEventReceipt],
+    ) -> Result<(IdState, IdSignerHistory), IdEventError> {
+        let mut pending = vec![];
+        let mut state = self.check_inception(&mut pending)?;
+        let mut history = IdSignerHistory::new(&state.id);
+        history.record(&state)?;
+        for event in events {
+            state = event.check_event(&mut state, &mut pending)?;
+            history.record(&state)?;
+        }
+        verify_signatures(&pending)?;
+        Ok((state, history))
+    }
+
+    /// Verifies an event like `verify_event` and records the new state in
+    /// the signer history.
+    pub fn verify_event_with_history(
+        &self,
+        state: &mut IdState,
+        history: &mut IdSignerHistory,
+    ) -> Result<IdState, IdEventError> {
+        let state = self.verify_event(state)?;
+        history.record(&state)?;
+        Ok(state)
+    }
+
+    /// Sequence number of an event, read from its payload.
+    pub fn sn(&self) -> Result<u64, IdEventError> {
+        let cid = Cid::from_str(&self.id)?;
+        Ok(IdEvent::from_payload(&self.payload, cid.codec())?.sn)
+    }
+
+    /// Persists the receipt as a CBOR block and pins it.
+    ///
+    /// Every call adds a reference, unpin the CID once the receipt is no
+    /// longer needed.
+    pub fn persist(&self, store: &mut impl BlockStore) -> Result<Cid, IdEventError> {
+        let cid = store.put(CBOR_CODE, &cbor::encode(self)?)?;
+        store.pin(&cid)?;
+        Ok(cid)
+    }
+
+    /// Loads a persisted receipt, its block is checked against the CID.
+    pub fn load(store: &impl BlockStore, cid: &Cid) -> Result<Self, IdEventError> {
+        let bytes = store
+            .get(cid)?
+            .ok_or_else(|| CommonError::BlockNotFound(cid.to_string()))?;
+        Ok(cbor::decode(&bytes)?)
+    }
+
+    fn check_inception(&self, pending: &mut Vec<PendingSignature>) -> Result<IdState, IdEventError> {
         ensure!(self.version == VERSION, IdEventError::UnsupportedVersion);
         let id = Cid::from_str(&self.id)?;
-        id.ensure(&self.payload, vec![CBOR_CODE])?;
-        let inception: IdInception = idp2p_common::cbor::decode(&self.payload)
-            .map_err(|e| CommonError::DecodeError(e.to_string()))?;
+        id.ensure(&self.payload, PAYLOAD_CODES.to_vec())?;
+        let inception = IdInception::from_payload(&self.payload, id.codec())?;
 
         let valid_from: DateTime<Utc> = VALID_FROM
             .parse()
             .map_err(|_| IdEventError::InvalidTimestamp)?;
-        let total_signers = inception.signers.len() as u8;
         let total_next_signers = inception.next_signers.len() as u8;
-        let total_signatures = self.proofs.len() as u8;
 
         // Compare seconds to seconds
         ensure!(
@@ -91,14 +164,10 @@ impl IdEventReceipt {
         );
 
         ensure!(
-            total_signers >= total_signatures,
+            inception.signers.len() >= self.proofs.len(),
             IdEventError::LackOfMinProofs
         );
 
-        ensure!(
-            total_signatures >= inception.threshold,
-            IdEventError::LackOfMinProofs
-        );
         ensure!(
             inception.version == VERSION,
             IdEventError::UnsupportedVersion
@@ -114,16 +183,21 @@ impl IdEventReceipt {
         for next_kid_str in &inception.next_signers {
             let next_kid = Cid::from_str(next_kid_str)?;
             ensure!(
-                next_kid.codec() == ED_CODE,
+                SIGNER_CODES.contains(&next_kid.codec()),
                 IdEventError::InvalidNextSigner(next_kid_str.clone())
             );
         }
 
         let timestamp: String = String::try_from(Timestamp(inception.timestamp))?;
-        self.verify_proofs(&inception.signers)?;
-       
+        let signed = self.verify_proofs(&inception.signers, pending)?;
+        ensure!(
+            signed >= inception.threshold as usize,
+            IdEventError::LackOfMinProofs
+        );
+
         let id_state = IdState {
             id: self.id.clone(),
+            sn: 0,
             event_id: self.id.clone(),
             event_timestamp: timestamp.clone(),
             prior_id: inception.prior_id.clone(),
@@ -132,15 +206,9 @@ impl IdEventReceipt {
             next_threshold: inception.next_threshold,
             signers: inception
                 .signers
-                .clone()
                 .into_iter()
                 .map(|s| s.to_state(0, &timestamp))
                 .collect(),
-            current_signers: inception
-                .signers
-                .into_iter()
-                .map(|signer| signer.id)
-                .collect(),
             next_signers: inception.next_signers.into_iter().collect(),
             delegated_signers: vec![],
             merkle_proof: inception.merkle_proof,
@@ -150,11 +218,15 @@ impl IdEventReceipt {
         Ok(id_state)
     }
 
-    pub fn verify_event(&self, state: &mut IdState) -> Result<IdState, IdEventError> {
+    fn check_event(
+        &self,
+        state: &mut IdState,
+        pending: &mut Vec<PendingSignature>,
+    ) -> Result<IdState, IdEventError> {
         let mut state = state.to_owned();
         let cid = Cid::from_str(&self.id)?;
-        cid.ensure(&self.payload, vec![CBOR_CODE])?;
-        let event: IdEvent = idp2p_common::cbor::decode(&self.payload)?;
+        cid.ensure(&self.payload, PAYLOAD_CODES.to_vec())?;
+        let event = IdEvent::from_payload(&self.payload, cid.codec())?;
 
         ensure!(event.version == VERSION, IdEventError::UnsupportedVersion);
 
@@ -171,6 +243,10 @@ impl IdEventReceipt {
             event.previous == state.event_id,
             IdEventError::PreviousNotMatch
         );
+        ensure!(
+            state.sn.checked_add(1) == Some(event.sn),
+            IdEventError::InvalidSn(event.sn)
+        );
 
         let timestamp: String = String::try_from(Timestamp(event.timestamp))?;
         use crate::internal::event::IdEventKind::*;
@@ -179,17 +255,14 @@ impl IdEventReceipt {
                 let proof_signers: BTreeSet<IdSigner> = state
                     .signers
                     .iter()
-                    .map(|s| IdSigner {
-                        id: s.id.clone(),
-                        public_key: s.public_key.clone(),
-                    })
-                    .collect();
-                // Require at least `state.threshold` proofs
+                    .map(IdSigner::try_from)
+                    .collect::<Result<_, _>>()?;
+                // Require at least `state.threshold` signer proofs
+                let signed = self.verify_proofs(&proof_signers, pending)?;
                 ensure!(
-                    self.proofs.len() as u8 >= state.threshold,
+                    signed >= state.threshold as usize,
                     IdEventError::LackOfMinProofs
                 );
-                self.verify_proofs(&proof_signers)?;
                 state.merkle_proof = merkle_proof;
             }
             Rotation {
@@ -205,10 +278,6 @@ impl IdEventReceipt {
                 let total_signers = all_signers.len() as u8;
                 let total_revealed_signers = revealed_signers.len() as u8;
                 let total_next_signers = next_signers.len() as u8;
-                ensure!(
-                    total_signers == self.proofs.len() as u8,
-                    IdEventError::ThresholdNotMatch
-                );
                 ensure!(total_signers >= threshold, IdEventError::ThresholdNotMatch);
 
                 ensure!(
@@ -229,35 +298,26 @@ impl IdEventReceipt {
                 for next_kid_str in &next_signers {
                     let next_kid = Cid::from_str(next_kid_str)?;
                     ensure!(
-                        next_kid.codec() == ED_CODE,
+                        SIGNER_CODES.contains(&next_kid.codec()),
                         IdEventError::InvalidNextSigner(next_kid_str.clone())
                     );
                 }
-                self.verify_proofs(&all_signers)?;
-                for signer_id in &state.current_signers {
-                    let signer = state
-                        .signers
-                        .iter_mut()
-                        .find(|s| &s.id == signer_id)
-                        .ok_or(IdEventError::InvalidSigner(signer_id.clone()))?;
-                    signer.valid_until = Some(timestamp.clone());
-                }
-                let all_signers: Vec<crate::types::IdSigner> = all_signers
-                    .clone()
+                // Every revealed and new signer signs
+                let signed = self.verify_proofs(&all_signers, pending)?;
+                ensure!(
+                    signed == all_signers.len(),
+                    IdEventError::ThresholdNotMatch
+                );
+                // Previous signers move to the signer history
+                state.signers = all_signers
                     .into_iter()
                     .map(|s| s.to_state(event.sn, &timestamp))
                     .collect();
-                state.signers.extend(all_signers);
                 state.next_signers = next_signers.into_iter().collect();
                 state.threshold = threshold;
                 state.next_threshold = next_threshold;
             }
             Revocation { revealed_signers } => {
-                ensure!(
-                    revealed_signers.len() == self.proofs.len(),
-                    IdEventError::ThresholdNotMatch
-                );
-
                 ensure!(
                     revealed_signers.len() as u8 >= state.next_threshold,
                     IdEventError::ThresholdNotMatch
@@ -268,7 +328,11 @@ impl IdEventReceipt {
                         IdEventError::ThresholdNotMatch
                     );
                 }
-                self.verify_proofs(&revealed_signers)?;
+                let signed = self.verify_proofs(&revealed_signers, pending)?;
+                ensure!(
+                    signed == revealed_signers.len(),
+                    IdEventError::ThresholdNotMatch
+                );
                 state.next_signers = vec![];
                 state.revoked = true;
                 state.revoked_at = Some(timestamp.clone());
@@ -277,11 +341,6 @@ impl IdEventReceipt {
                 revealed_signers,
                 next_id_proof,
             } => {
-                ensure!(
-                    revealed_signers.len() == self.proofs.len(),
-                    IdEventError::ThresholdNotMatch
-                );
-
                 ensure!(
                     revealed_signers.len() as u8 >= state.next_threshold,
                     IdEventError::ThresholdNotMatch
@@ -292,7 +351,11 @@ impl IdEventReceipt {
                         IdEventError::ThresholdNotMatch
                     );
                 }
-                self.verify_proofs(&revealed_signers)?;
+                let signed = self.verify_proofs(&revealed_signers, pending)?;
+                ensure!(
+                    signed == revealed_signers.len(),
+                    IdEventError::ThresholdNotMatch
+                );
                 state.next_signers = vec![];
                 state.next_id_proof = Some(next_id_proof);
             }
@@ -300,6 +363,7 @@ impl IdEventReceipt {
 
         // Update event timestamp in state
         state.event_timestamp = timestamp.clone();
+        state.sn = event.sn;
         state.event_id = self.id.clone();
 
         Ok(state)
@@ -310,13 +374,15 @@ impl IdEventReceipt {
 mod tests {
     use super::*;
     use crate::internal::event::IdEventKind::*;
-    use crate::internal::signer::IdSigner as InternalSigner;
+    use crate::internal::signer::{IdKeyPurpose, IdSigner as InternalSigner};
     use crate::internal::utils::Timestamp;
     use alloc::collections::BTreeSet;
     use chrono::{DateTime, Utc};
     use ed25519_dalek::{Signer as _, SigningKey, VerifyingKey};
-    use ciborium::cbor;
-    use idp2p_common::{cbor as common_cbor, cid::CidExt, CBOR_CODE, ED_CODE};
+    use idp2p_common::{
+        blockstore::MemoryBlockStore, cbor as common_cbor, cid::CidExt, crypto::signer::Signer,
+        CBOR_CODE, DAG_CBOR_CODE, ED_CODE,
+    };
     use rand::rngs::OsRng;
 
     fn valid_timestamp() -> i64 {
@@ -339,31 +405,11 @@ mod tests {
     }
 
     fn sign_receipt(payload: &[u8], creator: &str, kid: &str, sk: &SigningKey) -> IdProof {
-        let created = Utc::now();
-        let created_str = created.to_rfc3339();
-        let data = cbor!({
-            "did" => creator.to_string(),
-            "key_id" => kid.to_string(),
-            "created" => created.timestamp(),
-            "purpose" => "id-delegation",
-            "payload" => payload.to_vec(),
-        })
-        .expect("cbor data");
-
-        let data_bytes = common_cbor::encode(&data);
-        let signature = sk.sign(&data_bytes);
-
-        IdProof {
-            id: Cid::create(CBOR_CODE, payload)
-                .expect("proof cid")
-                .to_string(),
-            did: creator.into(),
-            key_id: kid.into(),
-            created: created_str,
-            purpose: "id-delegation".into(),
-            signature: signature.to_vec(),
-            previous: None,
-        }
+        let mut signer = Signer::new(ED_CODE, sk.as_bytes()).expect("signer");
+        // Some tests hash signer ids with other functions
+        signer.id = kid.into();
+        IdProof::create(creator, &signer, &Utc::now(), IdProofPurpose::EventSigning, payload)
+            .expect("proof")
     }
 
     fn base_state_with_signer(id: &str, pubkey: &[u8]) -> IdState {
@@ -373,6 +419,7 @@ mod tests {
             id: Cid::create(CBOR_CODE, b"idp2p-test")
                 .expect("state cid")
                 .to_string(),
+            sn: 0,
             event_id: Cid::create(CBOR_CODE, b"previous-event")
                 .expect("event cid")
                 .to_string(),
@@ -384,12 +431,12 @@ mod tests {
             signers: vec![crate::types::IdSigner {
                 id: id.to_string(),
                 public_key: pubkey.to_vec(),
+                purposes: vec!["event-signing".into()],
                 valid_from_sn: 0,
                 valid_until_sn: None,
                 valid_from: ts_str.clone(),
                 valid_until: None,
             }],
-            current_signers: vec![id.to_string()],
             next_signers: vec![id.to_string()],
             delegated_signers: vec![],
             merkle_proof: "existing-merkle-proof".into(),
@@ -398,6 +445,69 @@ mod tests {
         }
     }
 
+    fn create_log(sk: &SigningKey, sid: &str, len: u64) -> (IdEventReceipt, Vec<IdEventReceipt>) {
+        create_log_with(sk, sid, len, CBOR_CODE)
+    }
+
+    fn create_log_with(
+        sk: &SigningKey,
+        sid: &str,
+        len: u64,
+        codec: u64,
+    ) -> (IdEventReceipt, Vec<IdEventReceipt>) {
+        let signer = InternalSigner {
+            id: sid.to_string(),
+            public_key: sk.verifying_key().as_bytes().to_vec(),
+            purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
+        };
+        let inception = IdInception {
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: valid_timestamp(),
+            prior_id: None,
+            threshold: 1,
+            next_threshold: 1,
+            signers: BTreeSet::from([signer]),
+            next_signers: BTreeSet::from([sid.to_string()]),
+            delegated_signers: BTreeSet::new(),
+            merkle_proof: "inception-proof".into(),
+        };
+        let payload = inception.to_payload(codec).unwrap();
+        let id = Cid::create(codec, &payload).unwrap().to_string();
+        let inception = IdEventReceipt {
+            id: id.clone(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![sign_receipt(&payload, &id, sid, sk)],
+        };
+        let mut previous = id.clone();
+        let mut events = vec![];
+        for sn in 1..=len {
+            let event = IdEvent {
+                sn,
+                version: VERSION.into(),
+                patch: Cid::default(),
+                timestamp: valid_timestamp(),
+                previous: previous.clone(),
+                body: Interaction {
+                    merkle_proof: format!("proof-{sn}"),
+                },
+            };
+            let payload = event.to_payload(codec).unwrap();
+            let receipt = IdEventReceipt {
+                id: Cid::create(codec, &payload).unwrap().to_string(),
+                version: VERSION.into(),
+                created_at: Utc::now().to_rfc3339(),
+                payload: payload.clone(),
+                proofs: vec![sign_receipt(&payload, &id, sid, sk)],
+            };
+            previous = receipt.id.clone();
+            events.push(receipt);
+        }
+        (inception, events)
+    }
+
     #[test]
     fn test_interaction_event_success() {
         let (sid, vk, sk) = create_signer();
@@ -414,7 +524,7 @@ mod tests {
                 merkle_proof: "new-proof".into(),
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
             version: VERSION.into(),
@@ -437,7 +547,7 @@ mod tests {
         let ts = valid_timestamp();
 
         let event = IdEvent {
-            sn: 2,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: ts,
@@ -446,7 +556,7 @@ mod tests {
                 merkle_proof: "proof".into(),
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
             version: VERSION.into(),
@@ -467,7 +577,7 @@ mod tests {
         let mut state = base_state_with_signer(&sid, vk.as_bytes());
 
         let event = IdEvent {
-            sn: 3,
+            sn: 1,
             version: "0.9".into(),
             patch: Cid::default(),
             timestamp: valid_timestamp(),
@@ -476,7 +586,7 @@ mod tests {
                 merkle_proof: "proof".into(),
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
             version: VERSION.into(),
@@ -495,7 +605,7 @@ mod tests {
         let ts = valid_timestamp() - 10;
 
         let event = IdEvent {
-            sn: 4,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: ts,
@@ -504,7 +614,7 @@ mod tests {
                 merkle_proof: "proof".into(),
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
             version: VERSION.into(),
@@ -525,16 +635,16 @@ mod tests {
         state.signers.push(crate::types::IdSigner {
             id: sid2.clone(),
             public_key: vk2.as_bytes().to_vec(),
+            purposes: vec!["event-signing".into()],
             valid_from_sn: 0,
             valid_until_sn: None,
             valid_from: timestamp_string(ts),
             valid_until: None,
         });
-        state.current_signers.push(sid2.clone());
         state.threshold = 2;
 
         let event = IdEvent {
-            sn: 5,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: ts,
@@ -543,7 +653,7 @@ mod tests {
                 merkle_proof: "proof".into(),
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
             version: VERSION.into(),
@@ -561,7 +671,7 @@ mod tests {
         let mut state = base_state_with_signer(&sid, vk.as_bytes());
 
         let event = IdEvent {
-            sn: 6,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: valid_timestamp(),
@@ -570,7 +680,7 @@ mod tests {
                 merkle_proof: "proof".into(),
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
             version: VERSION.into(),
@@ -582,6 +692,34 @@ mod tests {
         assert!(matches!(err, IdEventError::PreviousNotMatch));
     }
 
+    #[test]
+    fn test_sn_must_follow_state() {
+        let (sid, vk, sk) = create_signer();
+        let mut state = base_state_with_signer(&sid, vk.as_bytes());
+        for sn in [0, 2, u64::MAX] {
+            let event = IdEvent {
+                sn,
+                version: VERSION.into(),
+                patch: Cid::default(),
+                timestamp: valid_timestamp(),
+                previous: state.event_id.clone(),
+                body: Interaction {
+                    merkle_proof: "proof".into(),
+                },
+            };
+            let payload = common_cbor::encode(&event).unwrap();
+            let receipt = IdEventReceipt {
+                id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+                version: VERSION.into(),
+                created_at: Utc::now().to_rfc3339(),
+                payload: payload.clone(),
+                proofs: vec![sign_receipt(&payload, &state.id, &sid, &sk)],
+            };
+            let err = receipt.verify_event(&mut state).unwrap_err();
+            assert!(matches!(err, IdEventError::InvalidSn(s) if s == sn));
+        }
+    }
+
     #[test]
     fn test_rotation_event_success() {
         let (sid1, vk1, sk1) = create_signer();
@@ -595,17 +733,19 @@ mod tests {
         revealed.insert(InternalSigner {
             id: sid1.clone(),
             public_key: vk1.as_bytes().to_vec(),
+            purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
         });
         let mut new_signers = BTreeSet::new();
         new_signers.insert(InternalSigner {
             id: sid2.clone(),
             public_key: vk2.as_bytes().to_vec(),
+            purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
         });
         let mut next_signers = BTreeSet::new();
         next_signers.insert(sid2.clone());
 
         let event = IdEvent {
-            sn: 7,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: ts,
@@ -618,7 +758,7 @@ mod tests {
                 next_signers: next_signers,
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
             version: VERSION.into(),
@@ -634,18 +774,14 @@ mod tests {
             .verify_event(&mut state)
             .expect("rotation should pass");
         let expected_ts = timestamp_string(ts);
-        let original = updated
-            .signers
-            .iter()
-            .find(|s| s.id == sid1)
-            .expect("original signer");
         assert_eq!(updated.threshold, 1);
         assert_eq!(updated.next_signers, vec![sid2.clone()]);
-        assert_eq!(original.valid_until.as_ref(), Some(&expected_ts));
+        assert_eq!(updated.signers.len(), 2);
         assert!(updated
             .signers
             .iter()
-            .any(|s| s.id == sid2 && s.valid_from_sn == event.sn));
+            .all(|s| s.valid_from_sn == event.sn && s.valid_from == expected_ts));
+        assert!(updated.signers.iter().any(|s| s.id == sid2));
     }
 
     #[test]
@@ -659,6 +795,7 @@ mod tests {
         revealed.insert(InternalSigner {
             id: sid.clone(),
             public_key: vk.as_bytes().to_vec(),
+            purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
         });
         let invalid_next = Cid::create(CBOR_CODE, vk.as_bytes())
             .unwrap()
@@ -667,7 +804,7 @@ mod tests {
         next_signers.insert(invalid_next);
 
         let event = IdEvent {
-            sn: 8,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: valid_timestamp(),
@@ -680,7 +817,7 @@ mod tests {
                 next_signers,
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
             version: VERSION.into(),
@@ -704,9 +841,10 @@ mod tests {
         revealed.insert(InternalSigner {
             id: sid.clone(),
             public_key: vk.as_bytes().to_vec(),
+            purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
         });
         let event = IdEvent {
-            sn: 9,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: ts,
@@ -715,7 +853,7 @@ mod tests {
                 revealed_signers: revealed,
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
             version: VERSION.into(),
@@ -745,9 +883,10 @@ mod tests {
         revealed.insert(InternalSigner {
             id: sid1.clone(),
             public_key: vk1.as_bytes().to_vec(),
+            purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
         });
         let event = IdEvent {
-            sn: 10,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: valid_timestamp(),
@@ -756,7 +895,7 @@ mod tests {
                 revealed_signers: revealed,
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
             version: VERSION.into(),
@@ -780,10 +919,11 @@ mod tests {
         revealed.insert(InternalSigner {
             id: sid.clone(),
             public_key: vk.as_bytes().to_vec(),
+            purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
         });
         let next_id_proof = "did:idp2p:new-proof";
         let event = IdEvent {
-            sn: 11,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: ts,
@@ -793,7 +933,7 @@ mod tests {
                 next_id_proof: next_id_proof.into(),
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
             version: VERSION.into(),
@@ -815,7 +955,7 @@ mod tests {
         let (sid, vk, sk) = create_signer();
         let mut state = base_state_with_signer(&sid, vk.as_bytes());
         let event = IdEvent {
-            sn: 12,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: valid_timestamp(),
@@ -824,7 +964,7 @@ mod tests {
                 merkle_proof: "proof".into(),
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let p1 = sign_receipt(&payload, &state.id, &sid, &sk);
         let p2 = sign_receipt(&payload, &state.id, &sid, &sk);
         let receipt = IdEventReceipt {
@@ -843,7 +983,7 @@ mod tests {
         let (sid, vk, sk) = create_signer();
         let mut state = base_state_with_signer(&sid, vk.as_bytes());
         let event = IdEvent {
-            sn: 13,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: valid_timestamp(),
@@ -852,7 +992,7 @@ mod tests {
                 merkle_proof: "proof".into(),
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let mut proof = sign_receipt(&payload, &state.id, &sid, &sk);
         proof.created = "not-a-date".into();
         let receipt = IdEventReceipt {
@@ -871,7 +1011,7 @@ mod tests {
         let (sid, vk, sk) = create_signer();
         let mut state = base_state_with_signer(&sid, vk.as_bytes());
         let event = IdEvent {
-            sn: 14,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: valid_timestamp(),
@@ -880,7 +1020,7 @@ mod tests {
                 merkle_proof: "proof".into(),
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let mut proof = sign_receipt(&payload, &state.id, &sid, &sk);
         proof.signature[0] ^= 0xFF;
         let receipt = IdEventReceipt {
@@ -900,7 +1040,7 @@ mod tests {
         let (sid2, _vk2, sk2) = create_signer();
         let mut state = base_state_with_signer(&sid1, vk1.as_bytes());
         let event = IdEvent {
-            sn: 15,
+            sn: 1,
             version: VERSION.into(),
             patch: Cid::default(),
             timestamp: valid_timestamp(),
@@ -909,7 +1049,7 @@ mod tests {
                 merkle_proof: "proof".into(),
             },
         };
-        let payload = common_cbor::encode(&event);
+        let payload = common_cbor::encode(&event).unwrap();
         let proof = sign_receipt(&payload, &state.id, &sid2, &sk2);
         let receipt = IdEventReceipt {
             id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
@@ -921,4 +1061,525 @@ mod tests {
         let err = receipt.verify_event(&mut state).unwrap_err();
         assert!(matches!(err, IdEventError::InvalidSigner(_)));
     }
+
+    #[test]
+    fn test_authentication_key_cannot_sign_rotation() {
+        let (sid1, vk1, sk1) = create_signer();
+        let (sid2, _vk2, _sk2) = create_signer();
+        let mut state = base_state_with_signer(&sid1, vk1.as_bytes());
+        state.next_signers = vec![sid1.clone()];
+
+        let mut revealed = BTreeSet::new();
+        revealed.insert(InternalSigner {
+            id: sid1.clone(),
+            public_key: vk1.as_bytes().to_vec(),
+            purposes: BTreeSet::from([IdKeyPurpose::Authentication]),
+        });
+        let mut next_signers = BTreeSet::new();
+        next_signers.insert(sid2.clone());
+        let event = IdEvent {
+            sn: 1,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: valid_timestamp(),
+            previous: state.event_id.clone(),
+            body: Rotation {
+                threshold: 1,
+                next_threshold: 1,
+                revealed_signers: revealed,
+                new_signers: BTreeSet::new(),
+                next_signers,
+            },
+        };
+        let payload = common_cbor::encode(&event).unwrap();
+        let receipt = IdEventReceipt {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![sign_receipt(&payload, &state.id, &sid1, &sk1)],
+        };
+        let err = receipt.verify_event(&mut state).unwrap_err();
+        assert!(matches!(err, IdEventError::InvalidProof { .. }));
+    }
+
+    #[test]
+    fn test_unknown_proof_purpose_rejected() {
+        let (sid, vk, sk) = create_signer();
+        let mut state = base_state_with_signer(&sid, vk.as_bytes());
+        let event = IdEvent {
+            sn: 1,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: valid_timestamp(),
+            previous: state.event_id.clone(),
+            body: Interaction {
+                merkle_proof: "proof".into(),
+            },
+        };
+        let payload = common_cbor::encode(&event).unwrap();
+        let mut proof = sign_receipt(&payload, &state.id, &sid, &sk);
+        proof.purpose = "anything".into();
+        let receipt = IdEventReceipt {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![proof],
+        };
+        let err = receipt.verify_event(&mut state).unwrap_err();
+        assert!(matches!(err, IdEventError::InvalidProof { .. }));
+    }
+
+    #[test]
+    fn test_proof_purpose_cannot_be_replayed() {
+        let (sid, vk, sk) = create_signer();
+        let mut state = base_state_with_signer(&sid, vk.as_bytes());
+        state.signers[0].purposes.push("authentication".into());
+        let event = IdEvent {
+            sn: 1,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: valid_timestamp(),
+            previous: state.event_id.clone(),
+            body: Interaction {
+                merkle_proof: "proof".into(),
+            },
+        };
+        let payload = common_cbor::encode(&event).unwrap();
+        // Signed as an authentication answer, relabelled as event signing
+        let created = Utc::now();
+        let data = IdProof::signing_input(
+            &state.id,
+            &sid,
+            &created,
+            IdProofPurpose::Authentication,
+            &payload,
+        )
+        .unwrap();
+        let mut proof = sign_receipt(&payload, &state.id, &sid, &sk);
+        proof.created = created.to_rfc3339();
+        proof.signature = sk.sign(&data).to_vec();
+        let receipt = IdEventReceipt {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![proof.clone()],
+        };
+        let err = receipt.verify_event(&mut state).unwrap_err();
+        assert!(matches!(err, IdEventError::InvalidProof { .. }));
+
+        proof.purpose = "authentication".into();
+        let receipt = IdEventReceipt {
+            proofs: vec![proof],
+            ..receipt
+        };
+        let err = receipt.verify_event(&mut state).unwrap_err();
+        assert!(matches!(err, IdEventError::InvalidProof { .. }));
+    }
+
+    #[test]
+    fn test_interaction_with_p256_signer() {
+        use p256::ecdsa::{Signature, SigningKey as P256SigningKey};
+
+        let sk = P256SigningKey::random(&mut OsRng);
+        let public = sk.verifying_key().to_encoded_point(true);
+        let sid = Cid::create(idp2p_common::P256_CODE, public.as_bytes())
+            .unwrap()
+            .to_string();
+        let mut state = base_state_with_signer(&sid, public.as_bytes());
+        let event = IdEvent {
+            sn: 1,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: valid_timestamp(),
+            previous: state.event_id.clone(),
+            body: Interaction {
+                merkle_proof: "proof".into(),
+            },
+        };
+        let payload = common_cbor::encode(&event).unwrap();
+        let created = Utc::now();
+        let data = IdProof::signing_input(
+            &state.id,
+            &sid,
+            &created,
+            IdProofPurpose::EventSigning,
+            &payload,
+        )
+        .unwrap();
+        let signature: Signature = sk.sign(&data);
+        let proof = IdProof {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            did: state.id.clone(),
+            key_id: sid.clone(),
+            created: created.to_rfc3339(),
+            purpose: "event-signing".into(),
+            signature: signature.to_bytes().to_vec(),
+            previous: None,
+            format: None,
+        };
+        let receipt = IdEventReceipt {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![proof],
+        };
+        let updated = receipt
+            .verify_event(&mut state)
+            .expect("p256 interaction should pass");
+        assert_eq!(updated.event_id, receipt.id);
+    }
+
+    #[test]
+    fn test_rotation_commits_to_pq_next_signer() {
+        let (sid, vk, sk) = create_signer();
+        let mut state = base_state_with_signer(&sid, vk.as_bytes());
+        state.next_signers = vec![sid.clone()];
+
+        let mut revealed = BTreeSet::new();
+        revealed.insert(InternalSigner {
+            id: sid.clone(),
+            public_key: vk.as_bytes().to_vec(),
+            purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
+        });
+        let pq_next = Cid::create(idp2p_common::MLDSA65_CODE, b"ml-dsa-65 public key")
+            .unwrap()
+            .to_string();
+        let event = IdEvent {
+            sn: 1,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: valid_timestamp(),
+            previous: state.event_id.clone(),
+            body: Rotation {
+                threshold: 1,
+                next_threshold: 1,
+                revealed_signers: revealed,
+                new_signers: BTreeSet::new(),
+                next_signers: BTreeSet::from([pq_next.clone()]),
+            },
+        };
+        let payload = common_cbor::encode(&event).unwrap();
+        let receipt = IdEventReceipt {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![sign_receipt(&payload, &state.id, &sid, &sk)],
+        };
+        let updated = receipt
+            .verify_event(&mut state)
+            .expect("rotation into pq commitment should pass");
+        assert_eq!(updated.next_signers, vec![pq_next]);
+    }
+
+    #[test]
+    fn test_verify_log_success() {
+        let (sid, _vk, sk) = create_signer();
+        let (inception, events) = create_log(&sk, &sid, 4);
+        let state = inception.verify_log(&events).expect("log should pass");
+        assert_eq!(state.sn, 4);
+        assert_eq!(state.event_id, events[3].id);
+        assert_eq!(state.merkle_proof, "proof-4");
+    }
+
+    #[test]
+    fn test_verify_log_records_history() {
+        let (sid, _vk, sk) = create_signer();
+        let (inception, mut events) = create_log(&sk, &sid, 1);
+        let signer = InternalSigner {
+            id: sid.clone(),
+            public_key: sk.verifying_key().as_bytes().to_vec(),
+            purposes: BTreeSet::from([IdKeyPurpose::EventSigning]),
+        };
+        let rotation = IdEvent {
+            sn: 2,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: valid_timestamp(),
+            previous: events[0].id.clone(),
+            body: Rotation {
+                threshold: 1,
+                next_threshold: 1,
+                revealed_signers: BTreeSet::from([signer]),
+                new_signers: BTreeSet::new(),
+                next_signers: BTreeSet::from([sid.clone()]),
+            },
+        };
+        let payload = common_cbor::encode(&rotation).unwrap();
+        events.push(IdEventReceipt {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![sign_receipt(&payload, &inception.id, &sid, &sk)],
+        });
+
+        let (state, history) = inception.verify_log_with_history(&events).unwrap();
+        assert_eq!(state, inception.verify_log(&events).unwrap());
+        assert_eq!(state.signers.len(), 1);
+        assert_eq!(history.signers.len(), 2);
+        assert_eq!(history.signer_at(&sid, 1).unwrap().valid_until_sn, Some(2));
+        assert_eq!(history.signer_at(&sid, 2).unwrap().valid_from_sn, 2);
+
+        // Stepwise replay records the same history
+        let mut state = inception.verify_inception().unwrap();
+        let mut stepwise = IdSignerHistory::new(&state.id);
+        stepwise.record(&state).unwrap();
+        for event in &events {
+            state = event
+                .verify_event_with_history(&mut state, &mut stepwise)
+                .unwrap();
+        }
+        assert_eq!(stepwise, history);
+    }
+
+    #[test]
+    fn test_verify_dag_cbor_log() {
+        let (sid, _vk, sk) = create_signer();
+        let (inception, events) = create_log_with(&sk, &sid, 3, DAG_CBOR_CODE);
+        let state = inception.verify_log(&events).expect("dag-cbor log should pass");
+        assert_eq!(state.sn, 3);
+        assert_eq!(Cid::from_str(&state.event_id).unwrap().codec(), DAG_CBOR_CODE);
+
+        // Previous is a tag 42 link to the prior event
+        let previous = Cid::from_str(&events[1].id).unwrap().to_bytes();
+        let link = [&[0xd8, 0x2a, 0x58, previous.len() as u8 + 1, 0x00][..], &previous].concat();
+        assert!(events[2].payload.windows(link.len()).any(|w| w == link.as_slice()));
+    }
+
+    #[test]
+    fn test_verify_mixed_codec_log() {
+        let (sid, _vk, sk) = create_signer();
+        let (inception, _) = create_log(&sk, &sid, 0);
+        let mut state = inception.verify_inception().unwrap();
+        let event = IdEvent {
+            sn: 1,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: valid_timestamp(),
+            previous: inception.id.clone(),
+            body: Interaction {
+                merkle_proof: "dag-proof".into(),
+            },
+        };
+        let payload = event.to_payload(DAG_CBOR_CODE).unwrap();
+        let receipt = IdEventReceipt {
+            id: Cid::create(DAG_CBOR_CODE, &payload).unwrap().to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![sign_receipt(&payload, &inception.id, &sid, &sk)],
+        };
+        assert_eq!(receipt.verify_event(&mut state).unwrap().merkle_proof, "dag-proof");
+
+        // The id codec decides how the payload is decoded
+        let mislabeled = IdEventReceipt {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            ..receipt
+        };
+        assert!(mislabeled.verify_event(&mut state).is_err());
+    }
+
+    #[test]
+    fn test_persisted_log_verifies() {
+        let (sid, _vk, sk) = create_signer();
+        let (inception, events) = create_log(&sk, &sid, 2);
+        let mut store = MemoryBlockStore::new();
+        let inception_cid = inception.persist(&mut store).unwrap();
+        let cids: Vec<Cid> = events.iter().map(|e| e.persist(&mut store).unwrap()).collect();
+        assert!(store.gc().unwrap().is_empty());
+
+        let loaded = IdEventReceipt::load(&store, &inception_cid).unwrap();
+        let loaded_events: Vec<IdEventReceipt> = cids
+            .iter()
+            .map(|cid| IdEventReceipt::load(&store, cid).unwrap())
+            .collect();
+        assert_eq!(loaded, inception);
+        assert_eq!(loaded.verify_log(&loaded_events).unwrap().sn, 2);
+
+        store.unpin(&cids[1]).unwrap();
+        assert_eq!(store.gc().unwrap(), vec![cids[1]]);
+        assert!(IdEventReceipt::load(&store, &cids[1]).is_err());
+    }
+
+    #[test]
+    fn test_verify_log_matches_stepwise_replay() {
+        let (sid, _vk, sk) = create_signer();
+        let (inception, events) = create_log(&sk, &sid, 3);
+        let mut state = inception.verify_inception().unwrap();
+        for event in &events {
+            state = event.verify_event(&mut state).unwrap();
+        }
+        assert_eq!(inception.verify_log(&events).unwrap(), state);
+    }
+
+    #[test]
+    fn test_verify_log_names_bad_signature() {
+        let (sid, _vk, sk) = create_signer();
+        let (inception, mut events) = create_log(&sk, &sid, 3);
+        events[1].proofs[0].signature[0] ^= 0xFF;
+        let err = inception.verify_log(&events).unwrap_err();
+        match err {
+            IdEventError::InvalidProof { kid, .. } => assert_eq!(kid, sid),
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_interaction_with_blake3_ids() {
+        let sk = SigningKey::generate(&mut OsRng);
+        let sid = Cid::create_with(ED_CODE, idp2p_common::BLAKE3_CODE, sk.verifying_key().as_bytes())
+            .unwrap()
+            .to_string();
+        let mut state = base_state_with_signer(&sid, sk.verifying_key().as_bytes());
+        let event = IdEvent {
+            sn: 1,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: valid_timestamp(),
+            previous: state.event_id.clone(),
+            body: Interaction {
+                merkle_proof: "blake3-proof".into(),
+            },
+        };
+        let payload = common_cbor::encode(&event).unwrap();
+        let receipt = IdEventReceipt {
+            id: Cid::create_with(CBOR_CODE, idp2p_common::BLAKE3_CODE, &payload)
+                .unwrap()
+                .to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![sign_receipt(&payload, &state.id, &sid, &sk)],
+        };
+        let updated = receipt.verify_event(&mut state).expect("blake3 ids should pass");
+        assert_eq!(updated.event_id, receipt.id);
+    }
+
+    #[test]
+    fn test_non_canonical_payload_rejected() {
+        let (sid, vk, sk) = create_signer();
+        let mut state = base_state_with_signer(&sid, vk.as_bytes());
+        let event = IdEvent {
+            sn: 1,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: valid_timestamp(),
+            previous: state.event_id.clone(),
+            body: Interaction {
+                merkle_proof: "proof".into(),
+            },
+        };
+        // Field order encoding, same event but not the deterministic bytes
+        let mut payload = Vec::new();
+        ciborium::ser::into_writer(&event, &mut payload).unwrap();
+        assert_ne!(payload, common_cbor::encode(&event).unwrap());
+        let receipt = IdEventReceipt {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![sign_receipt(&payload, &state.id, &sid, &sk)],
+        };
+        let err = receipt.verify_event(&mut state).unwrap_err();
+        assert!(matches!(
+            err,
+            IdEventError::CommonError(CommonError::NonCanonicalEncoding)
+        ));
+    }
+
+    fn cose_receipt(
+        state: &IdState,
+        sid: &str,
+        sk: &SigningKey,
+        header_purpose: IdProofPurpose,
+    ) -> IdEventReceipt {
+        let event = IdEvent {
+            sn: 1,
+            version: VERSION.into(),
+            patch: Cid::default(),
+            timestamp: valid_timestamp(),
+            previous: state.event_id.clone(),
+            body: Interaction {
+                merkle_proof: "cose-proof".into(),
+            },
+        };
+        let payload = common_cbor::encode(&event).unwrap();
+        let created = Utc::now();
+        let envelope = IdProof::cose_sign1(
+            &state.id,
+            sid,
+            &created,
+            header_purpose,
+            coset::iana::Algorithm::EdDSA,
+            &payload,
+            |data| sk.sign(data).to_vec(),
+        )
+        .unwrap();
+        IdEventReceipt {
+            id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+            version: VERSION.into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload: payload.clone(),
+            proofs: vec![IdProof {
+                id: Cid::create(CBOR_CODE, &payload).unwrap().to_string(),
+                did: state.id.clone(),
+                key_id: sid.into(),
+                created: created.to_rfc3339(),
+                purpose: "event-signing".into(),
+                signature: envelope,
+                previous: None,
+                format: Some("cose-sign1".into()),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_interaction_with_cose_sign1_proof() {
+        use coset::{CoseSign1, TaggedCborSerializable};
+
+        let (sid, vk, sk) = create_signer();
+        let mut state = base_state_with_signer(&sid, vk.as_bytes());
+        let receipt = cose_receipt(&state, &sid, &sk, IdProofPurpose::EventSigning);
+
+        // A plain COSE library verifies the envelope against the payload
+        let sign1 = CoseSign1::from_tagged_slice(&receipt.proofs[0].signature).unwrap();
+        assert_eq!(sign1.protected.header.key_id, sid.as_bytes());
+        sign1
+            .verify_detached_signature(&receipt.payload, b"", |sig, data| {
+                idp2p_common::verification::ed25519::verify(vk.as_bytes(), data, sig)
+            })
+            .expect("cose verification");
+
+        let updated = receipt
+            .verify_event(&mut state)
+            .expect("cose interaction should pass");
+        assert_eq!(updated.merkle_proof, "cose-proof");
+    }
+
+    #[test]
+    fn test_cose_sign1_header_mismatch_rejected() {
+        let (sid, vk, sk) = create_signer();
+        let mut state = base_state_with_signer(&sid, vk.as_bytes());
+        let receipt = cose_receipt(&state, &sid, &sk, IdProofPurpose::Authentication);
+        let err = receipt.verify_event(&mut state).unwrap_err();
+        match err {
+            IdEventError::InvalidProof { reason, .. } => assert_eq!(reason, "header mismatch"),
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_unknown_proof_format_rejected() {
+        let (sid, vk, sk) = create_signer();
+        let mut state = base_state_with_signer(&sid, vk.as_bytes());
+        let mut receipt = cose_receipt(&state, &sid, &sk, IdProofPurpose::EventSigning);
+        receipt.proofs[0].format = Some("jws".into());
+        let err = receipt.verify_event(&mut state).unwrap_err();
+        assert!(matches!(err, IdEventError::InvalidProof { .. }));
+    }
 }
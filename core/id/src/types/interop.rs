@@ -0,0 +1,500 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use cid::{
+    Cid,
+    multibase::{self, Base},
+};
+use idp2p_common::{
+    CBOR_CODE, ED_CODE, P256_CODE, SECP256K1_CODE, X25519_CODE, cid::CidExt,
+    crypto::agreement, error::CommonError, multikey::{self, DID_KEY_PREFIX}, utils::sha256_hash,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    internal::{
+        error::IdEventError,
+        proof::{IdProofFormat, IdProofPurpose},
+        signer::IdSigner as InternalSigner,
+    },
+    types::{IdProof, IdSigner, IdState},
+};
+
+pub const DID_PREFIX: &str = "did:idp2p:";
+pub const DATA_INTEGRITY_PROOF: &str = "DataIntegrityProof";
+pub const EDDSA_JCS_2022: &str = "eddsa-jcs-2022";
+
+/// DID URL of a signer key.
+pub fn verification_method(id: &str, key_id: &str) -> String {
+    format!("{DID_PREFIX}{id}#{key_id}")
+}
+
+/// Splits a verification method into identity id and key id.
+pub fn parse_verification_method(method: &str) -> Result<(&str, &str), IdEventError> {
+    method
+        .strip_prefix(DID_PREFIX)
+        .and_then(|m| m.split_once('#'))
+        .ok_or_else(|| IdEventError::InvalidSigner(method.into()))
+}
+
+/// JOSE algorithm of a signer key, if it has one.
+pub fn jose_algorithm(code: u64) -> Option<&'static str> {
+    match code {
+        ED_CODE => Some("EdDSA"),
+        P256_CODE => Some("ES256"),
+        SECP256K1_CODE => Some("ES256K"),
+        _ => None,
+    }
+}
+
+/// DID document of a did:key.
+///
+/// The key is a `Multikey` verification method used for every signing
+/// relationship, as the did:key method defines. An Ed25519 key also gets
+/// its X25519 form as the `keyAgreement` method.
+pub fn resolve_did_key(did: &str) -> Result<Value, IdEventError> {
+    let did = did.split_once('#').map_or(did, |(did, _)| did);
+    let (code, public_key) = multikey::from_did_key(did)?;
+    let multikey_method = |code: u64, public_key: &[u8]| {
+        let key = multikey::encode(code, public_key);
+        let id = format!("{did}#{key}");
+        let method = json!({
+            "id": id,
+            "type": "Multikey",
+            "controller": did,
+            "publicKeyMultibase": key,
+        });
+        (id, method)
+    };
+    let (method, verification_method) = multikey_method(code, &public_key);
+    let mut document = json!({
+        "@context": ["https://www.w3.org/ns/did/v1", "https://w3id.org/security/multikey/v1"],
+        "id": did,
+        "verificationMethod": [verification_method],
+        "authentication": [method],
+        "assertionMethod": [method],
+        "capabilityDelegation": [method],
+        "capabilityInvocation": [method],
+    });
+    if code == ED_CODE {
+        let x25519 = agreement::ed25519_public_to_x25519(&public_key)?;
+        let (method, agreement_method) = multikey_method(X25519_CODE, &x25519);
+        document["verificationMethod"] = json!([verification_method, agreement_method]);
+        document["keyAgreement"] = json!([method]);
+    }
+    Ok(document)
+}
+
+impl IdSigner {
+    /// did:key form of the signer.
+    pub fn did_key(&self) -> Result<String, IdEventError> {
+        InternalSigner::try_from(self)?.did_key()
+    }
+
+    /// Multikey of the signer public key.
+    pub fn multikey(&self) -> Result<String, IdEventError> {
+        let did = self.did_key()?;
+        Ok(did.trim_start_matches(DID_KEY_PREFIX).into())
+    }
+}
+
+fn jcs<T: Serialize>(value: &T) -> Result<Vec<u8>, IdEventError> {
+    Ok(serde_jcs::to_vec(value).map_err(|_| CommonError::EncodeError)?)
+}
+
+fn b64_decode(input: &str) -> Result<Vec<u8>, IdEventError> {
+    URL_SAFE_NO_PAD
+        .decode(input)
+        .map_err(|e| CommonError::DecodeError(e.to_string()).into())
+}
+
+/// A W3C Data Integrity proof.
+///
+/// Only the `eddsa-jcs-2022` cryptosuite is supported, RDF canonicalization
+/// (`eddsa-rdfc-2022`) is not.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataIntegrityProof {
+    #[serde(rename = "@context", skip_serializing_if = "Option::is_none", default)]
+    pub context: Option<Value>,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub cryptosuite: String,
+    pub created: String,
+    pub verification_method: String,
+    pub proof_purpose: String,
+    pub proof_value: String,
+}
+
+impl DataIntegrityProof {
+    /// Bytes signed for a document, `SHA-256(JCS(proof config)) || SHA-256(JCS(document))`.
+    ///
+    /// The proof config is the proof without `proofValue`, taking the
+    /// `@context` of the document when it has one.
+    pub fn signing_input(&self, document: &Value) -> Result<Vec<u8>, IdEventError> {
+        let mut document = document.clone();
+        if let Some(map) = document.as_object_mut() {
+            map.remove("proof");
+        }
+        let mut config = serde_json::to_value(self).map_err(|_| CommonError::EncodeError)?;
+        let map = config.as_object_mut().ok_or(CommonError::EncodeError)?;
+        map.remove("proofValue");
+        if let Some(context) = document.get("@context") {
+            map.insert("@context".into(), context.clone());
+        }
+        let mut input = sha256_hash(&jcs(&config)?).to_vec();
+        input.extend(sha256_hash(&jcs(&document)?));
+        Ok(input)
+    }
+
+    /// Verifies the proof on a document made by a current signer of `state`.
+    pub fn verify(&self, document: &Value, state: &IdState) -> Result<(), IdEventError> {
+        if let (Some(context), Some(doc_context)) = (&self.context, document.get("@context"))
+            && context != doc_context
+        {
+            return Err(IdEventError::invalid_proof(
+                &self.verification_method,
+                "context mismatch",
+            ));
+        }
+        let proof = IdProof::try_from(self)?;
+        state.verify_data_integrity(&proof, &self.signing_input(document)?)
+    }
+}
+
+impl TryFrom<&DataIntegrityProof> for IdProof {
+    type Error = IdEventError;
+
+    /// The proof id is the CID of the Data Integrity proof.
+    fn try_from(value: &DataIntegrityProof) -> Result<Self, Self::Error> {
+        let invalid = |reason: &str| IdEventError::invalid_proof(&value.verification_method, reason);
+        if value.kind != DATA_INTEGRITY_PROOF || value.cryptosuite != EDDSA_JCS_2022 {
+            return Err(invalid("unsupported cryptosuite"));
+        }
+        let purpose =
+            IdProofPurpose::from_w3c(&value.proof_purpose).ok_or_else(|| invalid("unknown purpose"))?;
+        let (did, key_id) = parse_verification_method(&value.verification_method)?;
+        let (base, signature) =
+            multibase::decode(&value.proof_value).map_err(|_| invalid("invalid proof value"))?;
+        if base != Base::Base58Btc {
+            return Err(invalid("invalid proof value"));
+        }
+        Ok(IdProof {
+            id: Cid::create(CBOR_CODE, &jcs(value)?)?.to_string(),
+            did: did.into(),
+            key_id: key_id.into(),
+            created: value.created.clone(),
+            purpose: purpose.as_ref().into(),
+            signature,
+            previous: None,
+            format: Some(IdProofFormat::EddsaJcs2022.as_ref().into()),
+        })
+    }
+}
+
+impl IdProof {
+    /// Data Integrity form of an `eddsa-jcs-2022` proof.
+    pub fn to_data_integrity(&self) -> Result<DataIntegrityProof, IdEventError> {
+        let invalid = |reason: &str| IdEventError::invalid_proof(&self.key_id, reason);
+        if self.format()? != IdProofFormat::EddsaJcs2022 {
+            return Err(invalid("unexpected format"));
+        }
+        let purpose = self.purpose()?.w3c().ok_or_else(|| invalid("unexpected purpose"))?;
+        Ok(DataIntegrityProof {
+            context: None,
+            kind: DATA_INTEGRITY_PROOF.into(),
+            cryptosuite: EDDSA_JCS_2022.into(),
+            created: self.created.clone(),
+            verification_method: verification_method(&self.did, &self.key_id),
+            proof_purpose: purpose.into(),
+            proof_value: multibase::encode(Base::Base58Btc, &self.signature),
+        })
+    }
+
+    /// Protected header of a JWS proof.
+    fn jws_header(
+        did: &str,
+        key_id: &str,
+        created: &DateTime<Utc>,
+        purpose: IdProofPurpose,
+        alg: &str,
+    ) -> Value {
+        json!({
+            "alg": alg,
+            "kid": verification_method(did, key_id),
+            "purpose": purpose.as_ref(),
+            "created": created.timestamp(),
+        })
+    }
+
+    /// Builds a detached compact JWS (`header..signature`) over a payload.
+    ///
+    /// `sign` gets the JWS signing input and returns the signature, ECDSA
+    /// signatures in fixed `r || s` form.
+    pub fn jws_sign<F: FnOnce(&[u8]) -> Vec<u8>>(
+        did: &str,
+        key_id: &str,
+        created: &DateTime<Utc>,
+        purpose: IdProofPurpose,
+        alg: &str,
+        payload: &[u8],
+        sign: F,
+    ) -> Result<String, IdEventError> {
+        let header = URL_SAFE_NO_PAD.encode(jcs(&Self::jws_header(did, key_id, created, purpose, alg))?);
+        let input = format!("{header}.{}", URL_SAFE_NO_PAD.encode(payload));
+        let signature = sign(input.as_bytes());
+        Ok(format!("{header}..{}", URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    /// Proof of a detached JWS, the header must carry `kid`, `purpose` and `created`.
+    ///
+    /// The proof id is the CID of the JWS.
+    pub fn from_jws(jws: &str) -> Result<Self, IdEventError> {
+        let invalid = |reason: &str| IdEventError::invalid_proof(jws, reason);
+        let (header, _) = jws.split_once('.').ok_or_else(|| invalid("invalid jws"))?;
+        let header: Value = serde_json::from_slice(&b64_decode(header)?)
+            .map_err(|e| CommonError::DecodeError(e.to_string()))?;
+        let field = |name: &str| header.get(name).ok_or_else(|| invalid("missing header"));
+        let kid = field("kid")?.as_str().ok_or_else(|| invalid("invalid kid"))?;
+        let (did, key_id) = parse_verification_method(kid)?;
+        let purpose = field("purpose")?.as_str().ok_or_else(|| invalid("invalid purpose"))?;
+        let created = field("created")?
+            .as_i64()
+            .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0))
+            .ok_or_else(|| invalid("invalid created"))?;
+        Ok(IdProof {
+            id: Cid::create(CBOR_CODE, jws.as_bytes())?.to_string(),
+            did: did.into(),
+            key_id: key_id.into(),
+            created: created.to_rfc3339(),
+            purpose: purpose.into(),
+            signature: jws.as_bytes().to_vec(),
+            previous: None,
+            format: Some(IdProofFormat::Jws.as_ref().into()),
+        })
+    }
+
+    /// Detached JWS of a `jws` proof.
+    pub fn to_jws(&self) -> Result<String, IdEventError> {
+        if self.format()? != IdProofFormat::Jws {
+            return Err(IdEventError::invalid_proof(&self.key_id, "unexpected format"));
+        }
+        String::from_utf8(self.signature.clone())
+            .map_err(|_| IdEventError::invalid_proof(&self.key_id, "invalid jws"))
+    }
+
+    /// Signed bytes and signature of a JWS proof.
+    ///
+    /// The protected header must agree with the proof fields.
+    pub(crate) fn jws_signed_data(
+        &self,
+        created: &DateTime<Utc>,
+        purpose: IdProofPurpose,
+        code: u64,
+        payload: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>), IdEventError> {
+        let invalid = |reason: &str| IdEventError::invalid_proof(&self.key_id, reason);
+        let jws = self.to_jws()?;
+        let mut parts = jws.split('.');
+        let (Some(header_b64), Some(""), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid("invalid jws"));
+        };
+        let header: Value = serde_json::from_slice(&b64_decode(header_b64)?)
+            .map_err(|_| invalid("invalid jws"))?;
+        let alg = jose_algorithm(code).ok_or_else(|| invalid("unsupported algorithm"))?;
+        let expected = Self::jws_header(&self.did, &self.key_id, created, purpose, alg);
+        let matches = header.get("crit").is_none()
+            && ["alg", "kid", "purpose", "created"]
+                .iter()
+                .all(|name| header.get(name) == expected.get(name));
+        if !matches {
+            return Err(invalid("header mismatch"));
+        }
+        let input = format!("{header_b64}.{}", URL_SAFE_NO_PAD.encode(payload));
+        Ok((input.into_bytes(), b64_decode(signature)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::IdSigner;
+    use ed25519_dalek::{Signer as _, SigningKey};
+
+    fn create_state(sk: &SigningKey, purposes: &[&str]) -> (IdState, String) {
+        let sid = Cid::create(ED_CODE, sk.verifying_key().as_bytes())
+            .unwrap()
+            .to_string();
+        let state = IdState {
+            id: Cid::create(CBOR_CODE, b"idp2p-test").unwrap().to_string(),
+            sn: 0,
+            event_id: Cid::create(CBOR_CODE, b"event").unwrap().to_string(),
+            event_timestamp: "2025-01-01T00:00:01Z".into(),
+            prior_id: None,
+            next_id_proof: None,
+            threshold: 1,
+            next_threshold: 1,
+            signers: vec![IdSigner {
+                id: sid.clone(),
+                public_key: sk.verifying_key().as_bytes().to_vec(),
+                purposes: purposes.iter().map(|p| p.to_string()).collect(),
+                valid_from_sn: 0,
+                valid_until_sn: None,
+                valid_from: "2025-01-01T00:00:01Z".into(),
+                valid_until: None,
+            }],
+            next_signers: vec![],
+            delegated_signers: vec![],
+            merkle_proof: "merkle-proof".into(),
+            revoked: false,
+            revoked_at: None,
+        };
+        (state, sid)
+    }
+
+    fn document() -> Value {
+        json!({
+            "@context": ["https://www.w3.org/ns/credentials/v2"],
+            "type": ["VerifiableCredential"],
+            "issuer": "did:idp2p:issuer",
+            "credentialSubject": { "name": "Alice" }
+        })
+    }
+
+    fn data_integrity(state: &IdState, sid: &str, sk: &SigningKey) -> DataIntegrityProof {
+        let mut proof = DataIntegrityProof {
+            context: None,
+            kind: DATA_INTEGRITY_PROOF.into(),
+            cryptosuite: EDDSA_JCS_2022.into(),
+            created: "2025-01-02T00:00:00Z".into(),
+            verification_method: verification_method(&state.id, sid),
+            proof_purpose: "assertionMethod".into(),
+            proof_value: String::new(),
+        };
+        let data = proof.signing_input(&document()).unwrap();
+        proof.proof_value = multibase::encode(Base::Base58Btc, sk.sign(&data).to_bytes());
+        proof
+    }
+
+    #[test]
+    fn test_data_integrity_roundtrip() {
+        let sk = SigningKey::from_bytes(&[3u8; 32]);
+        let (state, sid) = create_state(&sk, &["assertion"]);
+        let proof = data_integrity(&state, &sid, &sk);
+        proof.verify(&document(), &state).expect("proof should verify");
+
+        let id_proof = IdProof::try_from(&proof).unwrap();
+        assert_eq!(id_proof.key_id, sid);
+        assert_eq!(id_proof.purpose, "assertion");
+        assert_eq!(id_proof.to_data_integrity().unwrap(), proof);
+
+        let mut tampered = document();
+        tampered["credentialSubject"]["name"] = json!("Mallory");
+        assert!(proof.verify(&tampered, &state).is_err());
+    }
+
+    #[test]
+    fn test_data_integrity_not_verified_as_raw_proof() {
+        let sk = SigningKey::from_bytes(&[3u8; 32]);
+        let (state, sid) = create_state(&sk, &["assertion", "authentication"]);
+        let proof = data_integrity(&state, &sid, &sk);
+        let hash_data = proof.signing_input(&document()).unwrap();
+
+        // The signature doesn't cover the purpose, relabelling must not pass
+        let mut id_proof = IdProof::try_from(&proof).unwrap();
+        id_proof.purpose = "authentication".into();
+        let err = state.verify_proof(&id_proof, &hash_data).unwrap_err();
+        assert!(matches!(err, IdEventError::InvalidProof { .. }));
+        let id_proof = IdProof::try_from(&proof).unwrap();
+        assert!(state.verify_proof(&id_proof, &hash_data).is_err());
+    }
+
+    #[test]
+    fn test_data_integrity_needs_key_purpose() {
+        let sk = SigningKey::from_bytes(&[3u8; 32]);
+        let (state, sid) = create_state(&sk, &["authentication"]);
+        let proof = data_integrity(&state, &sid, &sk);
+        let err = proof.verify(&document(), &state).unwrap_err();
+        assert!(matches!(err, IdEventError::InvalidProof { .. }));
+    }
+
+    #[test]
+    fn test_data_integrity_other_identity_rejected() {
+        let sk = SigningKey::from_bytes(&[3u8; 32]);
+        let (state, sid) = create_state(&sk, &["assertion"]);
+        let mut proof = data_integrity(&state, &sid, &sk);
+        proof.verification_method = verification_method("other", &sid);
+        let err = proof.verify(&document(), &state).unwrap_err();
+        assert!(matches!(err, IdEventError::IdNotMatch(_)));
+    }
+
+    #[test]
+    fn test_jws_roundtrip() {
+        let sk = SigningKey::from_bytes(&[5u8; 32]);
+        let (state, sid) = create_state(&sk, &["authentication"]);
+        let created = DateTime::<Utc>::from_timestamp(1_750_000_000, 0).unwrap();
+        let jws = IdProof::jws_sign(
+            &state.id,
+            &sid,
+            &created,
+            IdProofPurpose::Authentication,
+            "EdDSA",
+            b"challenge",
+            |data| sk.sign(data).to_vec(),
+        )
+        .unwrap();
+
+        // Standard detached JWS verification
+        let (header, signature) = jws.split_once("..").unwrap();
+        let input = format!("{header}.{}", URL_SAFE_NO_PAD.encode(b"challenge"));
+        idp2p_common::verification::ed25519::verify(
+            sk.verifying_key().as_bytes(),
+            input.as_bytes(),
+            &URL_SAFE_NO_PAD.decode(signature).unwrap(),
+        )
+        .expect("jws should verify");
+
+        let proof = IdProof::from_jws(&jws).unwrap();
+        assert_eq!(proof.to_jws().unwrap(), jws);
+        state.verify_proof(&proof, b"challenge").expect("proof should verify");
+        assert!(state.verify_proof(&proof, b"other").is_err());
+    }
+
+    #[test]
+    fn test_signer_did_key_roundtrip() {
+        let sk = SigningKey::from_bytes(&[9u8; 32]);
+        let (state, sid) = create_state(&sk, &["authentication"]);
+        let did = state.signers[0].did_key().unwrap();
+        assert!(did.starts_with("did:key:z6Mk"));
+
+        let signer = InternalSigner::from_did_key(&did, Default::default()).unwrap();
+        assert_eq!(signer.id, sid);
+        assert_eq!(signer.public_key, sk.verifying_key().as_bytes());
+
+        let document = resolve_did_key(&did).unwrap();
+        assert_eq!(document["id"], json!(did));
+        assert_eq!(
+            document["verificationMethod"][0]["publicKeyMultibase"],
+            json!(state.signers[0].multikey().unwrap())
+        );
+
+        // The X25519 form of the key is the key agreement method
+        let x25519 = agreement::ed25519_public_to_x25519(sk.verifying_key().as_bytes()).unwrap();
+        let key = multikey::encode(X25519_CODE, &x25519);
+        assert!(key.starts_with("z6LS"));
+        assert_eq!(document["verificationMethod"][1]["publicKeyMultibase"], json!(key));
+        assert_eq!(document["keyAgreement"], json!([format!("{did}#{key}")]));
+    }
+
+    #[test]
+    fn test_signer_did_key_rejects_wrong_key() {
+        let sk = SigningKey::from_bytes(&[9u8; 32]);
+        let (mut state, _) = create_state(&sk, &["authentication"]);
+        state.signers[0].public_key = vec![1u8; 32];
+        assert!(state.signers[0].did_key().is_err());
+    }
+}
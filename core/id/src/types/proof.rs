@@ -3,10 +3,24 @@ use chrono::{DateTime, Utc};
 use ciborium::cbor;
 use cid::Cid;
 use core::str::FromStr;
-use idp2p_common::{ED_CODE, cid::CidExt, error::CommonError, verification::ed25519};
+use coset::{
+    CborSerializable, CoseSign1, CoseSign1Builder, Header, HeaderBuilder, Label,
+    TaggedCborSerializable, cbor::value::Value, iana,
+};
+use idp2p_common::{
+    CBOR_CODE, ED_CODE, P256_CODE, SECP256K1_CODE,
+    cid::CidExt,
+    crypto::signer::Signer,
+    error::CommonError,
+    verification::{self, SIGNER_CODES, ed25519},
+};
 use serde::{Deserialize, Serialize};
 
-use crate::internal::{error::IdEventError, signer::IdSigner};
+use crate::internal::{
+    error::IdEventError,
+    proof::{IdProofFormat, IdProofPurpose},
+    signer::IdSigner,
+};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct IdProof {
@@ -17,10 +31,269 @@ pub struct IdProof {
     pub purpose: String,
     pub signature: Vec<u8>,
     pub previous: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub format: Option<String>,
+}
+
+/// A proof signature whose structural checks passed, waiting to be verified.
+#[derive(Debug, Clone)]
+pub(crate) struct PendingSignature {
+    pub key_id: String,
+    pub code: u64,
+    pub public_key: Vec<u8>,
+    pub data: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// Verifies pending signatures, batching the Ed25519 ones.
+pub(crate) fn verify_signatures(pending: &[PendingSignature]) -> Result<(), IdEventError> {
+    let (batch, single): (Vec<&PendingSignature>, Vec<&PendingSignature>) =
+        pending.iter().partition(|p| p.code == ED_CODE);
+    for p in single {
+        verification::verify(p.code, &p.public_key, &p.data, &p.signature)
+            .map_err(|_| IdEventError::invalid_proof(&p.key_id, "invalid signature"))?;
+    }
+    let items: Vec<(&[u8], &[u8], &[u8])> = batch
+        .iter()
+        .map(|p| (p.public_key.as_slice(), p.data.as_slice(), p.signature.as_slice()))
+        .collect();
+    ed25519::verify_batch(&items).map_err(|e| {
+        let key_id = match e {
+            CommonError::BatchVerifyError(i) => batch[i].key_id.as_str(),
+            _ => "",
+        };
+        IdEventError::invalid_proof(key_id, "invalid signature")
+    })
+}
+
+/// Verifies every proof over the payload, rejecting duplicate key ids.
+///
+/// Proofs must be made for `purpose` and are checked against `signers`,
+/// except delegation proofs which are made by other identities and
+/// verified by the host. Signatures are pushed to `pending` so callers can
+/// verify them in one batch.
+///
+/// Returns how many distinct `signers` made a proof, the only count
+/// thresholds may use since delegation proofs aren't made by a signer.
+pub(crate) fn verify_proofs(
+    proofs: &[IdProof],
+    payload: &[u8],
+    signers: &BTreeSet<IdSigner>,
+    purpose: IdProofPurpose,
+    pending: &mut Vec<PendingSignature>,
+) -> Result<usize, IdEventError> {
+    let mut signed = 0;
+    let mut seen: BTreeSet<String> = BTreeSet::new();
+    for proof in proofs.iter() {
+        if !seen.insert(proof.key_id.clone()) {
+            return Err(IdEventError::invalid_proof(
+                &proof.key_id,
+                "duplicate proof",
+            ));
+        }
+        // Data Integrity proofs sign documents, not receipts
+        if proof.format()? == IdProofFormat::EddsaJcs2022 {
+            return Err(IdEventError::invalid_proof(&proof.key_id, "unexpected format"));
+        }
+        match proof.purpose()? {
+            IdProofPurpose::Delegation => {
+                crate::host::verify_proof(proof, payload)
+                    .map_err(|e| IdEventError::invalid_proof(&proof.key_id, &e.code))?;
+            }
+            p if p == purpose => {
+                pending.push(proof.check(payload, signers)?);
+                signed += 1;
+            }
+            _ => {
+                return Err(IdEventError::invalid_proof(
+                    &proof.key_id,
+                    "unexpected purpose",
+                ));
+            }
+        }
+    }
+    Ok(signed)
+}
+
+/// COSE algorithm of a signer key, if it has one.
+///
+/// ML-DSA and hybrid keys have no algorithm in the COSE registry we build
+/// on yet and secp256k1 Schnorr keys (`SECP256K1_SCHNORR_CODE`) have none
+/// at all.
+pub fn cose_algorithm(code: u64) -> Option<iana::Algorithm> {
+    match code {
+        ED_CODE => Some(iana::Algorithm::EdDSA),
+        P256_CODE => Some(iana::Algorithm::ES256),
+        SECP256K1_CODE => Some(iana::Algorithm::ES256K),
+        _ => None,
+    }
+}
+
+fn header_value<'a>(header: &'a Header, label: &str) -> Option<&'a Value> {
+    header
+        .rest
+        .iter()
+        .find(|(l, _)| *l == Label::Text(label.into()))
+        .map(|(_, v)| v)
 }
 
 impl IdProof {
+    pub fn format(&self) -> Result<IdProofFormat, IdEventError> {
+        match &self.format {
+            None => Ok(IdProofFormat::Idp2p),
+            Some(format) => IdProofFormat::from_str(format)
+                .map_err(|_| IdEventError::invalid_proof(&self.key_id, "unknown format")),
+        }
+    }
+
+    pub fn purpose(&self) -> Result<IdProofPurpose, IdEventError> {
+        IdProofPurpose::from_str(&self.purpose)
+            .map_err(|_| IdEventError::invalid_proof(&self.key_id, "unknown purpose"))
+    }
+
+    /// Bytes a signer signs for a proof.
+    ///
+    /// The purpose domain prefixes the CBOR encoded proof data.
+    pub fn signing_input(
+        did: &str,
+        key_id: &str,
+        created: &DateTime<Utc>,
+        purpose: IdProofPurpose,
+        payload: &[u8],
+    ) -> Result<Vec<u8>, IdEventError> {
+        let data = cbor!({
+            "did" => did,
+            "key_id" => key_id,
+            "created" => created.timestamp(),
+            "purpose" => purpose.as_ref(),
+            "payload" => payload,
+        })
+        .map_err(|_| CommonError::EncodeError)?;
+        let mut input = purpose.domain().to_vec();
+        input.extend(idp2p_common::cbor::encode(&data)?);
+        Ok(input)
+    }
+
+    /// Raw proof of a signer over a payload, e.g. a keystore signer.
+    pub fn create(
+        did: &str,
+        signer: &Signer,
+        created: &DateTime<Utc>,
+        purpose: IdProofPurpose,
+        payload: &[u8],
+    ) -> Result<Self, IdEventError> {
+        let input = Self::signing_input(did, &signer.id, created, purpose, payload)?;
+        Ok(Self {
+            id: Cid::create(CBOR_CODE, payload)?.to_string(),
+            did: did.into(),
+            key_id: signer.id.clone(),
+            created: created.to_rfc3339(),
+            purpose: purpose.as_ref().into(),
+            signature: signer.sign(&input)?,
+            previous: None,
+            format: None,
+        })
+    }
+
+    /// Protected header of a COSE_Sign1 proof.
+    ///
+    /// `kid` is the signer CID, `did`, `purpose` and `created` (seconds)
+    /// use text labels.
+    pub fn cose_protected(
+        did: &str,
+        key_id: &str,
+        created: &DateTime<Utc>,
+        purpose: IdProofPurpose,
+        alg: iana::Algorithm,
+    ) -> Header {
+        HeaderBuilder::new()
+            .algorithm(alg)
+            .key_id(key_id.as_bytes().to_vec())
+            .text_value("did".into(), Value::Text(did.into()))
+            .text_value("purpose".into(), Value::Text(purpose.as_ref().into()))
+            .text_value("created".into(), Value::Integer(created.timestamp().into()))
+            .build()
+    }
+
+    /// Builds a tagged COSE_Sign1 envelope over a detached payload.
+    ///
+    /// `sign` gets the COSE `Sig_structure` bytes and returns the signature.
+    pub fn cose_sign1<F: FnOnce(&[u8]) -> Vec<u8>>(
+        did: &str,
+        key_id: &str,
+        created: &DateTime<Utc>,
+        purpose: IdProofPurpose,
+        alg: iana::Algorithm,
+        payload: &[u8],
+        sign: F,
+    ) -> Result<Vec<u8>, IdEventError> {
+        CoseSign1Builder::new()
+            .protected(Self::cose_protected(did, key_id, created, purpose, alg))
+            .create_detached_signature(payload, b"", sign)
+            .build()
+            .to_tagged_vec()
+            .map_err(|_| IdEventError::from(CommonError::EncodeError))
+    }
+
+    /// Signed bytes and signature of a COSE_Sign1 proof.
+    ///
+    /// The protected header must agree with the proof fields.
+    fn cose_signed_data(
+        &self,
+        created: &DateTime<Utc>,
+        purpose: IdProofPurpose,
+        code: u64,
+        payload: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>), IdEventError> {
+        let invalid = |reason: &str| IdEventError::invalid_proof(&self.key_id, reason);
+        let sign1 = CoseSign1::from_tagged_slice(&self.signature)
+            .or_else(|_| CoseSign1::from_slice(&self.signature))
+            .map_err(|_| invalid("invalid envelope"))?;
+        if sign1.payload.is_some() {
+            return Err(invalid("attached payload"));
+        }
+        let alg = cose_algorithm(code).ok_or_else(|| invalid("unsupported algorithm"))?;
+        let header = &sign1.protected.header;
+        let matches = header.alg == Some(coset::Algorithm::Assigned(alg))
+            && header.crit.is_empty()
+            && header.key_id == self.key_id.as_bytes()
+            && header_value(header, "did") == Some(&Value::Text(self.did.clone()))
+            && header_value(header, "purpose") == Some(&Value::Text(purpose.as_ref().into()))
+            && header_value(header, "created") == Some(&Value::Integer(created.timestamp().into()));
+        if !matches {
+            return Err(invalid("header mismatch"));
+        }
+        Ok((sign1.tbs_detached_data(payload, b""), sign1.signature))
+    }
+
     pub fn verify(&self, payload: &[u8], signers: &BTreeSet<IdSigner>) -> Result<(), IdEventError> {
+        let pending = self.check(payload, signers)?;
+        verify_signatures(&[pending])
+    }
+
+    /// Checks everything but the signature itself.
+    ///
+    /// `eddsa-jcs-2022` proofs don't sign the proof fields, so they are
+    /// rejected here and only checked by `DataIntegrityProof::verify`.
+    pub(crate) fn check(
+        &self,
+        payload: &[u8],
+        signers: &BTreeSet<IdSigner>,
+    ) -> Result<PendingSignature, IdEventError> {
+        if self.format()? == IdProofFormat::EddsaJcs2022 {
+            return Err(IdEventError::invalid_proof(&self.key_id, "unexpected format"));
+        }
+        self.check_signed(payload, signers)
+    }
+
+    /// `check` for any format, an `eddsa-jcs-2022` payload must be the Data
+    /// Integrity hash data that binds the proof config.
+    pub(crate) fn check_signed(
+        &self,
+        payload: &[u8],
+        signers: &BTreeSet<IdSigner>,
+    ) -> Result<PendingSignature, IdEventError> {
+        let purpose = self.purpose()?;
         // Validate created is RFC3339
         let _created: DateTime<Utc> = self
             .created
@@ -33,35 +306,39 @@ impl IdProof {
             .find(|s| s.id == self.key_id)
             .ok_or_else(|| IdEventError::InvalidSigner(self.key_id.clone()))?;
 
+        // The key must be authorised for what it signs
+        if !signer.purposes.contains(&purpose.key_purpose()) {
+            return Err(IdEventError::invalid_proof(&self.key_id, "purpose not allowed"));
+        }
+
         // Ensure verification method CID matches the signer public key and codec
-        if let Err(_e) = kid.ensure(&signer.public_key, vec![ED_CODE]) {
+        if let Err(_e) = kid.ensure(&signer.public_key, SIGNER_CODES.to_vec()) {
             return Err(IdEventError::invalid_proof(&self.key_id, "key mismatch"));
         }
-        let data = cbor!({
-            "did" => self.did.clone(),
-            "key_id" => self.key_id.clone(),
-            "created" => _created.timestamp(),
-            "purpose" => self.purpose.clone(),
-            "payload" => payload.clone(),
-        })
-        .map_err(|_| CommonError::EncodeError)?;
-        let data_bytes = idp2p_common::cbor::encode(&data);
-
-        match kid.codec() {
-            ED_CODE => {
-                if let Err(_e) = ed25519::verify(&signer.public_key, &data_bytes, &self.signature) {
-                    return Err(IdEventError::invalid_proof(
-                        &self.key_id,
-                        "invalid signature",
-                    ));
-                }
+        let (data, signature) = match self.format()? {
+            IdProofFormat::Idp2p => (
+                Self::signing_input(&self.did, &self.key_id, &_created, purpose, payload)?,
+                self.signature.clone(),
+            ),
+            IdProofFormat::CoseSign1 => {
+                self.cose_signed_data(&_created, purpose, kid.codec(), payload)?
             }
-            _ => {
-                return Err(IdEventError::InvalidSigner(
-                    "Unsupported key type".to_string(),
-                ));
+            IdProofFormat::Jws => self.jws_signed_data(&_created, purpose, kid.codec(), payload)?,
+            // The payload is the Data Integrity hash data
+            IdProofFormat::EddsaJcs2022 => {
+                if kid.codec() != ED_CODE {
+                    return Err(IdEventError::invalid_proof(&self.key_id, "unsupported algorithm"));
+                }
+                (payload.to_vec(), self.signature.clone())
             }
-        }
-        Ok(())
+        };
+
+        Ok(PendingSignature {
+            key_id: self.key_id.clone(),
+            code: kid.codec(),
+            public_key: signer.public_key.clone(),
+            data,
+            signature,
+        })
     }
 }
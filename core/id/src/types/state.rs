@@ -1,7 +1,14 @@
-use idp2p_common::bytes::Bytes;
+use alloc::collections::BTreeSet;
+use cid::Cid;
+use idp2p_common::{CBOR_CODE, SHA2_256_CODE, bytes::Bytes, cid::CidExt, error::CommonError};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
+use crate::{
+    internal::{error::IdEventError, signer::IdSigner as InternalSigner},
+    types::{IdProof, verify_signatures},
+};
+
 #[serde_as]
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct IdSigner {
@@ -9,6 +16,9 @@ pub struct IdSigner {
     /// Public key of the signer.
     #[serde_as(as = "Bytes")]
     pub public_key: Vec<u8>,
+    /// Purposes the key may be used for, event signing when missing.
+    #[serde(default = "default_purposes")]
+    pub purposes: Vec<String>,
     /// Created at sn.
     pub valid_from_sn: u64,
     /// Revoked sn.
@@ -21,11 +31,21 @@ pub struct IdSigner {
     pub valid_until: Option<String>,
 }
 
+fn default_purposes() -> Vec<String> {
+    crate::internal::signer::default_purposes()
+        .iter()
+        .map(|p| p.as_ref().to_owned())
+        .collect()
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct IdState {
     /// Identifier
     pub id: String,
 
+    /// Last event number
+    pub sn: u64,
+
     /// Last event id
     pub event_id: String,
 
@@ -46,12 +66,9 @@ pub struct IdState {
     // Next threshold
     pub next_threshold: u8,
 
-    /// Signers
+    /// Current signers, retired ones are kept in `IdSignerHistory`
     pub signers: Vec<IdSigner>,
 
-    /// Current signers
-    pub current_signers: Vec<String>,
-
     /// CID codec should be 0xed
     pub next_signers: Vec<String>,
 
@@ -67,3 +84,201 @@ pub struct IdState {
     pub revoked_at: Option<String>,
 }
 
+
+/// All signers an identity has used, kept apart from the compact state.
+///
+/// Verification only needs `IdState`; the history is recorded from each
+/// verified state and is needed to check proofs made by retired signers.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct IdSignerHistory {
+    /// Identifier
+    pub id: String,
+
+    /// Current and retired signers
+    pub signers: Vec<IdSigner>,
+}
+
+impl IdSignerHistory {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_owned(),
+            signers: vec![],
+        }
+    }
+
+    /// Records a verified state, retiring signers that are no longer current.
+    pub fn record(&mut self, state: &IdState) -> Result<(), IdEventError> {
+        if state.id != self.id {
+            return Err(IdEventError::IdNotMatch(state.id.clone()));
+        }
+        let is_current = |signer: &IdSigner| {
+            state
+                .signers
+                .iter()
+                .any(|s| s.id == signer.id && s.valid_from_sn == signer.valid_from_sn)
+        };
+        for signer in self.signers.iter_mut() {
+            if signer.valid_until_sn.is_none() && !is_current(signer) {
+                signer.valid_until_sn = Some(state.sn);
+                signer.valid_until = Some(state.event_timestamp.clone());
+            }
+        }
+        for signer in state.signers.iter() {
+            let known = self
+                .signers
+                .iter()
+                .any(|s| s.id == signer.id && s.valid_from_sn == signer.valid_from_sn);
+            if !known {
+                self.signers.push(signer.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the signer that was active at the given event number.
+    pub fn signer_at(&self, kid: &str, sn: u64) -> Option<&IdSigner> {
+        self.signers.iter().find(|s| {
+            s.id == kid && s.valid_from_sn <= sn && s.valid_until_sn.is_none_or(|until| sn < until)
+        })
+    }
+}
+
+/// Verified state together with its digest.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct IdVerifiedState {
+    pub state: IdState,
+
+    /// CID of the canonical state encoding
+    pub state_cid: String,
+}
+
+impl TryFrom<IdState> for IdVerifiedState {
+    type Error = IdEventError;
+
+    fn try_from(state: IdState) -> Result<Self, Self::Error> {
+        let state_cid = state.state_cid()?.to_string();
+        Ok(Self { state, state_cid })
+    }
+}
+
+impl IdState {
+    /// Canonical encoding of the state.
+    ///
+    /// Lists are sorted so that two nodes holding the same state produce
+    /// the same bytes regardless of the order they received signers in.
+    pub fn to_canonical_bytes(&self) -> Result<Vec<u8>, CommonError> {
+        let mut state = self.clone();
+        state
+            .signers
+            .sort_by(|a, b| (&a.id, a.valid_from_sn).cmp(&(&b.id, b.valid_from_sn)));
+        state.next_signers.sort();
+        state.delegated_signers.sort();
+        idp2p_common::cbor::encode(&state)
+    }
+
+    /// Verifies a proof made by a current signer of this identity.
+    ///
+    /// Lets proofs from outside receipts, e.g. converted from JWS or Data
+    /// Integrity proofs, be checked against the resolved state.
+    pub fn verify_proof(&self, proof: &IdProof, payload: &[u8]) -> Result<(), IdEventError> {
+        proof.verify(payload, &self.proof_signers(proof)?)
+    }
+
+    /// Verifies an `eddsa-jcs-2022` proof over its Data Integrity hash data.
+    pub(crate) fn verify_data_integrity(
+        &self,
+        proof: &IdProof,
+        hash_data: &[u8],
+    ) -> Result<(), IdEventError> {
+        let pending = proof.check_signed(hash_data, &self.proof_signers(proof)?)?;
+        verify_signatures(&[pending])
+    }
+
+    fn proof_signers(&self, proof: &IdProof) -> Result<BTreeSet<InternalSigner>, IdEventError> {
+        if proof.did != self.id {
+            return Err(IdEventError::IdNotMatch(proof.did.clone()));
+        }
+        self.signers.iter().map(InternalSigner::try_from).collect()
+    }
+
+    /// Digest of the canonical state encoding.
+    ///
+    /// Peers compare it to find out whether they hold the same head, so it
+    /// is always hashed with SHA2-256.
+    pub fn state_cid(&self) -> Result<Cid, IdEventError> {
+        let bytes = self.to_canonical_bytes()?;
+        Ok(Cid::create_with(CBOR_CODE, SHA2_256_CODE, &bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer(id: &str, sn: u64) -> IdSigner {
+        IdSigner {
+            id: id.into(),
+            public_key: vec![sn as u8; 32],
+            purposes: vec!["event-signing".into()],
+            valid_from_sn: sn,
+            valid_until_sn: None,
+            valid_from: format!("2025-01-01T00:00:0{sn}Z"),
+            valid_until: None,
+        }
+    }
+
+    fn state(sn: u64, signers: Vec<IdSigner>) -> IdState {
+        IdState {
+            id: "id".into(),
+            sn,
+            event_id: format!("event-{sn}"),
+            event_timestamp: format!("2025-01-01T00:00:0{sn}Z"),
+            prior_id: None,
+            next_id_proof: None,
+            threshold: 1,
+            next_threshold: 1,
+            signers,
+            next_signers: vec![],
+            delegated_signers: vec![],
+            merkle_proof: "merkle-proof".into(),
+            revoked: false,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn test_history_retires_rotated_signers() {
+        let mut history = IdSignerHistory::new("id");
+        history.record(&state(0, vec![signer("a", 0)])).unwrap();
+        history.record(&state(1, vec![signer("a", 0)])).unwrap();
+        history
+            .record(&state(2, vec![signer("a", 2), signer("b", 2)]))
+            .unwrap();
+
+        assert_eq!(history.signers.len(), 3);
+        let retired = history.signer_at("a", 1).expect("retired signer");
+        assert_eq!(retired.valid_from_sn, 0);
+        assert_eq!(retired.valid_until_sn, Some(2));
+        assert_eq!(retired.valid_until.as_deref(), Some("2025-01-01T00:00:02Z"));
+        assert_eq!(history.signer_at("a", 2).unwrap().valid_from_sn, 2);
+        assert!(history.signer_at("b", 1).is_none());
+    }
+
+    #[test]
+    fn test_state_cid_ignores_list_order() {
+        let mut a = state(2, vec![signer("a", 2), signer("b", 2)]);
+        a.next_signers = vec!["x".into(), "y".into()];
+        let mut b = state(2, vec![signer("b", 2), signer("a", 2)]);
+        b.next_signers = vec!["y".into(), "x".into()];
+        assert_eq!(a.state_cid().unwrap(), b.state_cid().unwrap());
+
+        b.threshold = 2;
+        assert_ne!(a.state_cid().unwrap(), b.state_cid().unwrap());
+    }
+
+    #[test]
+    fn test_history_rejects_other_id() {
+        let mut history = IdSignerHistory::new("other");
+        assert!(history.record(&state(0, vec![signer("a", 0)])).is_err());
+    }
+}
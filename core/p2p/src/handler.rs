@@ -1,12 +1,31 @@
-use idp2p_id::types::Idp2pError;
+use idp2p_common::{
+    cbor::{self, DecodeLimits},
+    error::CommonError,
+};
+use idp2p_id::{internal::error::IdEventError, types::Idp2pError};
 
 use crate::{
-    idp2p::core::{id_verifier, p2p_sender, store},
-    model::{IdEntry, Wasmsg, WasmsgValue::*},
+    host::Host,
+    model::{IdEntry, Wasmsg, WasmsgValue::*, store_share},
 };
 
-pub fn handle(message: Vec<u8>) -> Result<(), Idp2pError> {
-    let message: Wasmsg = serde_json::from_slice(&message).unwrap();
+/// Decodes a gossip message within `DecodeLimits`, it comes from any peer.
+///
+/// Messages are CBOR since protocol version 2. Version 1 peers send JSON,
+/// which starts with `{` where a CBOR map never does, so both are read.
+fn decode(message: &[u8]) -> Result<Wasmsg, IdEventError> {
+    let limits = DecodeLimits::default();
+    if message.first() == Some(&b'{') {
+        if message.len() > limits.max_size {
+            return Err(CommonError::PayloadTooLarge(message.len()).into());
+        }
+        return Ok(serde_json::from_slice(message)?);
+    }
+    Ok(cbor::decode_with_limits(message, &limits)?)
+}
+
+pub fn handle(host: &mut impl Host, message: &[u8]) -> Result<(), Idp2pError> {
+    let message = decode(message)?;
     match message.value {
         IdPing {
             from_id,
@@ -18,11 +37,42 @@ pub fn handle(message: Vec<u8>) -> Result<(), Idp2pError> {
         } => {}
         IdPong {
             from_id,
+            from_state_cid,
             from_inception,
             from_events,
-            to_id,
-        } => {}
-        IdNotifyEvent(id_event_receipt) => {}
+            ..
+        } => {
+            // Heads are compared by digest, the log is only resolved when they differ
+            let stored = IdEntry::load(host, &from_id)?;
+            if let Some(entry) = &stored {
+                if entry.state.state_cid()?.to_string() == from_state_cid {
+                    return Ok(());
+                }
+            }
+            // Keep the resolved state together with its signer history
+            if from_inception.id != from_id {
+                return Ok(());
+            }
+            let providing = stored.as_ref().is_some_and(|entry| entry.providing);
+            let entry = IdEntry::resolve(host, from_inception, from_events, providing)?;
+            entry.save(host)?;
+            if let Some(stored) = stored {
+                stored.unpin(host)?;
+            }
+        }
+        IdNotifyEvent(receipt) => {
+            // Proofs name the identity, `apply` checks them against its state
+            let Some(did) = receipt.proofs.first().map(|proof| proof.did.clone()) else {
+                return Ok(());
+            };
+            if let Some(mut entry) = IdEntry::load(host, &did)? {
+                if !entry.contains(&receipt)? {
+                    entry.apply(host, receipt)?;
+                    entry.save(host)?;
+                }
+            }
+        }
+        IdRecoveryShare(share) => store_share(host, share)?,
         IdNotifyMessage {} => {},
     }
     Ok(())
@@ -57,3 +107,267 @@ Resolved {
     }
 }
 Notified(id_event_receipt) => {}*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::{BTreeMap, BTreeSet};
+    use crate::{
+        host::HostBlockStore,
+        model::{WasmsgValue, load_shares, recovery_key},
+    };
+    use chrono::{DateTime, Utc};
+    use cid::Cid;
+    use idp2p_common::{
+        CBOR_CODE, ED_CODE, blockstore::BlockStore, cid::CidExt, crypto::signer::Signer,
+    };
+    use idp2p_id::{
+        internal::{
+            event::{IdEvent, IdEventKind},
+            inception::IdInception,
+            proof::IdProofPurpose,
+            recovery::IdRecoveryShare,
+            signer::{IdSigner, default_purposes},
+        },
+        types::{IdEventReceipt, IdProof, IdSignerHistory, IdState, IdVerifiedState},
+    };
+
+    /// Host verifying natively, with an in memory store.
+    #[derive(Default)]
+    struct MemoryHost {
+        values: BTreeMap<String, Vec<u8>>,
+    }
+
+    impl Host for MemoryHost {
+        fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Idp2pError> {
+            Ok(self.values.get(key).cloned())
+        }
+
+        fn put(&mut self, key: &str, value: &[u8]) -> Result<(), Idp2pError> {
+            self.values.insert(key.into(), value.to_vec());
+            Ok(())
+        }
+
+        fn verify_event(
+            &self,
+            state: &IdState,
+            event: &IdEventReceipt,
+        ) -> Result<IdVerifiedState, Idp2pError> {
+            Ok(event.verify_event(&mut state.clone())?.try_into()?)
+        }
+
+        fn verify_log_with_history(
+            &self,
+            inception: &IdEventReceipt,
+            events: &[IdEventReceipt],
+        ) -> Result<(IdVerifiedState, IdSignerHistory), Idp2pError> {
+            let (state, history) = inception.verify_log_with_history(events)?;
+            Ok((state.try_into()?, history))
+        }
+    }
+
+    fn signer() -> Signer {
+        Signer::new(ED_CODE, &[7u8; 32]).unwrap()
+    }
+
+    fn timestamp() -> i64 {
+        let valid_from: DateTime<Utc> = "2025-01-01T00:00:01Z".parse().unwrap();
+        valid_from.timestamp()
+    }
+
+    fn receipt(did: Option<&str>, payload: Vec<u8>) -> IdEventReceipt {
+        let id = Cid::create(CBOR_CODE, &payload).unwrap().to_string();
+        let did = did.unwrap_or(&id);
+        let proof =
+            IdProof::create(did, &signer(), &Utc::now(), IdProofPurpose::EventSigning, &payload)
+                .unwrap();
+        IdEventReceipt {
+            id,
+            version: "1.0".into(),
+            created_at: Utc::now().to_rfc3339(),
+            payload,
+            proofs: vec![proof],
+        }
+    }
+
+    fn inception() -> IdEventReceipt {
+        let signer = signer();
+        let next = Signer::new(ED_CODE, &[8u8; 32]).unwrap();
+        let inception = IdInception {
+            version: "1.0".into(),
+            patch: Cid::default(),
+            timestamp: timestamp(),
+            prior_id: None,
+            threshold: 1,
+            next_threshold: 1,
+            signers: BTreeSet::from([IdSigner {
+                id: signer.id.clone(),
+                public_key: signer.public_key.clone(),
+                purposes: default_purposes(),
+            }]),
+            next_signers: BTreeSet::from([next.id]),
+            delegated_signers: BTreeSet::new(),
+            merkle_proof: "inception-proof".into(),
+        };
+        receipt(None, cbor::encode(&inception).unwrap())
+    }
+
+    fn interaction(state: &IdState) -> IdEventReceipt {
+        let event = IdEvent {
+            sn: state.sn + 1,
+            version: "1.0".into(),
+            patch: Cid::default(),
+            timestamp: timestamp(),
+            previous: state.event_id.clone(),
+            body: IdEventKind::Interaction {
+                merkle_proof: "interaction-proof".into(),
+            },
+        };
+        receipt(Some(&state.id), cbor::encode(&event).unwrap())
+    }
+
+    fn pong(
+        state: &IdState,
+        inception: IdEventReceipt,
+        events: BTreeSet<IdEventReceipt>,
+    ) -> Vec<u8> {
+        let value = IdPong {
+            from_id: state.id.clone(),
+            from_state_cid: state.state_cid().unwrap().to_string(),
+            from_inception: inception,
+            from_events: events,
+            to_id: "to".into(),
+        };
+        let message = Wasmsg {
+            protocol: "idp2p".into(),
+            version: "2".into(),
+            r#type: "id-pong".into(),
+            value,
+        };
+        cbor::encode(&message).unwrap()
+    }
+
+    #[test]
+    fn pong_same_head_test() {
+        let mut host = MemoryHost::default();
+        let inception = inception();
+        let entry = IdEntry::resolve(&mut host, inception.clone(), BTreeSet::new(), true).unwrap();
+        entry.save(&mut host).unwrap();
+        let stored = host.values.clone();
+
+        // The log is not resolved again, a broken one would fail otherwise
+        let mut broken = inception;
+        broken.payload.clear();
+        handle(&mut host, &pong(&entry.state, broken, BTreeSet::new())).unwrap();
+        assert_eq!(host.values, stored);
+    }
+
+    #[test]
+    fn pong_new_head_test() {
+        let mut host = MemoryHost::default();
+        let inception = inception();
+        let state = inception.verify_inception().unwrap();
+        handle(&mut host, &pong(&state, inception.clone(), BTreeSet::new())).unwrap();
+        let entry = IdEntry::load(&host, &state.id).unwrap().unwrap();
+        assert_eq!(entry.state, state);
+
+        // A peer a step ahead replaces the stored head, keeping `providing`
+        let mut entry = entry;
+        entry.providing = true;
+        entry.save(&mut host).unwrap();
+        let event = interaction(&state);
+        let next = event.verify_event(&mut state.clone()).unwrap();
+        let events = BTreeSet::from([event]);
+        handle(&mut host, &pong(&next, inception.clone(), events)).unwrap();
+        let entry = IdEntry::load(&host, &state.id).unwrap().unwrap();
+        assert_eq!(entry.state, next);
+        assert_eq!(entry.history.signers.len(), 1);
+        assert!(entry.providing);
+
+        // Receipts are blocks, the replaced entry's pins are dropped
+        let store = HostBlockStore(&mut host);
+        let cid = Cid::try_from(entry.inception.as_str()).unwrap();
+        assert_eq!(IdEventReceipt::load(&store, &cid).unwrap(), inception);
+        assert_eq!(store.refs(&cid).unwrap(), 1);
+        let cid = Cid::try_from(entry.events[0].as_str()).unwrap();
+        assert_eq!(IdEventReceipt::load(&store, &cid).unwrap().id, next.event_id);
+    }
+
+    #[test]
+    fn notify_event_test() {
+        let mut host = MemoryHost::default();
+        let inception = inception();
+        let entry = IdEntry::resolve(&mut host, inception, BTreeSet::new(), false).unwrap();
+        entry.save(&mut host).unwrap();
+        let event = interaction(&entry.state);
+        let notify = |event| {
+            let message = Wasmsg {
+                protocol: "idp2p".into(),
+                version: "2".into(),
+                r#type: "id-notify-event".into(),
+                value: IdNotifyEvent(event),
+            };
+            cbor::encode(&message).unwrap()
+        };
+        handle(&mut host, &notify(event.clone())).unwrap();
+        let applied = IdEntry::load(&host, &entry.id).unwrap().unwrap();
+        assert_eq!(applied.state.sn, 1);
+        assert_eq!(applied.events.len(), 1);
+
+        // A repeated notification changes nothing
+        handle(&mut host, &notify(event)).unwrap();
+        let again = IdEntry::load(&host, &entry.id).unwrap().unwrap();
+        assert_eq!(again.events, applied.events);
+    }
+
+    #[test]
+    fn recovery_share_test() {
+        let mut host = MemoryHost::default();
+        let share = |guardian: &str| IdRecoveryShare {
+            owner: "owner".into(),
+            guardian: guardian.into(),
+            recipient: "z6LSrecovery".into(),
+            sealed: vec![1, 2, 3],
+        };
+        let message = |share| {
+            let message = Wasmsg {
+                protocol: "idp2p".into(),
+                version: "2".into(),
+                r#type: "id-recovery-share".into(),
+                value: WasmsgValue::IdRecoveryShare(share),
+            };
+            cbor::encode(&message).unwrap()
+        };
+        handle(&mut host, &message(share("alice"))).unwrap();
+        handle(&mut host, &message(share("bob"))).unwrap();
+        handle(&mut host, &message(share("alice"))).unwrap();
+
+        // Returned shares wait together for the owner's `reconstruct`
+        let key = recovery_key("z6LSrecovery", "owner");
+        assert_eq!(load_shares(&host, &key).unwrap(), vec![share("alice"), share("bob")]);
+    }
+
+    fn message() -> Wasmsg {
+        Wasmsg {
+            protocol: "idp2p".into(),
+            version: "2".into(),
+            r#type: "id-notify-message".into(),
+            value: IdNotifyMessage {},
+        }
+    }
+
+    #[test]
+    fn decode_versions_test() {
+        let cbor = cbor::encode(&message()).unwrap();
+        assert_eq!(decode(&cbor).unwrap(), message());
+        // Version 1 peers gossip JSON
+        let json = serde_json::to_vec(&message()).unwrap();
+        assert_eq!(decode(&json).unwrap(), message());
+        let mut large = json.clone();
+        large.resize(DecodeLimits::default().max_size + 1, b' ');
+        assert!(matches!(
+            decode(&large),
+            Err(IdEventError::CommonError(CommonError::PayloadTooLarge(_)))
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+use alloc::{string::ToString, vec::Vec};
+use cid::Cid;
+use idp2p_common::{blockstore::BlockStore, error::CommonError};
+use idp2p_id::types::{IdEventReceipt, IdSignerHistory, IdState, IdVerifiedState, Idp2pError};
+
+use crate::idp2p::core::{id_verifier, store};
+
+/// Imports of the message handler world.
+///
+/// The component runs against `WitHost`, tests against an in memory host.
+pub trait Host {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Idp2pError>;
+    fn put(&mut self, key: &str, value: &[u8]) -> Result<(), Idp2pError>;
+    fn verify_event(
+        &self,
+        state: &IdState,
+        event: &IdEventReceipt,
+    ) -> Result<IdVerifiedState, Idp2pError>;
+    fn verify_log_with_history(
+        &self,
+        inception: &IdEventReceipt,
+        events: &[IdEventReceipt],
+    ) -> Result<(IdVerifiedState, IdSignerHistory), Idp2pError>;
+}
+
+pub struct WitHost;
+
+impl Host for WitHost {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Idp2pError> {
+        store::get(key)
+    }
+
+    fn put(&mut self, key: &str, value: &[u8]) -> Result<(), Idp2pError> {
+        store::put(key, value)
+    }
+
+    fn verify_event(
+        &self,
+        state: &IdState,
+        event: &IdEventReceipt,
+    ) -> Result<IdVerifiedState, Idp2pError> {
+        id_verifier::verify_event(state, event)
+    }
+
+    fn verify_log_with_history(
+        &self,
+        inception: &IdEventReceipt,
+        events: &[IdEventReceipt],
+    ) -> Result<(IdVerifiedState, IdSignerHistory), Idp2pError> {
+        id_verifier::verify_log_with_history(inception, events)
+    }
+}
+
+/// `BlockStore` over the key value store of a host.
+///
+/// Blocks are kept under `/blocks/{cid}` and pin counts under `/refs/{cid}`.
+/// The store can neither list nor remove keys, so a deleted block is an
+/// empty value and collecting unpinned blocks is left to the host.
+pub struct HostBlockStore<'a, H: Host>(pub &'a mut H);
+
+fn storage_error(e: Idp2pError) -> CommonError {
+    CommonError::StorageError(e.message)
+}
+
+impl<H: Host> BlockStore for HostBlockStore<'_, H> {
+    fn read(&self, cid: &Cid) -> Result<Option<Vec<u8>>, CommonError> {
+        let bytes = self.0.get(&format!("/blocks/{cid}")).map_err(storage_error)?;
+        Ok(bytes.filter(|bytes| !bytes.is_empty()))
+    }
+
+    fn write(&mut self, cid: &Cid, bytes: &[u8]) -> Result<(), CommonError> {
+        self.0.put(&format!("/blocks/{cid}"), bytes).map_err(storage_error)
+    }
+
+    fn delete(&mut self, cid: &Cid) -> Result<(), CommonError> {
+        self.write(cid, &[])?;
+        self.set_refs(cid, 0)
+    }
+
+    fn cids(&self) -> Result<Vec<Cid>, CommonError> {
+        Ok(vec![])
+    }
+
+    fn refs(&self, cid: &Cid) -> Result<u64, CommonError> {
+        let refs = self.0.get(&format!("/refs/{cid}")).map_err(storage_error)?;
+        match refs {
+            Some(bytes) => Ok(u64::from_be_bytes(
+                bytes
+                    .try_into()
+                    .map_err(|_| CommonError::StorageError(cid.to_string()))?,
+            )),
+            None => Ok(0),
+        }
+    }
+
+    fn set_refs(&mut self, cid: &Cid, refs: u64) -> Result<(), CommonError> {
+        self.0
+            .put(&format!("/refs/{cid}"), &refs.to_be_bytes())
+            .map_err(storage_error)
+    }
+}
@@ -3,6 +3,7 @@ use alloc::vec::Vec;
 extern crate alloc;
 
 mod error;
+mod host;
 mod model;
 mod handler;
 
@@ -21,6 +22,6 @@ export!(GuestComponent);
 
 impl Guest for GuestComponent {
     fn handle(message: Vec<u8>) -> Result<(), Idp2pError> {
-        handler::handle(message)
+        handler::handle(&mut host::WitHost, &message)
     }
 }
@@ -1,14 +1,144 @@
-use alloc::collections::BTreeSet;
-use idp2p_id::types::{IdEventReceipt, IdState};
+use alloc::{collections::BTreeSet, string::String, vec::Vec};
+use cid::Cid;
+use core::str::FromStr;
+use idp2p_common::{CBOR_CODE, blockstore::BlockStore, cbor, cid::CidExt};
+use idp2p_id::{
+    internal::{error::IdEventError, recovery::IdRecoveryShare},
+    types::{IdEventReceipt, IdSignerHistory, IdState, Idp2pError},
+};
 use serde::{Deserialize, Serialize};
 
+use crate::host::{Host, HostBlockStore};
+
+/// A resolved identity.
+///
+/// Receipts are persisted with `IdEventReceipt::persist` through the host
+/// `BlockStore`, the entry pins them and keeps their block CIDs.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct IdEntry {
     pub id: String,
     pub providing: bool,
     pub state: IdState,
-    pub inception: IdEventReceipt,
-    pub events: BTreeSet<IdEventReceipt>,
+    pub history: IdSignerHistory,
+    /// Block CID of the inception receipt
+    pub inception: String,
+    /// Block CIDs of the event receipts, in sequence number order
+    pub events: Vec<String>,
+}
+
+/// Block CID a receipt is persisted under.
+fn receipt_cid(receipt: &IdEventReceipt) -> Result<Cid, IdEventError> {
+    Ok(Cid::create(CBOR_CODE, &cbor::encode(receipt)?)?)
+}
+
+impl IdEntry {
+    /// Verifies a resolved log and keeps the signer history it produces.
+    pub fn resolve(
+        host: &mut impl Host,
+        inception: IdEventReceipt,
+        events: BTreeSet<IdEventReceipt>,
+        providing: bool,
+    ) -> Result<Self, Idp2pError> {
+        // Receipts are ordered by id, the log by sequence number
+        let mut log = events
+            .into_iter()
+            .map(|event| Ok((event.sn()?, event)))
+            .collect::<Result<Vec<_>, IdEventError>>()?;
+        log.sort_by_key(|(sn, _)| *sn);
+        let log: Vec<IdEventReceipt> = log.into_iter().map(|(_, event)| event).collect();
+        let (verified, history) = host.verify_log_with_history(&inception, &log)?;
+        let mut store = HostBlockStore(host);
+        let inception = inception.persist(&mut store)?.to_string();
+        let events = log
+            .iter()
+            .map(|event| Ok(event.persist(&mut store)?.to_string()))
+            .collect::<Result<_, IdEventError>>()?;
+        Ok(Self {
+            id: verified.state.id.clone(),
+            providing,
+            state: verified.state,
+            history,
+            inception,
+            events,
+        })
+    }
+
+    /// Entry of an identity kept in the store, if any.
+    pub fn load(host: &impl Host, id: &str) -> Result<Option<Self>, Idp2pError> {
+        match host.get(id)? {
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes).map_err(IdEventError::from)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save(&self, host: &mut impl Host) -> Result<(), Idp2pError> {
+        let bytes = serde_json::to_vec(self).map_err(IdEventError::from)?;
+        host.put(&self.id, &bytes)
+    }
+
+    /// Drops the pins of the entry's receipts, e.g. once it is replaced.
+    pub fn unpin(&self, host: &mut impl Host) -> Result<(), Idp2pError> {
+        let mut store = HostBlockStore(host);
+        for cid in core::iter::once(&self.inception).chain(&self.events) {
+            let cid = Cid::from_str(cid).map_err(IdEventError::from)?;
+            store.unpin(&cid).map_err(IdEventError::from)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the receipt is already part of the entry.
+    pub fn contains(&self, receipt: &IdEventReceipt) -> Result<bool, Idp2pError> {
+        let cid = receipt_cid(receipt)?.to_string();
+        Ok(self.inception == cid || self.events.contains(&cid))
+    }
+
+    /// Applies a notified event, recording the new state in the history.
+    pub fn apply(&mut self, host: &mut impl Host, event: IdEventReceipt) -> Result<(), Idp2pError> {
+        let verified = host.verify_event(&self.state, &event)?;
+        self.history.record(&verified.state)?;
+        self.state = verified.state;
+        let cid = event.persist(&mut HostBlockStore(host))?;
+        self.events.push(cid.to_string());
+        Ok(())
+    }
+}
+
+/// Most shares kept for a recipient and owner, one per share index.
+const MAX_RECOVERY_SHARES: usize = u8::MAX as usize;
+
+/// Store key of the shares sealed to `recipient` for `owner`.
+///
+/// A guardian finds the shares it holds under its key agreement signer id,
+/// the owner the returned ones under the `recovery_recipient` of its
+/// recovery key, ready for `reconstruct`.
+pub fn recovery_key(recipient: &str, owner: &str) -> String {
+    format!("/recovery/{recipient}/{owner}")
+}
+
+/// Shares received for a recipient and owner.
+pub fn load_shares(host: &impl Host, key: &str) -> Result<Vec<IdRecoveryShare>, Idp2pError> {
+    match host.get(key)? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes).map_err(IdEventError::from)?),
+        None => Ok(vec![]),
+    }
+}
+
+/// Keeps a received share until its recipient opens it.
+///
+/// Only the recipient can open a share, so forged ones are kept too and
+/// dropped later by `open` or `reconstruct`.
+pub fn store_share(host: &mut impl Host, share: IdRecoveryShare) -> Result<(), Idp2pError> {
+    let key = recovery_key(&share.recipient, &share.owner);
+    let mut shares = load_shares(host, &key)?;
+    if shares.contains(&share) {
+        return Ok(());
+    }
+    if shares.len() >= MAX_RECOVERY_SHARES {
+        return Err(IdEventError::RecoveryFailed("too many shares".into()).into());
+    }
+    shares.push(share);
+    let bytes = serde_json::to_vec(&shares).map_err(IdEventError::from)?;
+    host.put(&key, &bytes)
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -31,11 +161,15 @@ pub enum WasmsgValue {
     },
     IdPong {
         from_id: String,
+        /// Lets the pinging node skip the log when it already holds this head
+        from_state_cid: String,
         from_inception: IdEventReceipt,
         from_events: BTreeSet<IdEventReceipt>,
         to_id: String,
     },
     IdNotifyEvent(IdEventReceipt),
+    /// Recovery share sent to a guardian or returned to its owner
+    IdRecoveryShare(IdRecoveryShare),
     IdNotifyMessage {
         
     }
@@ -132,7 +132,7 @@ impl<S: IdStore, V: IdVerifier> IdNetworkEventLoop<S, V> {
                     .behaviour_mut()
                     .gossipsub
                     .subscribe(&ident_topic)?;
-                let data = cbor::encode(&payload);
+                let data = cbor::encode(&payload)?;
                 self.swarm.behaviour_mut().gossipsub.publish(topic, data)?;
             }
             Subscribe(topic) => {
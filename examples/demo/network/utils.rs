@@ -32,7 +32,7 @@ pub fn generate_actor(version: &str, peer: &PeerId) -> Result<PersistedIdIncepti
         next_signers: vec![create_signer().id],
         claims: vec![peer_claim],
     };
-    let inception_bytes = cbor::encode(&inception);
+    let inception_bytes = cbor::encode(&inception)?;
     let id = Id::new("id", CBOR_CODE, inception_bytes.as_slice()).unwrap();
     let pinception = PersistedIdInception {
         id: id.to_string(),